@@ -0,0 +1,29 @@
+//! GPU time of the compute passes of a 256² cloth with the vertex buffer in
+//! full and in half precision. The solver passes mostly stream the vertex
+//! buffer, so the ratio of the two times is close to the bandwidth saving of
+//! `Precision::F16` on this device. Needs `Features::TIMESTAMP_QUERY`. Run
+//! with `cargo bench --bench bandwidth`.
+
+mod common;
+
+use cloth_sim::instances_app::{ClothBuilder, Precision};
+
+const GRID_SIZE: u32 = 256;
+
+fn main() {
+    let Some(context) = common::headless_context() else {
+        return;
+    };
+    let vertices = (GRID_SIZE * GRID_SIZE) as u64;
+    let mut times = Vec::new();
+    for precision in [Precision::F32, Precision::F16] {
+        let buffer_mib = (vertices * precision.vertex_size()) as f64 / (1024.0 * 1024.0);
+        let Some(ms) = common::compute_ms(&context, ClothBuilder::new().grid_size(GRID_SIZE).precision(precision)) else {
+            eprintln!("skipped: no timestamp queries on this adapter");
+            return;
+        };
+        println!("{:?}: {:.1} MiB vertex buffer, {:.3} ms of compute per frame", precision, buffer_mib, ms);
+        times.push(ms);
+    }
+    println!("F16 compute time: {:.0}% of F32", 100.0 * times[1] / times[0]);
+}
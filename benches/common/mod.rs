@@ -0,0 +1,38 @@
+use cloth_sim::gpu_context::{GpuContext, HeadlessContext};
+use cloth_sim::instances_app::ClothBuilder;
+use wgpu_bootstrap::wgpu;
+
+// Frames run before timing, to build the pipelines and fill the caches
+const WARMUP_FRAMES: u32 = 30;
+const TIMED_FRAMES: u32 = 300;
+
+/// A headless device for the benchmark, `None` (and a note on stderr) on a
+/// machine without a GPU adapter.
+pub fn headless_context() -> Option<HeadlessContext> {
+    let context = HeadlessContext::new(800, 600);
+    if context.is_none() {
+        eprintln!("skipped: no GPU adapter");
+    }
+    context
+}
+
+/// Average GPU time of the physics compute passes of a frame of `builder`'s
+/// cloth, in milliseconds, from the timestamp queries of
+/// `InstanceApp::last_compute_ms`. The render passes and the CPU side of the
+/// frame are not included. `None` when the device has no timestamp queries.
+pub fn compute_ms(context: &HeadlessContext, builder: ClothBuilder) -> Option<f64> {
+    let mut app = builder.try_build(context).unwrap();
+    app.set_profiling(true);
+    for _ in 0..WARMUP_FRAMES {
+        app.advance(1.0 / 60.0, context);
+    }
+    // Waiting after every frame lets the profiler map its results in time, so
+    // each frame is profiled and the next `advance` collects it
+    let mut total_ms = 0.0;
+    for _ in 0..TIMED_FRAMES {
+        context.device().poll(wgpu::Maintain::Wait);
+        app.advance(1.0 / 60.0, context);
+        total_ms += app.last_compute_ms()? as f64;
+    }
+    Some(total_ms / TIMED_FRAMES as f64)
+}
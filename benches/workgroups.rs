@@ -0,0 +1,28 @@
+//! GPU time of the compute passes with each `WorkgroupLayout`, on 100² and
+//! 200² cloths. `Linear` is the 1D dispatch of 256 threads along the vertex
+//! index, the tiled layouts cover the grid with 8x8 or 16x16 workgroups.
+//! Needs `Features::TIMESTAMP_QUERY`. Run with
+//! `cargo bench --bench workgroups`.
+
+mod common;
+
+use cloth_sim::instances_app::{ClothBuilder, WorkgroupLayout};
+
+const GRID_SIZES: [u32; 2] = [100, 200];
+
+fn main() {
+    let Some(context) = common::headless_context() else {
+        return;
+    };
+    for grid_size in GRID_SIZES {
+        let mut linear_ms = None;
+        for (name, layout) in WorkgroupLayout::NAMES {
+            let Some(ms) = common::compute_ms(&context, ClothBuilder::new().grid_size(grid_size).workgroup_layout(layout)) else {
+                eprintln!("skipped: no timestamp queries on this adapter");
+                return;
+            };
+            let linear_ms = *linear_ms.get_or_insert(ms);
+            println!("{}²  {:>6}: {:.3} ms of compute per frame, {:.0}% of linear", grid_size, name, ms, 100.0 * ms / linear_ms);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+
+use crate::env_config::EnvError;
+
+/// Options `ClothBuilder::from_args` understands, printed on a usage error.
+pub const USAGE: &str = "usage: cloth_sim [options]
+
+  --grid N                  vertices per side of the cloth, 2..=512 (100)
+  --stiffness S[,SHEAR,BEND] spring stiffness, 0..=200 (25,15,5)
+  --gravity G               gravity magnitude, 0..=20 (6.8)
+  --time-step DT            simulated seconds per frame, 0.0001..=0.01 (0.0016)
+  --iterations N            constraint projection iterations, 0..=16 (0)
+  --relax-frames N          frames run without gravity at startup (0)
+  --precision f32|f16       vertex storage precision (f32)
+  --workgroup linear|8x8|16x16
+                            workgroups of the per-vertex compute passes (linear)
+  --seed N                  jitter the starting positions with seed N (no jitter)
+  --scene single|strip|gravity-space|flags
+                            scene shown at startup (single)
+  --preset drape|flag|hammock
+                            layout of the single cloth (drape)
+  -h, --help                print this message";
+
+/// A command line that `ClothBuilder::from_args` can't use.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgsError {
+    /// `--help` or `-h`, not a failure but the program should stop
+    Help,
+    Unknown(String),
+    MissingValue(&'static str),
+    Invalid(EnvError),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::Help => write!(f, "{}", USAGE),
+            ArgsError::Unknown(arg) => write!(f, "unknown argument {:?}\n\n{}", arg, USAGE),
+            ArgsError::MissingValue(flag) => write!(f, "{} needs a value\n\n{}", flag, USAGE),
+            ArgsError::Invalid(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+impl From<EnvError> for ArgsError {
+    fn from(error: EnvError) -> Self {
+        ArgsError::Invalid(error)
+    }
+}
+
+/// Splits `args` (without the program name) into `(flag, value)` pairs, each
+/// flag one of `flags` and given as `--flag value` or `--flag=value`. Stops at
+/// the first argument that isn't.
+pub fn flag_values<I: IntoIterator<Item = String>>(args: I, flags: &[&'static str]) -> Result<Vec<(&'static str, String)>, ArgsError> {
+    let mut args = args.into_iter();
+    let mut pairs = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            return Err(ArgsError::Help);
+        }
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let Some(flag) = flags.iter().copied().find(|flag| *flag == name) else {
+            return Err(ArgsError::Unknown(arg));
+        };
+        let value = inline_value.or_else(|| args.next()).ok_or(ArgsError::MissingValue(flag))?;
+        pairs.push((flag, value));
+    }
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instances_app::ClothBuilder;
+
+    const FLAGS: [&str; 2] = ["--grid", "--precision"];
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn invalid(values: &[&str]) -> EnvError {
+        match ClothBuilder::new().from_args(args(values)) {
+            Err(ArgsError::Invalid(error)) => error,
+            other => panic!("expected an invalid value, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn flags_take_the_next_or_an_inline_value() {
+        let pairs = flag_values(args(&["--grid", "64", "--precision=f16", "--grid="]), &FLAGS).unwrap();
+        assert_eq!(pairs, vec![("--grid", "64".to_string()), ("--precision", "f16".to_string()), ("--grid", String::new())]);
+        assert_eq!(flag_values(args(&[]), &FLAGS), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn help_stops_the_parsing() {
+        assert_eq!(flag_values(args(&["--grid", "64", "-h"]), &FLAGS), Err(ArgsError::Help));
+        assert_eq!(flag_values(args(&["--help", "--bogus"]), &FLAGS), Err(ArgsError::Help));
+        assert_eq!(ArgsError::Help.to_string(), USAGE);
+    }
+
+    #[test]
+    fn unknown_arguments_fail() {
+        assert_eq!(flag_values(args(&["--bogus", "1"]), &FLAGS), Err(ArgsError::Unknown("--bogus".to_string())));
+        assert_eq!(flag_values(args(&["64"]), &FLAGS), Err(ArgsError::Unknown("64".to_string())));
+        assert_eq!(flag_values(args(&["--grid=64", "--Grid=64"]), &FLAGS), Err(ArgsError::Unknown("--Grid=64".to_string())));
+        assert!(ArgsError::Unknown("--bogus".to_string()).to_string().starts_with("unknown argument \"--bogus\"\n\nusage:"));
+    }
+
+    #[test]
+    fn a_flag_at_the_end_needs_a_value() {
+        assert_eq!(flag_values(args(&["--precision", "f32", "--grid"]), &FLAGS), Err(ArgsError::MissingValue("--grid")));
+        assert!(ArgsError::MissingValue("--grid").to_string().starts_with("--grid needs a value\n\n"));
+    }
+
+    #[test]
+    fn values_out_of_range_fail() {
+        assert_eq!(invalid(&["--grid", "1"]).expected, "a number in 2..=512");
+        assert_eq!(invalid(&["--grid=513"]).expected, "a number in 2..=512");
+        assert_eq!(invalid(&["--iterations", "17"]).expected, "a number in 0..=16");
+        assert_eq!(invalid(&["--gravity", "-9.8"]).expected, "a number in 0..=20");
+        assert_eq!(invalid(&["--stiffness", "25,15"]).expected, "1 or 3 comma-separated numbers");
+        assert_eq!(invalid(&["--seed", "-1"]).name, "--seed");
+    }
+
+    #[test]
+    fn unknown_choices_fail() {
+        let error = invalid(&["--precision", "f64"]);
+        assert_eq!(error.expected, "one of f32, f16");
+        assert_eq!(ArgsError::from(error).to_string(), "invalid --precision=\"f64\": expected one of f32, f16");
+        assert_eq!(invalid(&["--workgroup", "32x32"]).name, "--workgroup");
+        assert_eq!(invalid(&["--scene", "bogus"]).name, "--scene");
+        assert_eq!(invalid(&["--preset", "bogus"]).name, "--preset");
+    }
+
+    #[test]
+    fn valid_arguments_build() {
+        assert!(ClothBuilder::new().from_args(args(&["--grid", "32", "--stiffness=40", "--precision", "F16", "--workgroup", "8x8"])).is_ok());
+    }
+}
@@ -2,29 +2,51 @@ struct Vertex {
     @location(0) position: vec4<f32>,  // 16-byte aligned
     @location(1) color: vec4<f32>,     // 16-byte aligned
     @location(2) mass: f32,
+    force: array<f32, 3>,  // net force of the last step, in the padding before velocity
     @align(16) @location(3) velocity: vec4<f32>,  // 16-byte aligned
     @location(4) fixed: f32,
+    @location(6) springs: u32,  // intact springs to the 8 neighbors, see SPRING_*
+    grid: vec2<u32>,  // row and column the vertex was built at, see COLOR_MODE_GRID
+    @align(16) @location(5) normal: vec4<f32>,  // written by cs_normals
+    pin_target: vec4<f32>,  // where a fixed vertex is held, w 1 once set, see cs_hold_pins
 }
 
 struct SimParams1 {
     @align(16) grid_k_radius: vec4<f32>,
-    @align(16) sphere_center: vec4<f32>,
+    @align(16) colliders: vec4<f32>,  // collider_count, self_collision_radius, sticky_collision, first_collider
+    @align(16) collision: vec4<f32>,
+    @align(16) orientation: vec4<f32>,
+    @align(16) options: vec4<f32>,
+    @align(16) counts: vec4<u32>,  // vertex_count, unused x3
 }
 
 struct SimParams2 {
     @align(16) stiffness: vec4<f32>,
     @align(16) rest_length: vec4<f32>,
     @align(16) gravity: vec4<f32>,
-    @align(16) _padding: vec4<f32>,
+    @align(16) time: vec4<f32>,
+    @align(16) flow: vec4<f32>,
+    @align(16) ground: vec4<f32>,  // plane_y, restitution, enabled, unused
+    @align(16) wind: vec4<f32>,    // wind xyz, turbulence
+    @align(16) clock: vec4<f32>,   // elapsed simulated time, unused x3
+    @align(16) damping: vec4<f32>, // settle_damping, air_drag, stabilize boost, sleep_threshold
+    @align(16) warp: vec4<f32>,    // warp stiffness, unused x3
+    @align(16) gust: vec4<f32>,    // gust acceleration xyz for the current frame, unused
+}
+
+// Per-frame statistics, cleared before cs_reduce_stats and read back on the CPU
+struct SimStats {
+    max_speed: atomic<u32>,    // f32 bits, the order of non-negative floats matches their bits
+    max_stretch: atomic<u32>,  // f32 bits, longest structural spring over its rest length
+    kinetic_energy: atomic<u32>, // f32 bits, summed with a compare exchange loop
 }
 
 struct Parameters {
-    sphere_center: vec4<f32>,
-    sphere_radius: f32,
+    collider_count: u32,
     dt: f32,
     gravity: vec4<f32>,
     sphere_damping: f32,
-    structural_stiffness: f32,
+    structural_stiffness: f32,  // weft, the springs along a row
     shear_stiffness: f32,
     bending_stiffness: f32,
     vertex_damping: f32,
@@ -36,22 +58,170 @@ struct Parameters {
     bending_max_length: f32,
     grid_width: u32,
     grid_height: u32,
+    sphere_inverted: bool,
+    contact_damping: f32,
+    sphere_friction: f32,
+    gravity_enabled: bool,
+    springs_enabled: bool,
+    max_spring_force: f32,
+    color_mode: u32,
+    flow_tile_size: f32,
+    flow_strength: f32,
+    flow_offset: vec2<f32>,
+    integration_method: u32,
+    tear_length: f32,
+    ground_enabled: bool,
+    ground_y: f32,
+    ground_restitution: f32,
+    wind: vec3<f32>,
+    turbulence: f32,
+    time: f32,
+    self_collision_radius: f32,
+    settle_damping: f32,
+    warp_stiffness: f32,  // the springs along a column
+    gust: vec3<f32>,
+    air_drag: f32,
+    sticky_collision: bool,  // vertices touching a collider become fixed
+    first_collider: u32,     // 1 while the first collider is the soft sphere
+    sleep_threshold: f32,    // speed below which settle_vertex stops a vertex
+    sphere_restitution: f32, // share of the speed into a collider it bounces back with
 };
 
+// A sphere, or a capsule around the segment center -/+ half_axis
+struct Collider {
+    center_radius: vec4<f32>,  // center xyz, radius w
+    half_axis: vec4<f32>,      // zero for a sphere, unused w
+}
+
+// Uniforms of the soft sphere, see `SoftParams` in instances_app.rs
+struct SoftParams {
+    center: vec4<f32>,      // anchor of the core springs xyz, unused w
+    gravity_dt: vec4<f32>,  // gravity xyz, dt of a substep w
+    springs: vec4<f32>,     // edge stiffness, core stiffness, damping, unused
+    contact: vec4<f32>,     // push radius, reach, stiffness, unused
+}
+
+// Rest offset of a soft sphere vertex from the center and its springs, the
+// range `first .. first + count` of soft_springs
+struct SoftNode {
+    rest: vec4<f32>,
+    springs: vec2<u32>,
+}
+
+struct SoftSpring {
+    neighbor: u32,
+    rest_length: f32,
+}
+
+// Operation applied by cs_apply_selection to every vertex with a non-zero weight
+struct SelectionOp {
+    kind: u32,          // 0 pin, 1 release, 2 impulse, 3 recolor, 4 translate, 5 mass
+    value: vec4<f32>,   // impulse, color or offset, unused for mass
+}
+
+const SELECTION_PIN = 0u;
+const SELECTION_RELEASE = 1u;
+const SELECTION_IMPULSE = 2u;
+const SELECTION_TRANSLATE = 4u;
+const SELECTION_MASS = 5u;
+
+// ColorMode in instances_app.rs
+const COLOR_MODE_MATERIAL = 0u;
+const COLOR_MODE_WORKGROUP = 1u;
+const COLOR_MODE_SPEED = 2u;
+const COLOR_MODE_STRAIN = 3u;
+const COLOR_MODE_GRID = 4u;
+
+// IntegrationMethod in instances_app.rs
+const INTEGRATION_EULER = 0u;
+const INTEGRATION_VERLET = 1u;
+
+// Bits of `Vertex.springs`, one per structural and shear neighbor. A cleared
+// bit is a torn spring. Bit k < 4 is structural_neighbor k.
+const SPRING_LEFT = 1u;
+const SPRING_RIGHT = 2u;
+const SPRING_TOP = 4u;
+const SPRING_BOTTOM = 8u;
+const SPRING_TOP_LEFT = 16u;
+const SPRING_TOP_RIGHT = 32u;
+const SPRING_BOTTOM_LEFT = 64u;
+const SPRING_BOTTOM_RIGHT = 128u;
+
+// MAX_COLLIDERS in instances_app.rs
+const MAX_COLLIDERS = 16u;
+
+// Vertices within this many rows and columns are held apart by the springs and
+// skipped by the self collision
+const SELF_COLLISION_SKIP = 2;
+// Most bucket entries a vertex looks at per cell, bounds the cost of crowded cells
+const MAX_SELF_COLLISION_CHECKS = 64u;
+
 // Adjusted constants for stability
-const DELTATIME = 0.0016;
 const SPHEREDAMPING = 0.5;
+// Structural springs are projected back to at most this stretch
+const MAX_STRETCH = 1.1;
 
-@group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
+// `vertices`, `load_vertex`, `load_previous_vertex`, `load_position`,
+// `store_vertex`, `store_normal` and `vertex_buffer_length` come from the storage
+// prelude (vertexStorageF32.wgsl or vertexStorageF16.wgsl) that is prepended
+// to this file when the pipeline is built. So do `WORKGROUP_WIDTH`,
+// `WORKGROUP_HEIGHT` and `WORKGROUP_INVOCATIONS`, the workgroup of the
+// per-vertex passes (see `WorkgroupLayout` in instances_app.rs).
 @group(0) @binding(1) var<uniform> params1: SimParams1;
 @group(0) @binding(2) var<uniform> params2: SimParams2;
+@group(0) @binding(3) var<storage, read_write> stats: SimStats;
+// Corrections computed by cs_project_jacobi, applied by cs_apply_jacobi
+@group(0) @binding(4) var<storage, read_write> projected: array<vec4<f32>>;
+// Packed rgba8 per vertex, drawn instead of the vertex color in the debug color modes
+@group(0) @binding(5) var<storage, read_write> debug_colors: array<u32>;
+// Wind velocities tiled over the world xz plane, xyz in rgb
+@group(0) @binding(6) var flow_texture: texture_2d<f32>;
+@group(0) @binding(7) var flow_sampler: sampler;
+// Collision spheres shared by all cloths, center xyz and radius w, see
+// `GpuCollider` in instances_app.rs. Only the first `collider_count` are used.
+@group(0) @binding(9) var<uniform> colliders: array<Collider, MAX_COLLIDERS>;
+// Triangle list of the fabric, torn cells are made degenerate by cs_tear_triangles
+@group(0) @binding(10) var<storage, read_write> triangles: array<u32>;
+// Spatial hash of the self collision pass: vertex_count() bucket heads followed
+// by one next link per vertex, both as vertex index + 1 so that 0 ends a list
+@group(0) @binding(11) var<storage, read_write> self_collision_grid: array<atomic<u32>>;
+
+// Vertices of the cloth. The dispatches round up to whole workgroups, every
+// per-vertex pass returns early for the threads at this index or past it.
+fn vertex_count() -> u32 {
+    return params1.counts.x;
+}
+
+// Vertex of the invocation: `global_id.x` along the vertex index with linear
+// workgroups, or the column and row of the grid with tiled ones. Past the last
+// column or row it is vertex_count() or more, which every pass skips.
+fn vertex_index(global_id: vec3<u32>) -> u32 {
+    if (WORKGROUP_HEIGHT == 1u) {
+        return global_id.x;
+    }
+    let cols = u32(params1.grid_k_radius.y);
+    if (global_id.x >= cols) {
+        return vertex_count();
+    }
+    return global_id.y * cols + global_id.x;
+}
+
+@group(1) @binding(0) var<storage, read> selection: array<f32>;
+@group(1) @binding(1) var<uniform> selection_op: SelectionOp;
+
+// Soft sphere, in the second group of its own pipelines next to the unused
+// selection bindings. Its vertices always have the full precision layout.
+@group(1) @binding(2) var<storage, read_write> soft_vertices: array<Vertex>;
+@group(1) @binding(3) var<storage, read> soft_previous: array<Vertex>;
+@group(1) @binding(4) var<storage, read> soft_nodes: array<SoftNode>;
+@group(1) @binding(5) var<storage, read> soft_springs: array<SoftSpring>;
+@group(1) @binding(6) var<uniform> soft: SoftParams;
 
 fn unpack_parameters(params1: SimParams1, params2: SimParams2) -> Parameters {
     return Parameters(
-        params1.sphere_center,   //sphere_center,
-        params1.grid_k_radius.w, //sphere_radius
-        DELTATIME,
-        params2.gravity,
+        min(u32(params1.colliders.x), MAX_COLLIDERS), //collider_count
+        params2.time.x,          //dt
+        cloth_gravity(params1, params2.gravity),
         SPHEREDAMPING,
         params2.stiffness.x,
         params2.stiffness.y,
@@ -63,52 +233,203 @@ fn unpack_parameters(params1: SimParams1, params2: SimParams2) -> Parameters {
         params2.rest_length.x * params1.grid_k_radius.z,
         params2.rest_length.y * params1.grid_k_radius.z,
         params2.rest_length.z * params1.grid_k_radius.z,
-        u32(params1.grid_k_radius.x), //grid_width
-        u32(params1.grid_k_radius.y), //grid_height
+        u32(params1.grid_k_radius.y), //grid_width, the columns
+        u32(params1.grid_k_radius.x), //grid_height, the rows
+        params1.collision.x > 0.5, //sphere_inverted
+        params1.collision.y, //contact_damping
+        params1.collision.z, //sphere_friction
+        params1.options.y < 0.5, //gravity_enabled
+        params1.options.z < 0.5, //springs_enabled
+        params2.stiffness.w,     //max_spring_force
+        u32(params1.options.w),  //color_mode
+        params2.flow.x,          //flow_tile_size
+        params2.flow.y,          //flow_strength
+        params2.flow.zw,         //flow_offset
+        u32(params2.time.w),     //integration_method
+        params2.rest_length.w,   //tear_length
+        params2.ground.z > 0.5,  //ground_enabled
+        params2.ground.x,        //ground_y
+        params2.ground.y,        //ground_restitution
+        params2.wind.xyz,        //wind
+        params2.wind.w,          //turbulence
+        params2.clock.x,         //time
+        params1.colliders.y,     //self_collision_radius
+        params2.damping.x + params2.damping.z, //settle_damping, with the stabilize boost
+        params2.warp.x,          //warp_stiffness
+        params2.gust.xyz,        //gust
+        params2.damping.y,       //air_drag
+        params1.colliders.z > 0.5, //sticky_collision
+        u32(params1.colliders.w), //first_collider
+        params2.damping.w,       //sleep_threshold
+        params1.collision.w,     //sphere_restitution
     );
 }
 
+// Gravity in world space, or rotated into the cloth's frame for local gravity
+fn cloth_gravity(params1: SimParams1, gravity: vec4<f32>) -> vec4<f32> {
+    if (params1.options.x < 0.5) {
+        return gravity;
+    }
+    return vec4<f32>(rotate_by_quaternion(params1.orientation, gravity.xyz), gravity.w);
+}
+
+fn rotate_by_quaternion(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+
+// Sphere of the collider's radius around the point of its axis closest to
+// `position`, the collider itself for a sphere. Colliding with it is colliding
+// with the capsule.
+fn collider_sphere(collider: Collider, position: vec3<f32>) -> vec4<f32> {
+    let axis = collider.half_axis.xyz;
+    let length_squared = dot(axis, axis);
+    if (length_squared == 0.0) {
+        return collider.center_radius;
+    }
+    let t = clamp(dot(position - collider.center_radius.xyz, axis) / length_squared, -1.0, 1.0);
+    return vec4<f32>(collider.center_radius.xyz + axis * t, collider.center_radius.w);
+}
 
-fn resolve_sphere_collision(vertex: Vertex, parameters: Parameters) -> Vertex {
+// `sphere` is center xyz and radius w, see `collider_sphere`. `inverted` keeps the
+// vertex inside it instead of outside.
+fn resolve_sphere_collision(vertex: Vertex, parameters: Parameters, sphere: vec4<f32>, inverted: bool) -> Vertex {
     if (vertex.fixed > 0.5) {
         return vertex;
     }
 
     let pos_3 = vertex.position.xyz;
-    let center_3 = parameters.sphere_center.xyz;
+    let center_3 = sphere.xyz;
+    let radius = sphere.w;
     let CS = pos_3 - center_3;
     let dist = length(CS);
 
+    if (inverted) {
+        return resolve_sphere_containment(vertex, parameters, sphere, CS, dist);
+    }
+
     // Expand collision detection range slightly
-    if (dist < parameters.sphere_radius + 0.1) {
+    if (dist < radius + 0.1) {
         let dir = CS / dist;
         
         // Ensure minimum distance from sphere
-        let min_offset = max(0.05, dist - parameters.sphere_radius);
-        let new_pos = center_3 + dir * (parameters.sphere_radius + min_offset);
-        
-        let normal_vel = dot(vertex.velocity.xyz, dir) * dir;
-        let tangent_vel = vertex.velocity.xyz - normal_vel;
-        
-        // Reduce tangential velocity more aggressively
-        let friction = 1.0;
-        // Add velocity clamping
-        let max_speed = 5.0;
-        let raw_velocity = (tangent_vel * friction) - (normal_vel * 0.7);
-        let new_velocity = normalize(raw_velocity) * min(length(raw_velocity), max_speed);
+        let min_offset = max(0.05, dist - radius);
+        let new_pos = center_3 + dir * (radius + min_offset);
         
-        return Vertex(
-            vec4<f32>(new_pos, vertex.position.w),
-            vertex.color,
-            vertex.mass,
-            vec4<f32>(new_velocity, 0.0),
-            vertex.fixed
-        );
+        return sphere_contact(vertex, parameters, new_pos, dir);
     }
     return vertex;
 }
 
-fn get_spring_force(vertex: Vertex, neighbor: Vertex, stiffness: f32, rest_length: f32) -> vec4<f32> {
+// Inverted sphere: the cloth is kept inside the shell and collides with its inner surface
+fn resolve_sphere_containment(vertex: Vertex, parameters: Parameters, sphere: vec4<f32>, CS: vec3<f32>, dist: f32) -> Vertex {
+    let radius = sphere.w;
+    if (dist > radius - 0.1 && dist > 0.0) {
+        // Surface normal seen from inside points towards the center
+        let dir = -CS / dist;
+
+        // Ensure minimum distance from the shell
+        let min_offset = max(0.05, radius - dist);
+        let new_pos = sphere.xyz - dir * (radius - min_offset);
+
+        return sphere_contact(vertex, parameters, new_pos, dir);
+    }
+    return vertex;
+}
+
+// Vertex pushed out to `position` on the sphere surface with the contact
+// normal `dir`. With sticky collision it stops there and becomes fixed, so the
+// integrator skips it from then on and its springs hold their neighbors.
+fn sphere_contact(vertex: Vertex, parameters: Parameters, position: vec3<f32>, dir: vec3<f32>) -> Vertex {
+    var velocity = vec3<f32>(0.0);
+    var fixed = 1.0;
+    if (!parameters.sticky_collision) {
+        velocity = sphere_contact_velocity(vertex.velocity.xyz, dir, parameters);
+        fixed = vertex.fixed;
+    }
+    return Vertex(
+        vec4<f32>(position, vertex.position.w),
+        vertex.color,
+        vertex.mass,
+        vertex.force,
+        vec4<f32>(velocity, 0.0),
+        fixed,
+        vertex.springs,
+        vertex.grid,
+        vertex.normal,
+        vertex.pin_target
+    );
+}
+
+// Keeps the vertex above the ground plane, bouncing the downward velocity back
+// up scaled by the restitution
+fn resolve_ground_collision(vertex: Vertex, parameters: Parameters) -> Vertex {
+    if (!parameters.ground_enabled || vertex.fixed > 0.5 || vertex.position.y >= parameters.ground_y) {
+        return vertex;
+    }
+    var velocity = vertex.velocity;
+    if (velocity.y < 0.0) {
+        velocity.y = -velocity.y * parameters.ground_restitution;
+    }
+    return Vertex(
+        vec4<f32>(vertex.position.x, parameters.ground_y, vertex.position.z, vertex.position.w),
+        vertex.color,
+        vertex.mass,
+        vertex.force,
+        velocity,
+        vertex.fixed,
+        vertex.springs,
+        vertex.grid,
+        vertex.normal,
+        vertex.pin_target
+    );
+}
+
+// Velocity scaled down by the settle damping at the end of a substep, after
+// the collision response so it can't undo its velocity clamping. Then zeroed
+// below the sleep threshold: the forces of the substep are already in it, so
+// a spring pulling hard enough still wakes the vertex.
+fn settle_vertex(vertex: Vertex, parameters: Parameters) -> Vertex {
+    if (vertex.fixed > 0.5) {
+        return vertex;
+    }
+    var settled = vertex;
+    settled.velocity *= max(1.0 - parameters.settle_damping * parameters.dt, 0.0);
+    if (length(settled.velocity.xyz) < parameters.sleep_threshold) {
+        settled.velocity = vec4<f32>(vec3<f32>(0.0), settled.velocity.w);
+    }
+    return settled;
+}
+
+// Velocity response for a vertex touching the sphere, `dir` is the contact normal
+fn sphere_contact_velocity(velocity: vec3<f32>, dir: vec3<f32>, parameters: Parameters) -> vec3<f32> {
+    let normal_speed = dot(velocity, dir);
+    let tangent_vel = velocity - normal_speed * dir;
+    // A vertex already moving away from the surface keeps going. Only the speed
+    // into it bounces back, scaled by the restitution so it never comes out
+    // faster than it went in. With the inverted sphere `dir` points towards the
+    // center, so this holds on both sides of the shell.
+    var normal_vel = normal_speed * dir;
+    if (normal_speed < 0.0) {
+        normal_vel = -normal_speed * parameters.sphere_restitution * dir;
+    }
+
+    // Friction takes away this share of the sliding velocity, 1 sticks the vertex
+    let friction = 1.0 - parameters.sphere_friction;
+    // Add velocity clamping
+    let max_speed = 5.0;
+    // Extra damping for vertices in contact only, settles resting cloth without slowing the free parts
+    let contact_damping = max(1.0 - parameters.contact_damping * parameters.dt, 0.0);
+    let raw_velocity = ((tangent_vel * friction) + normal_vel) * contact_damping;
+    let speed = length(raw_velocity);
+    // normalize() of a zero vector is undefined, a vertex at rest stays at rest
+    if (speed == 0.0) {
+        return vec3<f32>(0.0);
+    }
+    return normalize(raw_velocity) * min(speed, max_speed);
+}
+
+fn get_spring_force(vertex: Vertex, neighbor: Vertex, stiffness: f32, rest_length: f32, max_force: f32) -> vec4<f32> {
     let delta = neighbor.position - vertex.position;
     let current_length = length(delta);
     
@@ -130,8 +451,8 @@ fn get_spring_force(vertex: Vertex, neighbor: Vertex, stiffness: f32, rest_lengt
     
     let force = direction * displacement * effective_stiffness;
     
-    // Limit maximum force magnitude for stability
-    let max_force = 100.0;
+    // Limit the force of each spring before summing, so one over-tensioned
+    // spring cannot dominate the vertex
     let force_magnitude = length(force);
     if (force_magnitude > max_force) {
         return force * (max_force / force_magnitude);
@@ -140,14 +461,48 @@ fn get_spring_force(vertex: Vertex, neighbor: Vertex, stiffness: f32, rest_lengt
     return force;
 }
 
-fn resolve_spring_behavior(index: u32, vertex: Vertex, parameters: Parameters) -> Vertex {
-    // Skip if vertex is fixed
-    if (vertex.fixed > 0.5) {
-        return vertex;
+// Sum of the structural, shear and bending spring forces on the vertex, from
+// the neighbors' previous state
+fn spring_intact(vertex: Vertex, spring: u32) -> bool {
+    return (vertex.springs & spring) != 0u;
+}
+
+// Index of the neighbor at the other end of spring bit `k` (see SPRING_*), -1
+// past the grid border
+fn spring_neighbor(index: u32, k: u32, parameters: Parameters) -> i32 {
+    var col_offsets = array<i32, 8>(-1, 1, 0, 0, -1, 1, -1, 1);
+    var row_offsets = array<i32, 8>(0, 0, -1, 1, -1, -1, 1, 1);
+    let col = i32(index % parameters.grid_width) + col_offsets[k];
+    let row = i32(index / parameters.grid_width) + row_offsets[k];
+    if (col < 0 || col >= i32(parameters.grid_width) || row < 0 || row >= i32(parameters.grid_height)) {
+        return -1;
     }
+    return row * i32(parameters.grid_width) + col;
+}
 
+// Springs of the vertex still intact after this step: those stretched past
+// `rest_length * tear_length` break. Both ends read the previous state, so
+// they agree on which springs tore.
+fn tear_springs(index: u32, vertex: Vertex, parameters: Parameters) -> u32 {
+    var springs = vertex.springs;
+    for (var k = 0u; k < 8u; k++) {
+        let spring = 1u << k;
+        let neighbor_index = spring_neighbor(index, k, parameters);
+        if (neighbor_index < 0 || !spring_intact(vertex, spring)) {
+            continue;
+        }
+        let rest_length = select(parameters.shear_rest_length, parameters.structural_rest_length, k < 4u);
+        let current_length = distance(load_previous_vertex(u32(neighbor_index)).position.xyz, vertex.position.xyz);
+        if (current_length > rest_length * parameters.tear_length) {
+            springs &= ~spring;
+        }
+    }
+    return springs;
+}
+
+fn spring_force(index: u32, vertex: Vertex, parameters: Parameters) -> vec4<f32> {
     var force = vec4<f32>(0.0);
-    
+
     // Get current position in grid
     let row = index / parameters.grid_width;
     let col = index % parameters.grid_width;
@@ -163,126 +518,824 @@ fn resolve_spring_behavior(index: u32, vertex: Vertex, parameters: Parameters) -
     let has_two_top = row >= 2u;
     let has_two_bottom = row < parameters.grid_height - 2u;
 
-    // Structural springs (direct neighbors)
-    if (has_left) {
+    // Structural springs (direct neighbors), weft along the row and warp along
+    // the column
+    if (has_left && spring_intact(vertex, SPRING_LEFT)) {
         let left_index = index - 1u;
-        force += get_spring_force(vertex, vertices[left_index], 
-            parameters.structural_stiffness, parameters.structural_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(left_index), 
+            parameters.structural_stiffness, parameters.structural_rest_length, parameters.max_spring_force);
     }
 
-    if (has_right) {
+    if (has_right && spring_intact(vertex, SPRING_RIGHT)) {
         let right_index = index + 1u;
-        force += get_spring_force(vertex, vertices[right_index], 
-            parameters.structural_stiffness, parameters.structural_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(right_index), 
+            parameters.structural_stiffness, parameters.structural_rest_length, parameters.max_spring_force);
     }
 
-    if (has_top) {
+    if (has_top && spring_intact(vertex, SPRING_TOP)) {
         let top_index = index - parameters.grid_width;
-        force += get_spring_force(vertex, vertices[top_index], 
-            parameters.structural_stiffness, parameters.structural_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(top_index), 
+            parameters.warp_stiffness, parameters.structural_rest_length, parameters.max_spring_force);
     }
 
-    if (has_bottom) {
+    if (has_bottom && spring_intact(vertex, SPRING_BOTTOM)) {
         let bottom_index = index + parameters.grid_width;
-        force += get_spring_force(vertex, vertices[bottom_index], 
-            parameters.structural_stiffness, parameters.structural_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(bottom_index), 
+            parameters.warp_stiffness, parameters.structural_rest_length, parameters.max_spring_force);
     }
 
-    // Shear springs (diagonal neighbors)
-    if (has_top && has_left) {
+    // Shear springs (diagonal neighbors, see `shear_neighbors` in
+    // cpu_reference.rs), a single one at each corner of the grid
+    if (has_top && has_left && spring_intact(vertex, SPRING_TOP_LEFT)) {
         let top_left_index = index - parameters.grid_width - 1u;
-        force += get_spring_force(vertex, vertices[top_left_index], 
-            parameters.shear_stiffness, parameters.shear_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(top_left_index), 
+            parameters.shear_stiffness, parameters.shear_rest_length, parameters.max_spring_force);
     }
 
-    if (has_top && has_right) {
+    if (has_top && has_right && spring_intact(vertex, SPRING_TOP_RIGHT)) {
         let top_right_index = index - parameters.grid_width + 1u;
-        force += get_spring_force(vertex, vertices[top_right_index], 
-            parameters.shear_stiffness, parameters.shear_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(top_right_index), 
+            parameters.shear_stiffness, parameters.shear_rest_length, parameters.max_spring_force);
     }
 
-    if (has_bottom && has_left) {
+    if (has_bottom && has_left && spring_intact(vertex, SPRING_BOTTOM_LEFT)) {
         let bottom_left_index = index + parameters.grid_width - 1u;
-        force += get_spring_force(vertex, vertices[bottom_left_index], 
-            parameters.shear_stiffness, parameters.shear_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(bottom_left_index), 
+            parameters.shear_stiffness, parameters.shear_rest_length, parameters.max_spring_force);
     }
 
-    if (has_bottom && has_right) {
+    if (has_bottom && has_right && spring_intact(vertex, SPRING_BOTTOM_RIGHT)) {
         let bottom_right_index = index + parameters.grid_width + 1u;
-        force += get_spring_force(vertex, vertices[bottom_right_index], 
-            parameters.shear_stiffness, parameters.shear_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(bottom_right_index), 
+            parameters.shear_stiffness, parameters.shear_rest_length, parameters.max_spring_force);
     }
 
-    // Bending springs (two vertices away)
-    if (has_two_left) {
+    // Bending springs (two vertices away, see `bending_neighbors` in
+    // cpu_reference.rs), gone once a spring they span tore. The has_two_*
+    // checks keep the left and right ones from wrapping to another row.
+    if (has_two_left && spring_intact(vertex, SPRING_LEFT) && spring_intact(load_previous_vertex(index - 1u), SPRING_LEFT)) {
         let two_left_index = index - 2u;
-        force += get_spring_force(vertex, vertices[two_left_index], 
-            parameters.bending_stiffness, parameters.bending_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(two_left_index), 
+            parameters.bending_stiffness, parameters.bending_rest_length, parameters.max_spring_force);
     }
 
-    if (has_two_right) {
+    if (has_two_right && spring_intact(vertex, SPRING_RIGHT) && spring_intact(load_previous_vertex(index + 1u), SPRING_RIGHT)) {
         let two_right_index = index + 2u;
-        force += get_spring_force(vertex, vertices[two_right_index], 
-            parameters.bending_stiffness, parameters.bending_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(two_right_index), 
+            parameters.bending_stiffness, parameters.bending_rest_length, parameters.max_spring_force);
     }
 
-    if (has_two_top) {
+    if (has_two_top && spring_intact(vertex, SPRING_TOP) && spring_intact(load_previous_vertex(index - parameters.grid_width), SPRING_TOP)) {
         let two_top_index = index - 2u * parameters.grid_width;
-        force += get_spring_force(vertex, vertices[two_top_index], 
-            parameters.bending_stiffness, parameters.bending_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(two_top_index), 
+            parameters.bending_stiffness, parameters.bending_rest_length, parameters.max_spring_force);
     }
 
-    if (has_two_bottom) {
+    if (has_two_bottom && spring_intact(vertex, SPRING_BOTTOM) && spring_intact(load_previous_vertex(index + parameters.grid_width), SPRING_BOTTOM)) {
         let two_bottom_index = index + 2u * parameters.grid_width;
-        force += get_spring_force(vertex, vertices[two_bottom_index], 
-            parameters.bending_stiffness, parameters.bending_rest_length);
+        force += get_spring_force(vertex, load_previous_vertex(two_bottom_index), 
+            parameters.bending_stiffness, parameters.bending_rest_length, parameters.max_spring_force);
+    }
+
+    return force;
+}
+
+// Wind acceleration at `position`, from the scrolling flow texture
+fn flow_wind(position: vec3<f32>, parameters: Parameters) -> vec3<f32> {
+    let uv = position.xz / parameters.flow_tile_size + parameters.flow_offset;
+    return textureSampleLevel(flow_texture, flow_sampler, uv, 0.0).xyz * parameters.flow_strength;
+}
+
+// Pseudo random value in 0..1 for a lattice point
+fn lattice_hash(p: vec3<f32>) -> f32 {
+    let q = fract(p * 0.3183099 + vec3<f32>(0.71, 0.113, 0.419)) * 17.0;
+    return fract(q.x * q.y * q.z * (q.x + q.y + q.z));
+}
+
+// Value noise in -1..1, smoothly interpolated between the lattice points
+fn value_noise(p: vec3<f32>) -> f32 {
+    let cell = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let bottom = mix(
+        mix(lattice_hash(cell), lattice_hash(cell + vec3<f32>(1.0, 0.0, 0.0)), u.x),
+        mix(lattice_hash(cell + vec3<f32>(0.0, 1.0, 0.0)), lattice_hash(cell + vec3<f32>(1.0, 1.0, 0.0)), u.x),
+        u.y,
+    );
+    let top = mix(
+        mix(lattice_hash(cell + vec3<f32>(0.0, 0.0, 1.0)), lattice_hash(cell + vec3<f32>(1.0, 0.0, 1.0)), u.x),
+        mix(lattice_hash(cell + vec3<f32>(0.0, 1.0, 1.0)), lattice_hash(cell + vec3<f32>(1.0, 1.0, 1.0)), u.x),
+        u.y,
+    );
+    return mix(bottom, top, u.z) * 2.0 - 1.0;
+}
+
+// Steady wind plus turbulence that drifts with time
+fn wind_velocity(vertex: Vertex, parameters: Parameters) -> vec3<f32> {
+    let p = vertex.position.xyz + vec3<f32>(parameters.time);
+    let gust = vec3<f32>(
+        value_noise(p),
+        value_noise(p + vec3<f32>(17.0, 31.0, 47.0)),
+        value_noise(p + vec3<f32>(59.0, 13.0, 71.0)),
+    );
+    return parameters.wind + parameters.turbulence * gust;
+}
+
+// Only the part of the wind along the normal pushes, so faces square to the
+// wind catch the most
+fn wind_force(vertex: Vertex, wind: vec3<f32>) -> vec3<f32> {
+    let normal = vertex.normal.xyz;
+    return normal * dot(normal, wind);
+}
+
+// Drag against the velocity relative to the air, along the normal only: a face
+// moving broadside is held back, one sliding edge-on through the air is not
+fn air_drag_force(vertex: Vertex, wind: vec3<f32>, drag: f32) -> vec3<f32> {
+    let normal = vertex.normal.xyz;
+    return -drag * normal * dot(normal, vertex.velocity.xyz - wind);
+}
+
+// Springs, gravity and wind on the vertex, shared by both integrators
+fn accumulate_force(index: u32, vertex: Vertex, parameters: Parameters) -> vec4<f32> {
+    var force = vec4<f32>(0.0);
+
+    if (parameters.springs_enabled) {
+        force += spring_force(index, vertex, parameters);
     }
 
     // Apply gravity
-    force += parameters.gravity * vertex.mass;
-    
-    // Apply damping proportional to velocity
-    force += -parameters.vertex_damping * vertex.velocity;
-    
-    // Semi-implicit Euler integration
-    let acceleration = force / vertex.mass;
-    let new_velocity = vertex.velocity + acceleration * parameters.dt;
-    let new_position = vertex.position + new_velocity * parameters.dt;
-    
+    if (parameters.gravity_enabled) {
+        force += parameters.gravity * vertex.mass;
+    }
+
+    if (parameters.flow_strength > 0.0) {
+        force += vec4<f32>(flow_wind(vertex.position.xyz, parameters), 0.0) * vertex.mass;
+    }
+
+    let windy = any(parameters.wind != vec3<f32>(0.0)) || parameters.turbulence > 0.0;
+    if (windy || parameters.air_drag > 0.0) {
+        let wind = select(vec3<f32>(0.0), wind_velocity(vertex, parameters), windy);
+        force += vec4<f32>(wind_force(vertex, wind), 0.0);
+        if (parameters.air_drag > 0.0) {
+            force += vec4<f32>(air_drag_force(vertex, wind, parameters.air_drag), 0.0);
+        }
+    }
+
+    // Only non-zero on the frame a gust was triggered, the same push for every vertex
+    force += vec4<f32>(parameters.gust, 0.0) * vertex.mass;
+    return force;
+}
+
+fn resolve_spring_behavior(index: u32, vertex: Vertex, parameters: Parameters) -> Vertex {
+    // Skip if vertex is fixed, nothing moves it
+    if (vertex.fixed > 0.5) {
+        var pinned = vertex;
+        pinned.force = array<f32, 3>();
+        return pinned;
+    }
+
+    let force = accumulate_force(index, vertex, parameters).xyz;
+    let velocity = vertex.velocity.xyz;
+    let dt = parameters.dt;
+
+    // Only xyz is integrated, w stays as it was
+    var new_velocity: vec3<f32>;
+    var movement: vec3<f32>;
+    if (parameters.integration_method == INTEGRATION_VERLET) {
+        // Position Verlet: the last displacement (velocity * dt), damped, plus
+        // a * dt². cs_verlet_velocity derives the velocity from the positions
+        // again once the constraints and collisions have moved them.
+        let acceleration = force / vertex.mass;
+        movement = velocity * dt * max(1.0 - parameters.vertex_damping * dt, 0.0) + acceleration * dt * dt;
+        new_velocity = movement / dt;
+    } else {
+        // Semi-implicit Euler, with damping proportional to velocity
+        let acceleration = (force - parameters.vertex_damping * velocity) / vertex.mass;
+        new_velocity = velocity + acceleration * dt;
+        movement = new_velocity * dt;
+    }
+
     // Add position-based relaxation
-    let final_position = vertex.position + new_velocity * parameters.dt;
     let max_movement = parameters.structural_rest_length * 0.5;
-    let movement = final_position - vertex.position;
     let movement_length = length(movement);
-    
     if (movement_length > max_movement) {
-        let limited_movement = normalize(movement) * max_movement;
-        return Vertex(
-            vertex.position + limited_movement,
-            vertex.color,
-            vertex.mass,
-            new_velocity * 0.9, // Reduce velocity
-            vertex.fixed
-        );
+        movement *= max_movement / movement_length;
+        new_velocity *= 0.9; // Reduce velocity
     }
-    
-    return Vertex(final_position, vertex.color, vertex.mass, new_velocity, vertex.fixed);
+
+    return Vertex(
+        vertex.position + vec4<f32>(movement, 0.0),
+        vertex.color,
+        vertex.mass,
+        array<f32, 3>(force.x, force.y, force.z),
+        vec4<f32>(new_velocity, 0.0),
+        vertex.fixed,
+        vertex.springs,
+        vertex.grid,
+        vertex.normal,
+        vertex.pin_target
+    );
 }
 
+fn inverse_mass(vertex: Vertex) -> f32 {
+    if (vertex.fixed > 0.5) {
+        return 0.0;
+    }
+    return 1.0 / vertex.mass;
+}
 
-@compute @workgroup_size(256)
-fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
-    if (index >= arrayLength(&vertices)) {
+// Index of the k-th (left, right, up, down) structural neighbor, -1 past the grid border
+fn structural_neighbor(index: u32, k: u32, parameters: Parameters) -> i32 {
+    let row = index / parameters.grid_width;
+    let col = index % parameters.grid_width;
+    switch k {
+        case 0u: {
+            if (col > 0u) { return i32(index - 1u); }
+        }
+        case 1u: {
+            if (col < parameters.grid_width - 1u) { return i32(index + 1u); }
+        }
+        case 2u: {
+            if (row > 0u) { return i32(index - parameters.grid_width); }
+        }
+        default: {
+            if (row < parameters.grid_height - 1u) { return i32(index + parameters.grid_width); }
+        }
+    }
+    return -1;
+}
+
+// Averaged correction moving `vertex` towards satisfying the stretch limit of
+// its structural springs. Each spring is split between both ends by inverse mass.
+fn constraint_correction(index: u32, vertex: Vertex, parameters: Parameters) -> vec3<f32> {
+    let w_self = inverse_mass(vertex);
+    if (w_self == 0.0) {
+        return vec3<f32>(0.0);
+    }
+
+    let max_length = parameters.structural_rest_length * MAX_STRETCH;
+
+    var correction = vec3<f32>(0.0);
+    var count = 0.0;
+    for (var k = 0u; k < 4u; k++) {
+        let neighbor_index = structural_neighbor(index, k, parameters);
+        if (neighbor_index < 0 || !spring_intact(vertex, 1u << k)) {
+            continue;
+        }
+
+        let neighbor = load_vertex(u32(neighbor_index));
+        let delta = neighbor.position.xyz - vertex.position.xyz;
+        let current_length = length(delta);
+        if (current_length > max_length) {
+            let share = w_self / (w_self + inverse_mass(neighbor));
+            correction += share * (current_length - max_length) * delta / current_length;
+            count += 1.0;
+        }
+    }
+    return correction / max(count, 1.0);
+}
+
+// Moves the vertex and keeps the velocity consistent with the new position
+fn apply_correction(vertex: Vertex, correction: vec3<f32>, parameters: Parameters) -> Vertex {
+    var corrected = vertex;
+    corrected.position += vec4<f32>(correction, 0.0);
+    corrected.velocity += vec4<f32>(correction / parameters.dt, 0.0);
+    return corrected;
+}
+
+// Distinct, saturated color for a small integer id
+fn id_color(id: u32) -> u32 {
+    var h = id * 2654435761u;
+    h ^= h >> 16u;
+    let hue = f32(h & 0xffffu) / 65535.0;
+    let rgb = clamp(abs(fract(hue + vec3<f32>(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0) - 1.0, vec3<f32>(0.0), vec3<f32>(1.0));
+    return pack4x8unorm(vec4<f32>(rgb, 1.0));
+}
+
+// Row and column the vertex was built at as a ramp, red across the columns and
+// green along the rows. Blue where they disagree with the `row * grid_width +
+// col` layout the neighbor lookups assume.
+fn grid_color(index: u32, vertex: Vertex, parameters: Parameters) -> u32 {
+    let size = vec2<f32>(f32(max(parameters.grid_width, 2u) - 1u), f32(max(parameters.grid_height, 2u) - 1u));
+    let ramp = vec2<f32>(f32(vertex.grid.y), f32(vertex.grid.x)) / size;
+    let expected = vec2<u32>(index / parameters.grid_width, index % parameters.grid_width);
+    let mismatch = select(0.0, 1.0, any(vertex.grid != expected));
+    return pack4x8unorm(vec4<f32>(ramp, mismatch, 1.0));
+}
+
+// Stage 1: spring forces, gravity and integration
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) workgroup_count: vec3<u32>,
+) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
         return;
     }
-    var vertex = vertices[index];
+    // Read from the previous state only, so no vertex sees a neighbor that
+    // was already moved by this pass
+    var vertex = load_previous_vertex(index);
 
     let parameters = unpack_parameters(params1, params2);
 
+    if (parameters.tear_length > 0.0) {
+        vertex.springs = tear_springs(index, vertex, parameters);
+    }
     vertex = resolve_spring_behavior(index, vertex, parameters);
 
-    vertex = resolve_sphere_collision(vertex, parameters);
+    store_vertex(index, vertex);
+
+    if (parameters.color_mode == COLOR_MODE_WORKGROUP) {
+        debug_colors[index] = id_color(workgroup_id.y * workgroup_count.x + workgroup_id.x);
+    } else if (parameters.color_mode == COLOR_MODE_GRID) {
+        debug_colors[index] = grid_color(index, vertex, parameters);
+    }
+}
+
+// Largest relative stretch of the structural springs of the vertex, 0 when none is stretched
+fn structural_strain(index: u32, vertex: Vertex, parameters: Parameters) -> f32 {
+    var strain = 0.0;
+    for (var k = 0u; k < 4u; k++) {
+        let neighbor_index = structural_neighbor(index, k, parameters);
+        if (neighbor_index < 0 || !spring_intact(vertex, 1u << k)) {
+            continue;
+        }
+        let current_length = distance(load_vertex(u32(neighbor_index)).position.xyz, vertex.position.xyz);
+        strain = max(strain, current_length / parameters.structural_rest_length - 1.0);
+    }
+    return strain;
+}
+
+// Heat colors of the heatmap color modes, once per frame after all stages.
+// The gradient (heatmap_color) is generated from heatmap.rs
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_heatmap(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    let vertex = load_vertex(index);
+    let parameters = unpack_parameters(params1, params2);
+
+    var value = length(vertex.velocity.xyz);
+    if (parameters.color_mode == COLOR_MODE_STRAIN) {
+        value = structural_strain(index, vertex, parameters);
+    }
+    let t = (value - params2.time.y) / max(params2.time.z - params2.time.y, 1e-6);
+    debug_colors[index] = pack4x8unorm(vec4<f32>(heatmap_color(t), 1.0));
+}
+
+// Whether the spring between `a` and `b` is intact at both ends, `spring` is its
+// bit seen from `a` and `reverse` from `b`
+fn edge_intact(a: u32, spring: u32, b: u32, reverse: u32) -> bool {
+    return spring_intact(load_vertex(a), spring) && spring_intact(load_vertex(b), reverse);
+}
+
+// Triangles of one grid cell each, in the order of grid_indices in
+// instances_app/cloth.rs. A triangle that lost an edge or the diagonal to tearing
+// collapses onto the top left vertex, so the tear shows as a hole.
+@compute @workgroup_size(256)
+fn cs_tear_triangles(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let parameters = unpack_parameters(params1, params2);
+    let cols = parameters.grid_width;
+    let cell = global_id.x;
+    if (cell >= (parameters.grid_height - 1u) * (cols - 1u)) {
+        return;
+    }
+    let top_left = (cell / (cols - 1u)) * cols + cell % (cols - 1u);
+    let top_right = top_left + 1u;
+    let bottom_left = top_left + cols;
+    let bottom_right = bottom_left + 1u;
+
+    let diagonal = edge_intact(top_left, SPRING_BOTTOM_RIGHT, bottom_right, SPRING_TOP_LEFT);
+    let first = diagonal
+        && edge_intact(top_left, SPRING_BOTTOM, bottom_left, SPRING_TOP)
+        && edge_intact(bottom_left, SPRING_RIGHT, bottom_right, SPRING_LEFT);
+    let second = diagonal
+        && edge_intact(top_right, SPRING_BOTTOM, bottom_right, SPRING_TOP)
+        && edge_intact(top_left, SPRING_RIGHT, top_right, SPRING_LEFT);
+
+    let base = cell * 6u;
+    triangles[base] = top_left;
+    triangles[base + 1u] = select(top_left, bottom_left, first);
+    triangles[base + 2u] = select(top_left, bottom_right, first);
+    triangles[base + 3u] = top_left;
+    triangles[base + 4u] = select(top_left, bottom_right, second);
+    triangles[base + 5u] = select(top_left, top_right, second);
+}
+
+// Vertex normals for the lighting, from the positions at the end of the frame.
+// The cross products of the grid edges around the vertex are summed over the
+// cells it touches, so border and corner vertices use the cells they have.
+// Only positions are read and only the normal is written, so invocations never
+// touch the same field.
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_normals(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    let parameters = unpack_parameters(params1, params2);
+    let row = index / parameters.grid_width;
+    let col = index % parameters.grid_width;
+    let position = load_position(index);
+
+    // Edges to the four structural neighbors, zero where there is none
+    var right = vec3<f32>(0.0);
+    var down = vec3<f32>(0.0);
+    var left = vec3<f32>(0.0);
+    var up = vec3<f32>(0.0);
+    if (col + 1u < parameters.grid_width) {
+        right = load_position(index + 1u) - position;
+    }
+    if (row + 1u < parameters.grid_height) {
+        down = load_position(index + parameters.grid_width) - position;
+    }
+    if (col > 0u) {
+        left = load_position(index - 1u) - position;
+    }
+    if (row > 0u) {
+        up = load_position(index - parameters.grid_width) - position;
+    }
+
+    // Missing edges make their cells' terms vanish. Oriented so a flat cloth
+    // faces its local +y, like the initial normals.
+    let sum = cross(down, right) + cross(right, up) + cross(up, left) + cross(left, down);
+    if (dot(sum, sum) > 1e-12) {
+        store_normal(index, vec4<f32>(normalize(sum), 0.0));
+    }
+}
+
+// Stage 2: constraint projection, see ConstraintSolver in instances_app.rs
+
+// Jacobi: every vertex reads the same positions and writes its correction to `projected`...
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_project_jacobi(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    let vertex = load_vertex(index);
+    let parameters = unpack_parameters(params1, params2);
+
+    let correction = constraint_correction(index, vertex, parameters);
+    projected[index] = vec4<f32>(correction, 0.0);
+}
+
+// ...then the corrections are applied in a second dispatch
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_apply_jacobi(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    let parameters = unpack_parameters(params1, params2);
+
+    store_vertex(index, apply_correction(load_vertex(index), projected[index].xyz, parameters));
+}
+
+// Red-black Gauss-Seidel: structural neighbors always have the other color, so
+// each color can be corrected in place
+fn project_color(index: u32, color: u32) {
+    if (index >= vertex_count()) {
+        return;
+    }
+    let parameters = unpack_parameters(params1, params2);
+    let row = index / parameters.grid_width;
+    let col = index % parameters.grid_width;
+    if ((row + col) % 2u != color) {
+        return;
+    }
+    let vertex = load_vertex(index);
+
+    let correction = constraint_correction(index, vertex, parameters);
+    store_vertex(index, apply_correction(vertex, correction, parameters));
+}
+
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_project_red(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    project_color(vertex_index(global_id), 0u);
+}
+
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_project_black(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    project_color(vertex_index(global_id), 1u);
+}
+
+// Verlet only, after the constraints and the collisions: velocity of the
+// step from the positions before (the previous vertex buffer) and after it,
+// so the position corrections count as motion
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_verlet_velocity(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    var vertex = load_vertex(index);
+    if (vertex.fixed > 0.5) {
+        return;
+    }
+    let parameters = unpack_parameters(params1, params2);
+    let previous = load_previous_vertex(index);
+    vertex.velocity = vec4<f32>((vertex.position.xyz - previous.position.xyz) / parameters.dt, 0.0);
+    store_vertex(index, settle_vertex(vertex, parameters));
+}
+
+// Stage 3: collision response
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_collide(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    var vertex = load_vertex(index);
+
+    let parameters = unpack_parameters(params1, params2);
 
-    vertices[index] = vertex;
-}
\ No newline at end of file
+    // Containment only applies to the first collider, the others stay solid.
+    // A soft sphere replaces the first one, see cs_soft_push_cloth.
+    for (var i = parameters.first_collider; i < parameters.collider_count; i++) {
+        let sphere = collider_sphere(colliders[i], vertex.position.xyz);
+        vertex = resolve_sphere_collision(vertex, parameters, sphere, i == 0u && parameters.sphere_inverted);
+    }
+    vertex = resolve_ground_collision(vertex, parameters);
+    // Under Verlet the velocity is derived again by cs_verlet_velocity, which settles it
+    if (parameters.integration_method != INTEGRATION_VERLET) {
+        vertex = settle_vertex(vertex, parameters);
+    }
+
+    store_vertex(index, vertex);
+}
+
+// Last stage of every substep: puts every fixed vertex back on its pin target,
+// so nothing the other stages do to it (a constraint projection, a collision,
+// the float error of half precision) can drag a pin away. The first pass over
+// a fixed vertex without a target records where it is, and a released vertex
+// forgets its target so it's recorded again when pinned.
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_hold_pins(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    var vertex = load_vertex(index);
+    if (vertex.fixed < 0.5) {
+        if (vertex.pin_target.w > 0.5) {
+            vertex.pin_target.w = 0.0;
+            store_vertex(index, vertex);
+        }
+        return;
+    }
+    if (vertex.pin_target.w > 0.5) {
+        vertex.position = vec4<f32>(vertex.pin_target.xyz, vertex.position.w);
+    } else {
+        vertex.pin_target = vec4<f32>(vertex.position.xyz, 1.0);
+    }
+    vertex.velocity = vec4<f32>(0.0);
+    store_vertex(index, vertex);
+}
+
+// Self collision, in four dispatches: clear the hash, bin every vertex into
+// the cell of a uniform grid with `self_collision_radius` sized cells, compute
+// the push of the vertices closer than the radius into `projected`, then
+// apply it with cs_apply_jacobi
+fn self_collision_cell(position: vec3<f32>, radius: f32) -> vec3<i32> {
+    return vec3<i32>(floor(position / radius));
+}
+
+fn self_collision_bucket(cell: vec3<i32>) -> u32 {
+    let cell_bits = bitcast<vec3<u32>>(cell);
+    let hash = (cell_bits.x * 73856093u) ^ (cell_bits.y * 19349663u) ^ (cell_bits.z * 83492791u);
+    return hash % vertex_count();
+}
+
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_clear_self_collision(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    atomicStore(&self_collision_grid[index], 0u);
+}
+
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_bin_self_collision(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    let parameters = unpack_parameters(params1, params2);
+
+    let bucket = self_collision_bucket(self_collision_cell(load_position(index), parameters.self_collision_radius));
+    let next = atomicExchange(&self_collision_grid[bucket], index + 1u);
+    atomicStore(&self_collision_grid[vertex_count() + index], next);
+}
+
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_self_collide(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    let vertex = load_vertex(index);
+    let parameters = unpack_parameters(params1, params2);
+    let radius = parameters.self_collision_radius;
+    let position = vertex.position.xyz;
+    let row = i32(index / parameters.grid_width);
+    let col = i32(index % parameters.grid_width);
+
+    // Each vertex of a close pair moves half the overlap away from the other
+    var correction = vec3<f32>(0.0);
+    let cell = self_collision_cell(position, radius);
+    for (var z = -1; z <= 1; z++) {
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                var entry = atomicLoad(&self_collision_grid[self_collision_bucket(cell + vec3<i32>(x, y, z))]);
+                for (var checks = 0u; entry != 0u && checks < MAX_SELF_COLLISION_CHECKS; checks++) {
+                    let other = entry - 1u;
+                    entry = atomicLoad(&self_collision_grid[vertex_count() + other]);
+                    let other_row = i32(other / parameters.grid_width);
+                    let other_col = i32(other % parameters.grid_width);
+                    if (abs(other_row - row) <= SELF_COLLISION_SKIP && abs(other_col - col) <= SELF_COLLISION_SKIP) {
+                        continue;
+                    }
+                    let offset = position - load_position(other);
+                    let distance = length(offset);
+                    if (distance < radius && distance > 1e-6) {
+                        correction += offset / distance * (radius - distance) * 0.5;
+                    }
+                }
+            }
+        }
+    }
+    if (vertex.fixed > 0.5) {
+        correction = vec3<f32>(0.0);
+    }
+    projected[index] = vec4<f32>(correction, 0.0);
+}
+
+var<workgroup> energy_partials: array<f32, WORKGROUP_INVOCATIONS>;
+
+// Max speed of the free vertices, drives the adaptive substeps, max stretch
+// of the structural springs and total kinetic energy of the free vertices
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_reduce_stats(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = vertex_index(global_id);
+    var energy = 0.0;
+    if (index < vertex_count()) {
+        let vertex = load_vertex(index);
+        let parameters = unpack_parameters(params1, params2);
+        atomicMax(&stats.max_stretch, bitcast<u32>(1.0 + structural_strain(index, vertex, parameters)));
+        if (vertex.fixed < 0.5) {
+            let speed = length(vertex.velocity.xyz);
+            atomicMax(&stats.max_speed, bitcast<u32>(speed));
+            energy = 0.5 * vertex.mass * speed * speed;
+        }
+    }
+
+    // Sum the workgroup first, every invocation reaches the barriers
+    energy_partials[local_index] = energy;
+    for (var stride = WORKGROUP_INVOCATIONS / 2u; stride > 0u; stride = stride / 2u) {
+        workgroupBarrier();
+        if (local_index < stride) {
+            energy_partials[local_index] += energy_partials[local_index + stride];
+        }
+    }
+    if (local_index != 0u) {
+        return;
+    }
+    // No float atomics, add the workgroup sum to the bits until no other
+    // workgroup wrote in between
+    var current = atomicLoad(&stats.kinetic_energy);
+    loop {
+        let exchanged = atomicCompareExchangeWeak(&stats.kinetic_energy, current, bitcast<u32>(bitcast<f32>(current) + energy_partials[0]));
+        if (exchanged.exchanged) {
+            break;
+        }
+        current = exchanged.old_value;
+    }
+}
+
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
+fn cs_apply_selection(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = vertex_index(global_id);
+    if (index >= vertex_count()) {
+        return;
+    }
+    let weight = selection[index];
+    if (weight <= 0.0) {
+        return;
+    }
+    var vertex = load_vertex(index);
+
+    switch selection_op.kind {
+        case SELECTION_PIN: {
+            vertex.fixed = 1.0;
+            vertex.pin_target = vec4<f32>(vertex.position.xyz, 1.0);
+        }
+        case SELECTION_RELEASE: {
+            vertex.fixed = 0.0;
+            vertex.pin_target.w = 0.0;
+        }
+        case SELECTION_IMPULSE: {
+            if (vertex.fixed < 0.5) {
+                vertex.velocity += vec4<f32>(selection_op.value.xyz * weight / vertex.mass, 0.0);
+            }
+        }
+        case SELECTION_TRANSLATE: {
+            vertex.fixed = 1.0;
+            vertex.position += vec4<f32>(selection_op.value.xyz * weight, 0.0);
+            vertex.pin_target = vec4<f32>(vertex.position.xyz, 1.0);
+        }
+        // The weight is the new mass
+        case SELECTION_MASS: {
+            vertex.mass = weight;
+        }
+        default: {
+            vertex.color = mix(vertex.color, selection_op.value, weight);
+        }
+    }
+
+    store_vertex(index, vertex);
+}
+
+// Soft sphere, once per substep after the cloth stages: springs along the
+// icosphere edges, core springs pulling every vertex back to its rest offset
+// from the center, gravity, and a push from the vertices of the first cloth
+// within `contact.y`. Semi-implicit Euler from soft_previous to soft_vertices.
+@compute @workgroup_size(256)
+fn cs_soft_step(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = global_id.x;
+    if (index >= arrayLength(&soft_vertices)) {
+        return;
+    }
+    var vertex = soft_previous[index];
+    let node = soft_nodes[index];
+    let position = vertex.position.xyz;
+    let dt = soft.gravity_dt.w;
+
+    var force = soft.gravity_dt.xyz * vertex.mass;
+    for (var i = node.springs.x; i < node.springs.x + node.springs.y; i++) {
+        let spring = soft_springs[i];
+        let delta = soft_previous[spring.neighbor].position.xyz - position;
+        let distance = length(delta);
+        if (distance > 1e-6) {
+            force += soft.springs.x * (distance - spring.rest_length) * delta / distance;
+        }
+    }
+    force += soft.springs.y * (soft.center.xyz + node.rest.xyz - position);
+    for (var i = 0u; i < vertex_buffer_length(); i++) {
+        let delta = position - load_position(i);
+        let distance = length(delta);
+        if (distance < soft.contact.y && distance > 1e-6) {
+            force += soft.contact.z * (soft.contact.y - distance) * delta / distance;
+        }
+    }
+
+    let velocity = (vertex.velocity.xyz + force / vertex.mass * dt) * max(1.0 - soft.springs.z * dt, 0.0);
+    let next = position + velocity * dt;
+    vertex.position = vec4<f32>(next, vertex.position.w);
+    vertex.velocity = vec4<f32>(velocity, 0.0);
+    vertex.force = array<f32, 3>(force.x, force.y, force.z);
+    // Close enough to the surface normal while the ball keeps its shape
+    let outward = next - soft.center.xyz;
+    if (dot(outward, outward) > 0.0) {
+        vertex.normal = vec4<f32>(normalize(outward), 0.0);
+    }
+    soft_vertices[index] = vertex;
+}
+
+// Keeps every cloth vertex at least `contact.x` away from the soft sphere
+// vertices, in place of the rigid collider: the position is pushed out and
+// the velocity into the ball, relative to the ball vertex, is removed
+@compute @workgroup_size(256)
+fn cs_soft_push_cloth(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = global_id.x;
+    if (index >= vertex_buffer_length()) {
+        return;
+    }
+    var vertex = load_vertex(index);
+    if (vertex.fixed > 0.5) {
+        return;
+    }
+    var position = vertex.position.xyz;
+    var velocity = vertex.velocity.xyz;
+    for (var i = 0u; i < arrayLength(&soft_vertices); i++) {
+        let ball = soft_vertices[i];
+        let delta = position - ball.position.xyz;
+        let distance = length(delta);
+        if (distance < soft.contact.x && distance > 1e-6) {
+            let normal = delta / distance;
+            position += normal * (soft.contact.x - distance);
+            velocity -= normal * min(dot(velocity - ball.velocity.xyz, normal), 0.0);
+        }
+    }
+    vertex.position = vec4<f32>(position, vertex.position.w);
+    vertex.velocity = vec4<f32>(velocity, vertex.velocity.w);
+    store_vertex(index, vertex);
+}
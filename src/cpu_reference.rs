@@ -0,0 +1,622 @@
+/// CPU mirror of a fabric vertex, with the four-component position and
+/// velocity the shader works on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CpuVertex {
+    pub position: [f32; 4],
+    pub velocity: [f32; 4],
+    pub mass: f32,
+    pub fixed: bool,
+}
+
+/// Everything `cpu_step` needs from the uniforms, already unpacked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepParams {
+    pub grid_rows: u32,
+    pub grid_cols: u32,
+    pub dt: f32,
+    /// Structural along the rows (weft), shear and bending
+    pub stiffness: [f32; 3],
+    /// Structural along the columns
+    pub warp_stiffness: f32,
+    /// Structural, shear and bending
+    pub rest_length: [f32; 3],
+    pub max_spring_force: f32,
+    pub damping: f32,
+    /// Gravity acceleration in the frame the cloth is simulated in
+    pub gravity: [f32; 4],
+    pub gravity_enabled: bool,
+    pub springs_enabled: bool,
+    pub collision_enabled: bool,
+    /// Colliders as center xyz and radius w, and the capsule half axis (zero
+    /// for a sphere), resolved in order
+    pub colliders: Vec<([f32; 4], [f32; 4])>,
+    /// The first collider keeps the cloth inside instead of outside
+    pub sphere_inverted: bool,
+    /// Height and restitution of the ground plane, `None` without one
+    pub ground_plane: Option<[f32; 2]>,
+    pub contact_damping: f32,
+    /// Share of the sliding velocity the sphere contact takes away
+    pub sphere_friction: f32,
+    /// Share of the speed into a collider that bounces back
+    pub sphere_restitution: f32,
+    /// Velocity damping per second applied after the collision response
+    pub settle_damping: f32,
+    /// Speed below which the settled velocity is zeroed
+    pub sleep_threshold: f32,
+    /// Position Verlet instead of semi-implicit Euler, see `IntegrationMethod`
+    pub verlet: bool,
+}
+
+// Same constants as computeShader.wgsl
+const STRETCH_STIFFENING: f32 = 1.1;
+const CONTACT_MAX_SPEED: f32 = 5.0;
+const CONTACT_MARGIN: f32 = 0.1;
+const CONTACT_MIN_OFFSET: f32 = 0.05;
+
+type Vec4 = [f32; 4];
+
+fn add(a: Vec4, b: Vec4) -> Vec4 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]]
+}
+
+fn sub(a: Vec4, b: Vec4) -> Vec4 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]]
+}
+
+fn scale(a: Vec4, s: f32) -> Vec4 {
+    a.map(|c| c * s)
+}
+
+fn length(a: Vec4) -> f32 {
+    a.iter().map(|c| c * c).sum::<f32>().sqrt()
+}
+
+fn dot3(a: Vec4, b: Vec4) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// get_spring_force
+fn spring(vertex: &CpuVertex, neighbor: &CpuVertex, stiffness: f32, rest_length: f32, max_force: f32) -> Vec4 {
+    let delta = sub(neighbor.position, vertex.position);
+    let current_length = length(delta);
+    if current_length == 0.0 {
+        return [0.0; 4];
+    }
+    let direction = scale(delta, 1.0 / current_length);
+    let stretch_factor = current_length / rest_length;
+    let mut effective_stiffness = stiffness;
+    if stretch_factor > STRETCH_STIFFENING {
+        effective_stiffness *= stretch_factor * stretch_factor;
+    }
+    let force = scale(direction, (current_length - rest_length) * effective_stiffness);
+    let force_magnitude = length(force);
+    if force_magnitude > max_force {
+        return scale(force, max_force / force_magnitude);
+    }
+    force
+}
+
+// Row and column offsets of the shear springs, in the shader's order
+const SHEAR_OFFSETS: [(i64, i64); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+// Row and column offsets of the bending springs, in the shader's order
+const BENDING_OFFSETS: [(i64, i64); 4] = [(0, -2), (0, 2), (-2, 0), (2, 0)];
+
+// Index of the vertex `dr` rows and `dc` columns away, `None` past the grid
+// border instead of wrapping to another row
+fn grid_neighbor(grid_rows: u32, grid_cols: u32, index: usize, dr: i64, dc: i64) -> Option<usize> {
+    let (rows, cols) = (grid_rows as i64, grid_cols as i64);
+    let (r, c) = (index as i64 / cols + dr, index as i64 % cols + dc);
+    if r < 0 || r >= rows || c < 0 || c >= cols {
+        return None;
+    }
+    Some((r * cols + c) as usize)
+}
+
+/// Vertices the shear springs of `index` connect to: the diagonal neighbors
+/// top-left, top-right, bottom-left and bottom-right, in the order
+/// `spring_force` in computeShader.wgsl sums them. Those past the grid border
+/// are left out, so a corner has a single one, e.g. only `grid_cols + 1` for
+/// vertex 0, and the other border vertices two.
+pub fn shear_neighbors(grid_rows: u32, grid_cols: u32, index: usize) -> Vec<usize> {
+    SHEAR_OFFSETS
+        .iter()
+        .filter_map(|&(dr, dc)| grid_neighbor(grid_rows, grid_cols, index, dr, dc))
+        .collect()
+}
+
+/// Vertices the bending springs of `index` connect to: two columns left and
+/// right, then two rows up and down, in the order `spring_force` in
+/// computeShader.wgsl sums them. Those past the grid border are left out.
+pub fn bending_neighbors(grid_rows: u32, grid_cols: u32, index: usize) -> Vec<usize> {
+    BENDING_OFFSETS
+        .iter()
+        .filter_map(|&(dr, dc)| grid_neighbor(grid_rows, grid_cols, index, dr, dc))
+        .collect()
+}
+
+// spring_force, neighbors summed in the shader's order
+fn spring_force(state: &[CpuVertex], index: usize, params: &StepParams) -> Vec4 {
+    let [weft, shear, bending] = [0, 1, 2].map(|i| (params.stiffness[i], params.rest_length[i]));
+    let warp = (params.warp_stiffness, params.rest_length[0]);
+    let neighbors = [
+        (0, -1, weft),
+        (0, 1, weft),
+        (-1, 0, warp),
+        (1, 0, warp),
+    ];
+    let shear_springs = SHEAR_OFFSETS.map(|(dr, dc)| (dr, dc, shear));
+    let bending_springs = BENDING_OFFSETS.map(|(dr, dc)| (dr, dc, bending));
+
+    let vertex = &state[index];
+    let mut force = [0.0; 4];
+    for (dr, dc, (stiffness, rest_length)) in neighbors.into_iter().chain(shear_springs).chain(bending_springs) {
+        let Some(neighbor) = grid_neighbor(params.grid_rows, params.grid_cols, index, dr, dc) else {
+            continue;
+        };
+        let neighbor = &state[neighbor];
+        force = add(force, spring(vertex, neighbor, stiffness, rest_length, params.max_spring_force));
+    }
+    force
+}
+
+// resolve_spring_behavior, without the flow texture wind
+fn integrate(state: &[CpuVertex], index: usize, params: &StepParams) -> CpuVertex {
+    let vertex = state[index];
+    if vertex.fixed {
+        return vertex;
+    }
+
+    let mut force = [0.0; 4];
+    if params.springs_enabled {
+        force = add(force, spring_force(state, index, params));
+    }
+    if params.gravity_enabled {
+        force = add(force, scale(params.gravity, vertex.mass));
+    }
+    // Only xyz is integrated, w stays as it was
+    let force = [force[0], force[1], force[2], 0.0];
+    let velocity = [vertex.velocity[0], vertex.velocity[1], vertex.velocity[2], 0.0];
+    let dt = params.dt;
+
+    let (mut new_velocity, mut movement) = if params.verlet {
+        let acceleration = scale(force, 1.0 / vertex.mass);
+        let movement = add(scale(velocity, dt * (1.0 - params.damping * dt).max(0.0)), scale(acceleration, dt * dt));
+        (scale(movement, 1.0 / dt), movement)
+    } else {
+        let acceleration = scale(sub(force, scale(velocity, params.damping)), 1.0 / vertex.mass);
+        let new_velocity = add(velocity, scale(acceleration, dt));
+        (new_velocity, scale(new_velocity, dt))
+    };
+
+    let max_movement = params.rest_length[0] * 0.5;
+    let movement_length = length(movement);
+    if movement_length > max_movement {
+        movement = scale(movement, max_movement / movement_length);
+        new_velocity = scale(new_velocity, 0.9);
+    }
+    CpuVertex {
+        position: add(vertex.position, movement),
+        velocity: new_velocity,
+        ..vertex
+    }
+}
+
+// sphere_contact_velocity, `dir` is the contact normal
+fn contact_velocity(velocity: Vec4, dir: Vec4, params: &StepParams) -> Vec4 {
+    let normal_speed = dot3(velocity, dir);
+    let tangent = sub(velocity, scale(dir, normal_speed));
+    let normal = if normal_speed < 0.0 { scale(dir, -normal_speed * params.sphere_restitution) } else { scale(dir, normal_speed) };
+    let contact_damping = (1.0 - params.contact_damping * params.dt).max(0.0);
+    let raw = scale(add(scale(tangent, 1.0 - params.sphere_friction), normal), contact_damping);
+    let raw = [raw[0], raw[1], raw[2], 0.0];
+    let raw_length = length(raw);
+    if raw_length == 0.0 {
+        return [0.0; 4];
+    }
+    scale(raw, raw_length.min(CONTACT_MAX_SPEED) / raw_length)
+}
+
+// collider_sphere: the sphere around the point of the capsule axis closest to `position`
+fn collider_sphere((center_radius, half_axis): ([f32; 4], [f32; 4]), position: Vec4) -> [f32; 4] {
+    let length_squared = dot3(half_axis, half_axis);
+    if length_squared == 0.0 {
+        return center_radius;
+    }
+    let t = (dot3(sub(position, center_radius), half_axis) / length_squared).clamp(-1.0, 1.0);
+    let center = add(center_radius, scale(half_axis, t));
+    [center[0], center[1], center[2], center_radius[3]]
+}
+
+// resolve_sphere_collision and resolve_sphere_containment
+fn collide(vertex: CpuVertex, params: &StepParams, sphere: [f32; 4], inverted: bool) -> CpuVertex {
+    if vertex.fixed {
+        return vertex;
+    }
+    let [cx, cy, cz, radius] = sphere;
+    let cs = [vertex.position[0] - cx, vertex.position[1] - cy, vertex.position[2] - cz, 0.0];
+    let dist = length(cs);
+
+    let (dir, surface_distance) = if inverted {
+        if !(dist > radius - CONTACT_MARGIN && dist > 0.0) {
+            return vertex;
+        }
+        let dir = scale(cs, -1.0 / dist);
+        (dir, -(radius - CONTACT_MIN_OFFSET.max(radius - dist)))
+    } else {
+        if dist >= radius + CONTACT_MARGIN {
+            return vertex;
+        }
+        let dir = scale(cs, 1.0 / dist);
+        (dir, radius + CONTACT_MIN_OFFSET.max(dist - radius))
+    };
+    let new_position = [
+        cx + dir[0] * surface_distance,
+        cy + dir[1] * surface_distance,
+        cz + dir[2] * surface_distance,
+        vertex.position[3],
+    ];
+    CpuVertex {
+        position: new_position,
+        velocity: contact_velocity(vertex.velocity, dir, params),
+        ..vertex
+    }
+}
+
+// resolve_ground_collision
+fn collide_ground(vertex: CpuVertex, plane_y: f32, restitution: f32) -> CpuVertex {
+    if vertex.fixed || vertex.position[1] >= plane_y {
+        return vertex;
+    }
+    let mut velocity = vertex.velocity;
+    if velocity[1] < 0.0 {
+        velocity[1] = -velocity[1] * restitution;
+    }
+    let [x, _, z, w] = vertex.position;
+    CpuVertex {
+        position: [x, plane_y, z, w],
+        velocity,
+        ..vertex
+    }
+}
+
+// settle_vertex
+fn settle(vertex: CpuVertex, params: &StepParams) -> CpuVertex {
+    if vertex.fixed {
+        return vertex;
+    }
+    let velocity = scale(vertex.velocity, (1.0 - params.settle_damping * params.dt).max(0.0));
+    let velocity = if dot3(velocity, velocity).sqrt() < params.sleep_threshold {
+        [0.0, 0.0, 0.0, velocity[3]]
+    } else {
+        velocity
+    };
+    CpuVertex { velocity, ..vertex }
+}
+
+/// One simulation step of `state` (`grid_rows` x `grid_cols` vertices, row by
+/// row), mirroring the integration pass (`cs_main`) then the collision pass
+/// (`cs_collide`) of the compute shader, and with `verlet` the velocity pass
+/// (`cs_verlet_velocity`).
+///
+/// Every vertex reads its neighbors from the state before the step, like the
+/// GPU pass does from the previous vertex buffer. GPU float math is not
+/// bit-exact, so expect small differences. The wind, the flow texture wind and
+/// the constraint projection are not mirrored.
+pub fn cpu_step(state: &mut [CpuVertex], params: &StepParams) {
+    assert_eq!(state.len(), (params.grid_rows * params.grid_cols) as usize, "state must hold grid_rows * grid_cols vertices");
+    let integrated: Vec<CpuVertex> = (0..state.len()).map(|index| integrate(state, index, params)).collect();
+    for (vertex, integrated) in state.iter_mut().zip(integrated) {
+        let previous = *vertex;
+        *vertex = integrated;
+        if params.collision_enabled {
+            for (i, collider) in params.colliders.iter().enumerate() {
+                let sphere = collider_sphere(*collider, vertex.position);
+                *vertex = collide(*vertex, params, sphere, i == 0 && params.sphere_inverted);
+            }
+            if let Some([plane_y, restitution]) = params.ground_plane {
+                *vertex = collide_ground(*vertex, plane_y, restitution);
+            }
+            if !params.verlet {
+                *vertex = settle(*vertex, params);
+            }
+        }
+        if params.verlet && !vertex.fixed {
+            let moved = sub(vertex.position, previous.position);
+            vertex.velocity = scale([moved[0], moved[1], moved[2], 0.0], 1.0 / params.dt);
+            *vertex = settle(*vertex, params);
+        }
+    }
+}
+
+/// Largest differences between two states of the same cloth.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StepComparison {
+    pub max_position_error: f32,
+    pub max_velocity_error: f32,
+    /// Vertex with the largest position error
+    pub worst_vertex: usize,
+}
+
+impl StepComparison {
+    pub fn new(expected: &[CpuVertex], actual: &[CpuVertex]) -> Self {
+        // Matching non-finite values count as equal, a single one as infinitely far
+        let error = |a: [f32; 4], b: [f32; 4]| {
+            let (a, b) = ([a[0], a[1], a[2], 0.0], [b[0], b[1], b[2], 0.0]);
+            match (a.iter().all(|c| c.is_finite()), b.iter().all(|c| c.is_finite())) {
+                (true, true) => length(sub(a, b)),
+                (false, false) => 0.0,
+                _ => f32::INFINITY,
+            }
+        };
+        let mut comparison = StepComparison {
+            max_position_error: 0.0,
+            max_velocity_error: 0.0,
+            worst_vertex: 0,
+        };
+        for (index, (e, a)) in expected.iter().zip(actual).enumerate() {
+            let position_error = error(e.position, a.position);
+            if position_error > comparison.max_position_error {
+                comparison.max_position_error = position_error;
+                comparison.worst_vertex = index;
+            }
+            comparison.max_velocity_error = comparison.max_velocity_error.max(error(e.velocity, a.velocity));
+        }
+        comparison
+    }
+
+    pub fn within(&self, position_tolerance: f32, velocity_tolerance: f32) -> bool {
+        self.max_position_error <= position_tolerance && self.max_velocity_error <= velocity_tolerance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Springs at rest on the grid, no gravity, no collision and no damping
+    fn params(grid_rows: u32, grid_cols: u32) -> StepParams {
+        StepParams {
+            grid_rows,
+            grid_cols,
+            dt: 0.01,
+            stiffness: [10.0; 3],
+            warp_stiffness: 10.0,
+            rest_length: [1.0, std::f32::consts::SQRT_2, 2.0],
+            max_spring_force: 1000.0,
+            damping: 0.0,
+            gravity: [0.0, -9.8, 0.0, 0.0],
+            gravity_enabled: false,
+            springs_enabled: true,
+            collision_enabled: false,
+            colliders: Vec::new(),
+            sphere_inverted: false,
+            ground_plane: None,
+            contact_damping: 0.0,
+            sphere_friction: 0.0,
+            sphere_restitution: 0.0,
+            settle_damping: 0.0,
+            sleep_threshold: 0.0,
+            verlet: false,
+        }
+    }
+
+    // Flat grid in the xz plane, row by row
+    fn grid(grid_rows: u32, grid_cols: u32, spacing: f32) -> Vec<CpuVertex> {
+        (0..grid_rows * grid_cols)
+            .map(|index| CpuVertex {
+                position: [(index % grid_cols) as f32 * spacing, 0.0, (index / grid_cols) as f32 * spacing, 1.0],
+                velocity: [0.0; 4],
+                mass: 1.0,
+                fixed: false,
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+        let error = length(sub(actual, expected));
+        assert!(error < 1e-6, "{:?} is not {:?}", actual, expected);
+    }
+
+    // 3 rows of 4 columns:
+    //   0  1  2  3
+    //   4  5  6  7
+    //   8  9 10 11
+    #[test]
+    fn shear_neighbors_of_the_corners() {
+        assert_eq!(shear_neighbors(3, 4, 0), vec![5]);
+        assert_eq!(shear_neighbors(3, 4, 3), vec![6]);
+        assert_eq!(shear_neighbors(3, 4, 8), vec![5]);
+        assert_eq!(shear_neighbors(3, 4, 11), vec![6]);
+    }
+
+    #[test]
+    fn shear_neighbors_of_an_interior_vertex() {
+        // Top-left, top-right, bottom-left, bottom-right
+        assert_eq!(shear_neighbors(3, 4, 5), vec![0, 2, 8, 10]);
+        assert_eq!(shear_neighbors(3, 4, 6), vec![1, 3, 9, 11]);
+    }
+
+    #[test]
+    fn shear_neighbors_do_not_wrap_across_rows() {
+        // Flat index arithmetic would give 4 a bottom-left 7 and 7 a top-right
+        // 4, both at the other end of the same row
+        assert_eq!(shear_neighbors(3, 4, 4), vec![1, 9]);
+        assert_eq!(shear_neighbors(3, 4, 7), vec![2, 10]);
+    }
+
+    // 5 rows of 4 columns:
+    //    0  1  2  3
+    //    4  5  6  7
+    //    8  9 10 11
+    //   12 13 14 15
+    //   16 17 18 19
+    #[test]
+    fn bending_neighbors_skip_one_vertex() {
+        // Two columns left and right, then two rows up and down
+        assert_eq!(bending_neighbors(5, 4, 10), vec![8, 2, 18]);
+        assert_eq!(bending_neighbors(5, 4, 9), vec![11, 1, 17]);
+    }
+
+    #[test]
+    fn bending_neighbors_of_the_corners() {
+        assert_eq!(bending_neighbors(5, 4, 0), vec![2, 8]);
+        assert_eq!(bending_neighbors(5, 4, 3), vec![1, 11]);
+        assert_eq!(bending_neighbors(5, 4, 16), vec![18, 8]);
+        assert_eq!(bending_neighbors(5, 4, 19), vec![17, 11]);
+    }
+
+    #[test]
+    fn bending_neighbors_of_the_edges() {
+        assert_eq!(bending_neighbors(5, 4, 1), vec![3, 9]);
+        assert_eq!(bending_neighbors(5, 4, 4), vec![6, 12]);
+        assert_eq!(bending_neighbors(5, 4, 7), vec![5, 15]);
+        assert_eq!(bending_neighbors(5, 4, 18), vec![16, 10]);
+    }
+
+    #[test]
+    fn bending_neighbors_do_not_wrap_across_rows() {
+        // 3 + 2 and 2 + 2 land on the next row, 4 - 2 and 5 - 2 on the one before
+        assert!(!bending_neighbors(5, 4, 3).contains(&5));
+        assert!(!bending_neighbors(5, 4, 2).contains(&4));
+        assert!(!bending_neighbors(5, 4, 4).contains(&2));
+        assert!(!bending_neighbors(5, 4, 5).contains(&3));
+        for index in 0..20 {
+            for neighbor in bending_neighbors(5, 4, index) {
+                let (dr, dc) = (neighbor as i64 / 4 - index as i64 / 4, neighbor as i64 % 4 - index as i64 % 4);
+                assert!(matches!((dr.abs(), dc.abs()), (0, 2) | (2, 0)), "{} -> {}", index, neighbor);
+            }
+        }
+    }
+
+    #[test]
+    fn bending_neighbors_of_a_narrow_grid() {
+        // Too narrow for any bending spring along the rows
+        assert_eq!(bending_neighbors(3, 2, 2), Vec::<usize>::new());
+        assert_eq!(bending_neighbors(3, 2, 0), vec![4]);
+        assert_eq!(bending_neighbors(1, 1, 0), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn grid_at_rest_stays_put() {
+        let params = params(4, 5);
+        let mut state = grid(4, 5, 1.0);
+        let before = state.clone();
+        cpu_step(&mut state, &params);
+        assert_eq!(StepComparison::new(&before, &state).max_position_error, 0.0);
+        assert!(state.iter().all(|vertex| vertex.velocity == [0.0; 4]));
+    }
+
+    #[test]
+    fn gravity_is_integrated_semi_implicitly() {
+        let mut params = params(2, 2);
+        params.springs_enabled = false;
+        params.gravity_enabled = true;
+        let mut state = grid(2, 2, 1.0);
+        cpu_step(&mut state, &params);
+        for (vertex, start) in state.iter().zip(grid(2, 2, 1.0)) {
+            assert_close(vertex.velocity, [0.0, -0.098, 0.0, 0.0]);
+            assert_close(vertex.position, add(start.position, [0.0, -0.00098, 0.0, 0.0]));
+        }
+    }
+
+    #[test]
+    fn fixed_vertices_do_not_move() {
+        let mut params = params(3, 3);
+        params.gravity_enabled = true;
+        let mut state = grid(3, 3, 1.5);
+        state[0].fixed = true;
+        let pinned = state[0];
+        cpu_step(&mut state, &params);
+        assert_eq!(state[0], pinned);
+        assert!(state[4].position[1] < 0.0);
+    }
+
+    #[test]
+    fn stretched_spring_pulls_both_ends_together() {
+        let params = params(1, 2);
+        let mut state = grid(1, 2, 2.0);
+        cpu_step(&mut state, &params);
+        // Stretched to twice its rest length, so the stiffness is scaled by 4
+        assert_close(state[0].velocity, [0.4, 0.0, 0.0, 0.0]);
+        assert_close(state[1].velocity, [-0.4, 0.0, 0.0, 0.0]);
+        assert_close(state[0].position, [0.004, 0.0, 0.0, 1.0]);
+        assert_close(state[1].position, [1.996, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn vertex_inside_a_sphere_is_pushed_out_and_bounces() {
+        let mut params = params(1, 1);
+        params.collision_enabled = true;
+        params.colliders = vec![([0.0, 0.0, 0.0, 1.0], [0.0; 4])];
+        params.sphere_restitution = 0.5;
+        let mut state = vec![CpuVertex {
+            position: [0.0, 0.5, 0.0, 1.0],
+            velocity: [0.0, -1.0, 0.0, 0.0],
+            mass: 1.0,
+            fixed: false,
+        }];
+        cpu_step(&mut state, &params);
+        assert_close(state[0].position, [0.0, 1.0 + CONTACT_MIN_OFFSET, 0.0, 1.0]);
+        assert_close(state[0].velocity, [0.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn ground_plane_stops_the_fall() {
+        let mut params = params(1, 1);
+        params.collision_enabled = true;
+        params.ground_plane = Some([0.0, 0.5]);
+        let mut state = vec![CpuVertex {
+            position: [0.0, 0.005, 0.0, 1.0],
+            velocity: [0.0, -1.0, 0.0, 0.0],
+            mass: 1.0,
+            fixed: false,
+        }];
+        cpu_step(&mut state, &params);
+        assert_close(state[0].position, [0.0, 0.0, 0.0, 1.0]);
+        assert_close(state[0].velocity, [0.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn verlet_velocity_is_the_distance_moved() {
+        let mut params = params(2, 2);
+        params.springs_enabled = false;
+        params.gravity_enabled = true;
+        params.verlet = true;
+        let start = grid(2, 2, 1.0);
+        let mut state = start.clone();
+        cpu_step(&mut state, &params);
+        for (vertex, start) in state.iter().zip(&start) {
+            let moved = sub(vertex.position, start.position);
+            assert_close(vertex.velocity, scale(moved, 1.0 / params.dt));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "grid_rows * grid_cols")]
+    fn state_must_match_the_grid() {
+        cpu_step(&mut grid(2, 2, 1.0), &params(3, 3));
+    }
+
+    #[test]
+    fn comparison_reports_the_worst_vertex() {
+        let expected = grid(2, 3, 1.0);
+        let mut actual = expected.clone();
+        actual[4].position[1] = 0.25;
+        actual[1].velocity[0] = 0.5;
+        let comparison = StepComparison::new(&expected, &actual);
+        assert_eq!(comparison.worst_vertex, 4);
+        assert_eq!(comparison.max_position_error, 0.25);
+        assert_eq!(comparison.max_velocity_error, 0.5);
+        assert!(comparison.within(0.25, 0.5));
+        assert!(!comparison.within(0.2, 0.5));
+    }
+
+    #[test]
+    fn comparison_treats_a_lone_nan_as_infinitely_far() {
+        let expected = grid(1, 2, 1.0);
+        let mut actual = expected.clone();
+        actual[1].position[0] = f32::NAN;
+        assert_eq!(StepComparison::new(&expected, &actual).max_position_error, f32::INFINITY);
+        assert_eq!(StepComparison::new(&actual, &actual).max_position_error, 0.0);
+    }
+}
@@ -0,0 +1,53 @@
+use std::f32::consts::PI;
+
+/// Tileable tangent-space normal map of a plain weave, `size` x `size` RGBA8
+/// texels (normal xyz mapped to rgb, alpha 255).
+///
+/// The tile holds `threads` x `threads` crossings; at each one either the warp
+/// (running along v) or the weft (running along u) is on top, alternating like
+/// a checkerboard.
+pub fn weave_normal_map(size: u32, threads: u32) -> Vec<u8> {
+    let height = |x: i64, y: i64| {
+        let n = size as i64;
+        let (x, y) = (x.rem_euclid(n) as f32, y.rem_euclid(n) as f32);
+        let cu = x / size as f32 * threads as f32;
+        let cv = y / size as f32 * threads as f32;
+        let warp_on_top = (cu.floor() as u32 + cv.floor() as u32).is_multiple_of(2);
+        // Round thread cross-section, the thread underneath sits lower
+        if warp_on_top {
+            (PI * cu.fract()).sin()
+        } else {
+            (PI * cv.fract()).sin()
+        }
+    };
+
+    // Bump height in texels, controls how steep the weave looks
+    let scale = size as f32 / threads as f32 * 0.15;
+    let mut texels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size as i64 {
+        for x in 0..size as i64 {
+            let dx = (height(x + 1, y) - height(x - 1, y)) * 0.5 * scale;
+            let dy = (height(x, y + 1) - height(x, y - 1)) * 0.5 * scale;
+            let length = (dx * dx + dy * dy + 1.0).sqrt();
+            let normal = [-dx / length, -dy / length, 1.0 / length];
+            texels.extend(normal.map(|c| ((c * 0.5 + 0.5) * 255.0).round() as u8));
+            texels.push(255);
+        }
+    }
+    texels
+}
+
+/// `size` x `size` RGBA8 checkerboard of `squares` x `squares` light and dark
+/// squares, the default fabric texture: straight lines make the deformation
+/// easy to follow.
+pub fn checkerboard(size: u32, squares: u32) -> Vec<u8> {
+    let squares = squares.clamp(1, size.max(1));
+    let mut texels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let light = (x * squares / size + y * squares / size).is_multiple_of(2);
+            texels.extend_from_slice(if light { &[235, 235, 235, 255] } else { &[40, 60, 120, 255] });
+        }
+    }
+    texels
+}
@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+/// Divergence of two simulations started from the same state, one of them with
+/// a single vertex nudged.
+///
+/// Each sample is the largest distance between matching vertices of the two
+/// runs, `frame` frames after the nudge. The positions are read back without
+/// stalling the GPU, so samples come every frame or two rather than for every
+/// frame. For a chaotic system the divergence grows roughly exponentially
+/// until it saturates at the size of the motion, so the growth rate of the
+/// early samples is a measure of the solver's sensitivity.
+#[derive(Clone, Debug)]
+pub struct DivergenceRun {
+    pub vertex: u32,
+    pub nudge: f32,
+    pub frames: u32,
+    samples: Vec<(u32, f32)>,
+}
+
+impl DivergenceRun {
+    pub fn new(vertex: u32, nudge: f32, frames: u32) -> Self {
+        DivergenceRun {
+            vertex,
+            nudge,
+            frames,
+            samples: Vec::with_capacity(frames as usize),
+        }
+    }
+
+    /// Records the largest distance between `reference` and `perturbed` after
+    /// `frame` frames. Frames not past the last recorded one are ignored.
+    pub fn record(&mut self, frame: u32, reference: &[[f32; 3]], perturbed: &[[f32; 3]]) {
+        if self.samples.last().is_some_and(|(last, _)| frame <= *last) {
+            return;
+        }
+        let max_distance = reference
+            .iter()
+            .zip(perturbed)
+            .map(|(a, b)| ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt())
+            .fold(0.0, f32::max);
+        self.samples.push((frame, max_distance));
+    }
+
+    /// Frame and divergence of every sample, in frame order.
+    pub fn samples(&self) -> &[(u32, f32)] {
+        &self.samples
+    }
+
+    /// Frame of the last sample, 0 before the first one.
+    pub fn last_frame(&self) -> u32 {
+        self.samples.last().map_or(0, |(frame, _)| *frame)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.last_frame() >= self.frames
+    }
+
+    /// Average exponential growth per frame, `ln(d_n / d_0) / (frame_n -
+    /// frame_0)`, over the samples before the divergence first exceeds
+    /// `saturation`. `None` until there are two usable samples.
+    pub fn growth_rate(&self, saturation: f32) -> Option<f32> {
+        let (first_frame, first) = *self.samples.first().filter(|(_, d)| *d > 0.0)?;
+        let (frame, last) = self.samples.iter().skip(1).take_while(|(_, d)| *d < saturation).last()?;
+        Some((last / first).ln() / (frame - first_frame) as f32)
+    }
+
+    /// Writes the curve as CSV: `frame,max_divergence`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "frame,max_divergence")?;
+        for (frame, divergence) in &self.samples {
+            writeln!(writer, "{},{:e}", frame, divergence)?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Run with one sample per (frame, divergence)
+    fn run_with(samples: &[(u32, f32)]) -> DivergenceRun {
+        let mut run = DivergenceRun::new(0, 1e-4, 10);
+        for &(frame, divergence) in samples {
+            run.record(frame, &[[0.0; 3]], &[[divergence, 0.0, 0.0]]);
+        }
+        run
+    }
+
+    #[test]
+    fn record_keeps_the_largest_distance() {
+        let mut run = DivergenceRun::new(1, 1e-4, 10);
+        let reference = [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [2.0, 0.0, 0.0]];
+        let perturbed = [[0.0, 0.0, 0.0], [1.0, 1.0, 4.0], [2.0, 0.0, 1.0]];
+        run.record(1, &reference, &perturbed);
+        assert_eq!(run.samples(), &[(1, 3.0)]);
+    }
+
+    #[test]
+    fn record_ignores_frames_already_sampled() {
+        let run = run_with(&[(1, 0.1), (3, 0.2), (3, 0.5), (2, 0.5), (4, 0.3)]);
+        assert_eq!(run.samples(), &[(1, 0.1), (3, 0.2), (4, 0.3)]);
+        assert_eq!(run.last_frame(), 4);
+    }
+
+    #[test]
+    fn done_once_the_last_frame_is_sampled() {
+        assert!(!run_with(&[]).is_done());
+        assert!(!run_with(&[(2, 0.1), (9, 0.1)]).is_done());
+        assert!(run_with(&[(2, 0.1), (11, 0.1)]).is_done());
+    }
+
+    #[test]
+    fn growth_rate_of_an_exponential_over_uneven_frames() {
+        let samples: Vec<(u32, f32)> = [1, 2, 4, 5, 7].iter().map(|&frame| (frame, 1e-4 * (0.5 * frame as f32).exp())).collect();
+        let rate = run_with(&samples).growth_rate(1.0).unwrap();
+        assert!((rate - 0.5).abs() < 1e-4, "{}", rate);
+    }
+
+    #[test]
+    fn growth_rate_stops_at_saturation() {
+        // Doubles every frame until it saturates at 0.1
+        let rate = run_with(&[(1, 0.01), (2, 0.02), (3, 0.04), (4, 0.1), (5, 0.1)]).growth_rate(0.1).unwrap();
+        assert!((rate - 2f32.ln()).abs() < 1e-5, "{}", rate);
+    }
+
+    #[test]
+    fn growth_rate_needs_two_usable_samples() {
+        assert_eq!(run_with(&[]).growth_rate(1.0), None);
+        assert_eq!(run_with(&[(1, 0.01)]).growth_rate(1.0), None);
+        assert_eq!(run_with(&[(1, 0.0), (2, 0.01)]).growth_rate(1.0), None);
+        assert_eq!(run_with(&[(1, 0.01), (2, 2.0)]).growth_rate(1.0), None);
+    }
+
+    #[test]
+    fn csv_has_one_line_per_sample() {
+        let mut csv = Vec::new();
+        run_with(&[(1, 0.5), (3, 0.25)]).write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "frame,max_divergence\n1,5e-1\n3,2.5e-1\n");
+    }
+}
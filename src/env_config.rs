@@ -0,0 +1,205 @@
+use std::env;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::param_bounds::ParamBounds;
+
+/// A `CLOTH_*` environment variable that is set but can't be used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvError {
+    pub name: &'static str,
+    pub value: String,
+    pub expected: String,
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {}={:?}: expected {}", self.name, self.value, self.expected)
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+// Value of `name`, `None` when unset or blank
+fn var(name: &'static str) -> Result<Option<String>, EnvError> {
+    match env::var(name) {
+        Ok(value) if value.trim().is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value.trim().to_string())),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(value)) => Err(EnvError {
+            name,
+            value: value.to_string_lossy().into_owned(),
+            expected: "valid UTF-8".to_string(),
+        }),
+    }
+}
+
+fn parse_in<T: FromStr + PartialOrd + fmt::Display>(name: &'static str, value: &str, range: &RangeInclusive<T>) -> Result<T, EnvError> {
+    let error = || EnvError {
+        name,
+        value: value.to_string(),
+        expected: format!("a number in {}..={}", range.start(), range.end()),
+    };
+    let parsed = value.trim().parse::<T>().map_err(|_| error())?;
+    if range.contains(&parsed) {
+        Ok(parsed)
+    } else {
+        Err(error())
+    }
+}
+
+/// A float within `bounds`, e.g. `CLOTH_GRAVITY=9.81`.
+pub fn f32_var(name: &'static str, bounds: ParamBounds) -> Result<Option<f32>, EnvError> {
+    var(name)?.map(|value| parse_f32(name, &value, bounds)).transpose()
+}
+
+/// An integer within `range`, e.g. `CLOTH_ITERATIONS=4`.
+pub fn u32_var(name: &'static str, range: RangeInclusive<u32>) -> Result<Option<u32>, EnvError> {
+    var(name)?.map(|value| parse_u32(name, &value, range)).transpose()
+}
+
+/// A 64-bit integer within `range`, e.g. `CLOTH_SEED=12345678901`.
+pub fn u64_var(name: &'static str, range: RangeInclusive<u64>) -> Result<Option<u64>, EnvError> {
+    var(name)?.map(|value| parse_u64(name, &value, range)).transpose()
+}
+
+/// Either one float used for all `N` values or `N` comma-separated floats,
+/// e.g. `CLOTH_STIFFNESS=40` or `CLOTH_STIFFNESS=25,15,5`.
+pub fn f32_list_var<const N: usize>(name: &'static str, bounds: ParamBounds) -> Result<Option<[f32; N]>, EnvError> {
+    var(name)?.map(|value| parse_f32_list(name, &value, bounds)).transpose()
+}
+
+/// One of `choices`, compared case-insensitively, e.g. `CLOTH_PRECISION=f16`.
+pub fn choice_var<T: Copy>(name: &'static str, choices: &[(&str, T)]) -> Result<Option<T>, EnvError> {
+    var(name)?.map(|value| parse_choice(name, &value, choices)).transpose()
+}
+
+/// `value` of the setting `name` as a float within `bounds`. The `parse_*`
+/// functions read the values of the variables above and of the command line
+/// options, `name` is only used in the error.
+pub fn parse_f32(name: &'static str, value: &str, bounds: ParamBounds) -> Result<f32, EnvError> {
+    parse_in(name, value, &bounds.range())
+}
+
+pub fn parse_u32(name: &'static str, value: &str, range: RangeInclusive<u32>) -> Result<u32, EnvError> {
+    parse_in(name, value, &range)
+}
+
+pub fn parse_u64(name: &'static str, value: &str, range: RangeInclusive<u64>) -> Result<u64, EnvError> {
+    parse_in(name, value, &range)
+}
+
+pub fn parse_f32_list<const N: usize>(name: &'static str, value: &str, bounds: ParamBounds) -> Result<[f32; N], EnvError> {
+    let parts: Vec<&str> = value.split(',').collect();
+    let mut values = [0.0; N];
+    match parts.len() {
+        1 => values = [parse_in(name, parts[0], &bounds.range())?; N],
+        len if len == N => {
+            for (slot, part) in values.iter_mut().zip(&parts) {
+                *slot = parse_in(name, part, &bounds.range())?;
+            }
+        }
+        _ => {
+            return Err(EnvError {
+                name,
+                value: value.to_string(),
+                expected: format!("1 or {} comma-separated numbers", N),
+            })
+        }
+    }
+    Ok(values)
+}
+
+pub fn parse_choice<T: Copy>(name: &'static str, value: &str, choices: &[(&str, T)]) -> Result<T, EnvError> {
+    choices
+        .iter()
+        .find(|(label, _)| label.eq_ignore_ascii_case(value.trim()))
+        .map(|(_, choice)| *choice)
+        .ok_or_else(|| EnvError {
+            name,
+            value: value.to_string(),
+            expected: format!("one of {}", choices.iter().map(|(label, _)| *label).collect::<Vec<_>>().join(", ")),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHOICES: [(&str, u8); 2] = [("f32", 32), ("f16", 16)];
+
+    fn expected<T: fmt::Debug>(result: Result<T, EnvError>) -> String {
+        result.unwrap_err().expected
+    }
+
+    #[test]
+    fn numbers_are_trimmed_and_range_checked() {
+        assert_eq!(parse_u32("CLOTH_GRID", " 64 ", 2..=512), Ok(64));
+        assert_eq!(parse_f32("CLOTH_GRAVITY", "9.81", ParamBounds::GRAVITY), Ok(9.81));
+        assert_eq!(parse_u64("CLOTH_SEED", "12345678901", 0..=u64::MAX), Ok(12345678901));
+        assert_eq!(expected(parse_u32("CLOTH_GRID", "1", 2..=512)), "a number in 2..=512");
+        assert_eq!(expected(parse_u32("CLOTH_GRID", "513", 2..=512)), "a number in 2..=512");
+        assert_eq!(expected(parse_f32("CLOTH_GRAVITY", "-1", ParamBounds::GRAVITY)), "a number in 0..=20");
+        assert_eq!(expected(parse_f32("CLOTH_GRAVITY", "NaN", ParamBounds::GRAVITY)), "a number in 0..=20");
+    }
+
+    #[test]
+    fn malformed_numbers_fail() {
+        for value in ["", "ten", "4.5", "-3", "0x10"] {
+            let error = parse_u32("CLOTH_ITERATIONS", value, 0..=16).unwrap_err();
+            assert_eq!(error, EnvError { name: "CLOTH_ITERATIONS", value: value.to_string(), expected: "a number in 0..=16".to_string() });
+        }
+        assert!(parse_u64("CLOTH_SEED", "18446744073709551616", 0..=u64::MAX).is_err());
+        assert!(parse_f32("CLOTH_GRAVITY", "9,81", ParamBounds::GRAVITY).is_err());
+    }
+
+    #[test]
+    fn lists_take_one_or_n_values() {
+        assert_eq!(parse_f32_list::<3>("CLOTH_STIFFNESS", "40", ParamBounds::STIFFNESS), Ok([40.0; 3]));
+        assert_eq!(parse_f32_list::<3>("CLOTH_STIFFNESS", "25, 15,5", ParamBounds::STIFFNESS), Ok([25.0, 15.0, 5.0]));
+        assert_eq!(expected(parse_f32_list::<3>("CLOTH_STIFFNESS", "25,15", ParamBounds::STIFFNESS)), "1 or 3 comma-separated numbers");
+        assert_eq!(expected(parse_f32_list::<3>("CLOTH_STIFFNESS", "1,2,3,4", ParamBounds::STIFFNESS)), "1 or 3 comma-separated numbers");
+        assert_eq!(expected(parse_f32_list::<3>("CLOTH_STIFFNESS", "25,,5", ParamBounds::STIFFNESS)), "a number in 0..=200");
+        assert_eq!(expected(parse_f32_list::<3>("CLOTH_STIFFNESS", "25,15,500", ParamBounds::STIFFNESS)), "a number in 0..=200");
+    }
+
+    #[test]
+    fn choices_ignore_case() {
+        assert_eq!(parse_choice("CLOTH_PRECISION", " F16", &CHOICES), Ok(16));
+        let error = parse_choice("CLOTH_PRECISION", "f64", &CHOICES).unwrap_err();
+        assert_eq!(error.expected, "one of f32, f16");
+        assert_eq!(error.to_string(), "invalid CLOTH_PRECISION=\"f64\": expected one of f32, f16");
+    }
+
+    // Each test sets its own variables, the tests run in parallel
+    #[test]
+    fn unset_and_blank_variables_are_none() {
+        env::remove_var("CLOTH_TEST_UNSET");
+        env::set_var("CLOTH_TEST_BLANK", "  ");
+        assert_eq!(u32_var("CLOTH_TEST_UNSET", 0..=1), Ok(None));
+        assert_eq!(f32_var("CLOTH_TEST_BLANK", ParamBounds::GRAVITY), Ok(None));
+        assert_eq!(choice_var("CLOTH_TEST_BLANK", &CHOICES), Ok(None));
+    }
+
+    #[test]
+    fn set_variables_are_parsed() {
+        env::set_var("CLOTH_TEST_LIST", "1,2");
+        env::set_var("CLOTH_TEST_SEED", "7");
+        env::set_var("CLOTH_TEST_RANGE", "3");
+        assert_eq!(f32_list_var::<2>("CLOTH_TEST_LIST", ParamBounds::STIFFNESS), Ok(Some([1.0, 2.0])));
+        assert_eq!(u64_var("CLOTH_TEST_SEED", 0..=10), Ok(Some(7)));
+        let error = u32_var("CLOTH_TEST_RANGE", 0..=2).unwrap_err();
+        assert_eq!((error.name, error.value.as_str()), ("CLOTH_TEST_RANGE", "3"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_variables_fail() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        env::set_var("CLOTH_TEST_BYTES", OsStr::from_bytes(b"f\xff"));
+        assert_eq!(expected(choice_var("CLOTH_TEST_BYTES", &CHOICES)), "valid UTF-8");
+    }
+}
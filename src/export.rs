@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+
+/// Writes a triangle mesh as ASCII PLY (`format ascii 1.0`).
+///
+/// Each `vertex` element has float `x y z` followed by uchar `red green blue`,
+/// each `face` element is a uchar-counted list of int `vertex_indices` (always
+/// 3 here). `indices` is a triangle list, as used by the fabric index buffer.
+pub fn write_ply<W: Write>(mut writer: W, positions: &[[f32; 3]], colors: &[[u8; 3]], indices: &[u32]) -> io::Result<()> {
+    let face_count = indices.len() / 3;
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment cloth_sim fabric export")?;
+    writeln!(writer, "element vertex {}", positions.len())?;
+    writeln!(writer, "property float x")?;
+    writeln!(writer, "property float y")?;
+    writeln!(writer, "property float z")?;
+    writeln!(writer, "property uchar red")?;
+    writeln!(writer, "property uchar green")?;
+    writeln!(writer, "property uchar blue")?;
+    writeln!(writer, "element face {}", face_count)?;
+    writeln!(writer, "property list uchar int vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for (position, color) in positions.iter().zip(colors) {
+        writeln!(
+            writer,
+            "{} {} {} {} {} {}",
+            position[0], position[1], position[2], color[0], color[1], color[2]
+        )?;
+    }
+    for triangle in indices.chunks_exact(3) {
+        writeln!(writer, "3 {} {} {}", triangle[0], triangle[1], triangle[2])?;
+    }
+    writer.flush()
+}
+
+/// Writes a triangle mesh as Wavefront OBJ.
+///
+/// One `v` line per position, then one `vn` line per normal when `normals` is
+/// given (matching the positions one to one), then an `f` line per triangle of
+/// the `indices` triangle list. OBJ indices start at 1, so every index is
+/// offset by one, and faces reference the normal of the same index (`a//a`).
+pub fn write_obj<W: Write>(mut writer: W, positions: &[[f32; 3]], normals: Option<&[[f32; 3]]>, indices: &[u32]) -> io::Result<()> {
+    writeln!(writer, "# cloth_sim fabric export")?;
+    for position in positions {
+        writeln!(writer, "v {} {} {}", position[0], position[1], position[2])?;
+    }
+    if let Some(normals) = normals {
+        for normal in normals {
+            writeln!(writer, "vn {} {} {}", normal[0], normal[1], normal[2])?;
+        }
+    }
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+        if normals.is_some() {
+            writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        } else {
+            writeln!(writer, "f {} {} {}", a, b, c)?;
+        }
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2 rows of 3 columns in the xz plane, two triangles per quad
+    fn grid() -> (Vec<[f32; 3]>, Vec<u32>) {
+        let (rows, cols) = (2, 3);
+        let positions = (0..rows * cols).map(|i| [(i % cols) as f32 * 0.5, 1.0, (i / cols) as f32 * 0.5]).collect();
+        let mut indices = Vec::new();
+        for row in 0..rows - 1 {
+            for col in 0..cols - 1 {
+                let i = row * cols + col;
+                indices.extend([i, i + cols, i + 1, i + 1, i + cols, i + cols + 1]);
+            }
+        }
+        (positions, indices)
+    }
+
+    fn lines(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> Vec<String> {
+        let mut bytes = Vec::new();
+        write(&mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn ply_header_counts_the_vertices_and_faces() {
+        let (positions, indices) = grid();
+        let colors = vec![[255, 128, 0]; positions.len()];
+        let lines = lines(|out| write_ply(out, &positions, &colors, &indices));
+        assert_eq!(lines[..2], ["ply", "format ascii 1.0"]);
+        assert!(lines.contains(&"element vertex 6".to_string()));
+        assert!(lines.contains(&"element face 4".to_string()));
+        let body = &lines[lines.iter().position(|line| line == "end_header").unwrap() + 1..];
+        assert_eq!(body.len(), 6 + 4);
+        assert_eq!(body[0], "0 1 0 255 128 0");
+        assert_eq!(body[5], "1 1 0.5 255 128 0");
+        // PLY indices start at 0
+        assert_eq!(body[6], "3 0 3 1");
+        assert_eq!(body[9], "3 2 4 5");
+    }
+
+    #[test]
+    fn obj_faces_are_one_based() {
+        let (positions, indices) = grid();
+        let lines = lines(|out| write_obj(out, &positions, None, &indices));
+        let vertices: Vec<_> = lines.iter().filter(|line| line.starts_with("v ")).collect();
+        let faces: Vec<_> = lines.iter().filter(|line| line.starts_with("f ")).collect();
+        assert_eq!(vertices.len(), 6);
+        assert_eq!(vertices[1], "v 0.5 1 0");
+        assert_eq!(faces, ["f 1 4 2", "f 2 4 5", "f 2 5 3", "f 3 5 6"]);
+        assert!(!lines.iter().any(|line| line.starts_with("vn ")));
+    }
+
+    #[test]
+    fn obj_faces_reference_the_normals() {
+        let (positions, indices) = grid();
+        let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+        let lines = lines(|out| write_obj(out, &positions, Some(&normals), &indices));
+        assert_eq!(lines.iter().filter(|line| *line == "vn 0 1 0").count(), 6);
+        // Every `v` line comes before the first `vn` line
+        let last_vertex = lines.iter().rposition(|line| line.starts_with("v ")).unwrap();
+        let first_normal = lines.iter().position(|line| line.starts_with("vn ")).unwrap();
+        assert!(last_vertex < first_normal);
+        assert_eq!(lines.last().unwrap(), "f 3//3 5//5 6//6");
+    }
+}
@@ -0,0 +1,79 @@
+use std::io::{self, BufRead};
+
+/// 2D field of wind velocities, `width` x `height` texels stored row by row
+/// from the top, one xyz vector per texel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowMap {
+    pub width: u32,
+    pub height: u32,
+    pub texels: Vec<[f32; 3]>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid PFM: {}", message))
+}
+
+// Next whitespace separated header token, consuming the single whitespace after it
+fn header_token<R: BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut token = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0].is_ascii_whitespace() {
+            if token.is_empty() {
+                continue;
+            }
+            break;
+        }
+        token.push(byte[0]);
+    }
+    String::from_utf8(token).map_err(|_| invalid("header is not ASCII"))
+}
+
+/// Reads a Portable Float Map, the raw float image format most fluid solvers
+/// and image tools can write.
+///
+/// `PF` files give three channels per texel, used as is; `Pf` files give one,
+/// used as the x component. The scale line only gives the byte order
+/// (negative means little endian), its magnitude is ignored.
+pub fn read_pfm<R: BufRead>(mut reader: R) -> io::Result<FlowMap> {
+    let channels = match header_token(&mut reader)?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid("expected PF or Pf")),
+    };
+    let width: u32 = header_token(&mut reader)?.parse().map_err(|_| invalid("bad width"))?;
+    let height: u32 = header_token(&mut reader)?.parse().map_err(|_| invalid("bad height"))?;
+    let scale: f32 = header_token(&mut reader)?.parse().map_err(|_| invalid("bad scale"))?;
+    if width == 0 || height == 0 {
+        return Err(invalid("empty image"));
+    }
+
+    let mut data = vec![0u8; width as usize * height as usize * channels * 4];
+    reader.read_exact(&mut data)?;
+    let values: Vec<f32> = data
+        .chunks_exact(4)
+        .map(|bytes| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            if scale < 0.0 {
+                f32::from_le_bytes(bytes)
+            } else {
+                f32::from_be_bytes(bytes)
+            }
+        })
+        .collect();
+
+    // PFM rows go from the bottom up
+    let row_length = width as usize * channels;
+    let texels = values
+        .chunks_exact(row_length)
+        .rev()
+        .flat_map(|row| row.chunks_exact(channels))
+        .map(|texel| match texel {
+            [x, y, z] => [*x, *y, *z],
+            [x] => [*x, 0.0, 0.0],
+            _ => unreachable!(),
+        })
+        .collect();
+    Ok(FlowMap { width, height, texels })
+}
@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+/// Wall clock durations of the last `capacity` frames, in seconds.
+///
+/// A ring buffer: once full, every new frame pushes the oldest one out, so
+/// `average` follows the recent frame rate without being thrown off by a
+/// single stutter.
+#[derive(Clone, Debug)]
+pub struct FrameTimes {
+    capacity: usize,
+    samples: VecDeque<f32>,
+}
+
+impl FrameTimes {
+    pub fn new(capacity: usize) -> Self {
+        FrameTimes {
+            capacity: capacity.max(1),
+            samples: VecDeque::with_capacity(capacity.max(1)),
+        }
+    }
+
+    pub fn push(&mut self, delta_time: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(delta_time);
+    }
+
+    /// Duration of the last frame, 0 before the first one.
+    pub fn last(&self) -> f32 {
+        self.samples.back().copied().unwrap_or(0.0)
+    }
+
+    /// Mean duration over the recorded frames, 0 before the first one.
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.samples.iter().sum::<f32>() / self.samples.len() as f32
+        }
+    }
+
+    /// Longest recorded frame, the stutter the plot is scaled to.
+    pub fn max(&self) -> f32 {
+        self.samples.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Oldest first.
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -0,0 +1,133 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use wgpu_bootstrap::{cgmath, wgpu};
+
+/// What the simulation needs from the window: the device and queue, and the
+/// format and size of the color target it renders to. Implemented by the
+/// window's `wgpu_bootstrap::Context` and by `HeadlessContext`, which runs the
+/// same code without a window (tests and benchmarks).
+pub trait GpuContext {
+    fn device(&self) -> &wgpu::Device;
+    fn queue(&self) -> &wgpu::Queue;
+    fn format(&self) -> wgpu::TextureFormat;
+    fn depth_stencil_format(&self) -> wgpu::TextureFormat;
+    /// Size of the color target in pixels
+    fn size(&self) -> cgmath::Vector2<f32>;
+}
+
+/// The context `InstanceApp` and its parts take, either kind of `GpuContext`.
+pub type Context = dyn GpuContext;
+
+impl GpuContext for wgpu_bootstrap::Context {
+    fn device(&self) -> &wgpu::Device {
+        wgpu_bootstrap::Context::device(self)
+    }
+
+    fn queue(&self) -> &wgpu::Queue {
+        wgpu_bootstrap::Context::queue(self)
+    }
+
+    fn format(&self) -> wgpu::TextureFormat {
+        wgpu_bootstrap::Context::format(self)
+    }
+
+    fn depth_stencil_format(&self) -> wgpu::TextureFormat {
+        wgpu_bootstrap::Context::depth_stencil_format(self)
+    }
+
+    fn size(&self) -> cgmath::Vector2<f32> {
+        wgpu_bootstrap::Context::size(self)
+    }
+}
+
+/// A device and queue without a window or surface. Renders go to offscreen
+/// targets, see `InstanceApp::capture_frame`.
+pub struct HeadlessContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    size: cgmath::Vector2<f32>,
+}
+
+impl HeadlessContext {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+    pub const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    /// Opens the default adapter with its own limits and, when it has them,
+    /// timestamp queries for `InstanceApp::last_compute_ms`. `None` when the
+    /// machine has no adapter (e.g. CI without a
+    /// GPU or software renderer). `width` and `height` only set the aspect of
+    /// the camera and the size of captures. Only the primary backends are
+    /// tried: naga can't translate the compare exchange loop of
+    /// `cs_reduce_stats` to GLSL, so the GL backend can't build the pipelines.
+    pub fn new(width: u32, height: u32) -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::PRIMARY,
+            ..Default::default()
+        });
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+        let (device, queue) = block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Headless Device"),
+                required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                required_limits: adapter.limits(),
+                memory_hints: wgpu::MemoryHints::default(),
+            },
+            None,
+        ))
+        .ok()?;
+        Some(HeadlessContext {
+            device,
+            queue,
+            size: cgmath::Vector2::new(width as f32, height as f32),
+        })
+    }
+}
+
+impl GpuContext for HeadlessContext {
+    fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    fn format(&self) -> wgpu::TextureFormat {
+        Self::FORMAT
+    }
+
+    fn depth_stencil_format(&self) -> wgpu::TextureFormat {
+        Self::DEPTH_STENCIL_FORMAT
+    }
+
+    fn size(&self) -> cgmath::Vector2<f32> {
+        self.size
+    }
+}
+
+// Wakes the thread blocked in `block_on`
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// Runs `future` on the current thread. wgpu's adapter and device requests
+// resolve without an executor on native backends, this only parks in between
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut task_context = TaskContext::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut task_context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use wgpu_bootstrap::egui;
+
+/// Compass widget editing a unit direction vector.
+///
+/// The disc is the direction seen from above (x to the right, z downwards), the
+/// center meaning straight down or straight up. Dragging moves the tip of the
+/// arrow inside the disc and keeps the current hemisphere; clicking the center
+/// button flips between pointing down (filled dot) and up (hollow dot).
+pub fn gravity_compass(direction: &mut [f32; 3]) -> impl egui::Widget + '_ {
+    move |ui: &mut egui::Ui| {
+        let size = ui.spacing().interact_size.y * 5.0;
+        let (rect, mut response) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click_and_drag());
+        let center = rect.center();
+        let radius = size / 2.0 - 2.0;
+        let downwards = direction[1] <= 0.0;
+
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let offset = (pointer - center) / radius;
+            if response.clicked() && offset.length() < 0.15 {
+                direction[1] = -direction[1];
+                if direction[1] == 0.0 {
+                    direction[1] = if downwards { 1.0 } else { -1.0 };
+                }
+            } else if response.dragged() || response.clicked() {
+                let offset = if offset.length() > 1.0 { offset.normalized() } else { offset };
+                let vertical = (1.0 - offset.length_sq()).max(0.0).sqrt();
+                *direction = [offset.x, if downwards { -vertical } else { vertical }, offset.y];
+            }
+            response.mark_changed();
+        }
+
+        if ui.is_rect_visible(rect) {
+            let visuals = ui.style().interact(&response);
+            let painter = ui.painter();
+            painter.circle(center, radius, visuals.bg_fill, visuals.bg_stroke);
+            painter.line_segment([center - egui::vec2(radius, 0.0), center + egui::vec2(radius, 0.0)], visuals.bg_stroke);
+            painter.line_segment([center - egui::vec2(0.0, radius), center + egui::vec2(0.0, radius)], visuals.bg_stroke);
+
+            let tip = center + egui::vec2(direction[0], direction[2]) * radius;
+            painter.arrow(center, tip - center, visuals.fg_stroke);
+            if direction[1] <= 0.0 {
+                painter.circle_filled(tip, 4.0, visuals.fg_stroke.color);
+            } else {
+                painter.circle_stroke(tip, 4.0, visuals.fg_stroke);
+            }
+        }
+
+        response.on_hover_text("Gravity direction seen from above, click the center to flip up/down")
+    }
+}
@@ -0,0 +1,76 @@
+use wgpu_bootstrap::egui;
+
+/// Colors of the heatmap gradient, evenly spaced from the low to the high end
+/// of the range (a viridis-like ramp that stays readable in grayscale).
+///
+/// The compute shader gets the same stops through `wgsl`, so the legend and the
+/// rendered colors can't drift apart.
+pub const GRADIENT: [[f32; 3]; 5] = [
+    [0.267, 0.005, 0.329],
+    [0.230, 0.322, 0.546],
+    [0.128, 0.567, 0.551],
+    [0.369, 0.789, 0.383],
+    [0.993, 0.906, 0.144],
+];
+
+/// Gradient color at `t` in 0..=1, linear between the stops.
+pub fn color(t: f32) -> [f32; 3] {
+    let scaled = t.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f32;
+    let low = (scaled.floor() as usize).min(GRADIENT.len() - 2);
+    let f = scaled - low as f32;
+    let (a, b) = (GRADIENT[low], GRADIENT[low + 1]);
+    [a[0] + (b[0] - a[0]) * f, a[1] + (b[1] - a[1]) * f, a[2] + (b[2] - a[2]) * f]
+}
+
+/// WGSL for `GRADIENT` and a `heatmap_color(t)` matching `color`, prepended to
+/// the compute shader.
+pub fn wgsl() -> String {
+    let stops: Vec<String> = GRADIENT
+        .iter()
+        .map(|[r, g, b]| format!("vec3<f32>({:?}, {:?}, {:?})", r, g, b))
+        .collect();
+    format!(
+        "const HEATMAP_STOPS = array<vec3<f32>, {count}>({stops});\n\
+         fn heatmap_color(t: f32) -> vec3<f32> {{\n\
+         \x20   let scaled = clamp(t, 0.0, 1.0) * f32({count}u - 1u);\n\
+         \x20   let low = min(u32(floor(scaled)), {count}u - 2u);\n\
+         \x20   var stops = HEATMAP_STOPS;\n\
+         \x20   return mix(stops[low], stops[low + 1u], scaled - f32(low));\n\
+         }}\n",
+        count = GRADIENT.len(),
+        stops = stops.join(", "),
+    )
+}
+
+fn color32(t: f32) -> egui::Color32 {
+    let [r, g, b] = color(t).map(|c| (c * 255.0).round() as u8);
+    egui::Color32::from_rgb(r, g, b)
+}
+
+/// Color bar of the gradient with the values at both ends, `label` names the
+/// quantity next to the maximum.
+pub fn legend<'a>(min: f32, max: f32, label: &'a str) -> impl egui::Widget + 'a {
+    move |ui: &mut egui::Ui| {
+        ui.vertical(|ui| {
+            let width = ui.available_width().min(240.0);
+            let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 14.0), egui::Sense::hover());
+            let segments = 48;
+            let segment_width = rect.width() / segments as f32;
+            for i in 0..segments {
+                let min_x = rect.min.x + i as f32 * segment_width;
+                let segment = egui::Rect::from_min_max(egui::pos2(min_x, rect.min.y), egui::pos2(min_x + segment_width + 0.5, rect.max.y));
+                ui.painter().rect_filled(segment, 0.0, color32((i as f32 + 0.5) / segments as f32));
+            }
+            ui.horizontal(|ui| {
+                ui.set_width(width);
+                ui.monospace(format!("{:.3}", min));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.monospace(format!("{:.3}", max));
+                    ui.weak(label);
+                });
+            });
+            response
+        })
+        .inner
+    }
+}
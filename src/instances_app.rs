@@ -1,439 +1,2566 @@
-use wgpu_bootstrap::{
-    cgmath, egui,
-    util::{
-        geometry::icosphere,
-        orbit_camera::{CameraUniform, OrbitCamera},
-    },
-    wgpu::{self, util::DeviceExt, ComputePass},
-    App, Context,
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+
+use crate::cpu_reference::{self, CpuVertex, StepComparison, StepParams};
+use crate::divergence::DivergenceRun;
+use crate::export;
+use crate::flow_map;
+use crate::frame_times::FrameTimes;
+use crate::gpu_context::Context;
+use crate::orbit_camera::{CameraUniform, OrbitCamera};
+use crate::param_bounds::ParamBounds;
+use crate::pin_animation::PinTrack;
+use crate::profiler::Profiler;
+use crate::projection::{self, Lens};
+use crate::ruler::{Ruler, RulerPoint};
+use crate::selection::Selection;
+use crate::sim_config::{ColliderConfig, ConfigError, SimConfig};
+use crate::vertex::{
+    pack_half4, PackedVertex, Vertex, FIXED_OFFSET, FORCE_OFFSET, INTACT_SPRINGS, PACKED_FIXED_OFFSET, PACKED_PIN_TARGET_OFFSET, PACKED_SPRINGS_OFFSET, PACKED_VELOCITY_OFFSET, PIN_TARGET_OFFSET, SPRINGS_OFFSET,
+    VELOCITY_OFFSET,
+};
+use wgpu_bootstrap::{cgmath, egui, wgpu, App};
+use cgmath::{InnerSpace, Rotation};
+
+mod builder;
+mod cloth;
+mod collider;
+mod panel;
+mod render;
+mod scenes;
+mod setup;
+mod soft_sphere;
+mod step;
+
+pub use builder::{ClothBuilder, ClothConfig, ClothTransform, FabricConfig, GridLimitError};
+pub use collider::Collider;
+pub use scenes::{ClothPreset, MaterialParam, Scene};
+use cloth::{
+    clamp_gravity, create_detail_texture, create_fabric_texture, create_flow_texture, guarded_mass, write_config_params, Cloth, ClothBindings, ClothDesc,
+    FabricMaterial, SimParams1, SimParams2,
 };
+use collider::{collider_vertex, ColliderMesh, GpuCollider, SphereMotion};
+use render::{create_render_pipeline, DepthPrepassPipelines, RenderStyle};
+use soft_sphere::SoftSphere;
+
+/// Storage format of the fabric vertex buffer.
+///
+/// `F16` packs positions and velocities as half floats (48 bytes per vertex
+/// instead of 112). It relies only on the core WGSL `pack2x16float` builtins, so
+/// it does not need `wgpu::Features::SHADER_F16`, which wgpu 22 can't use from
+/// WGSL anyway (naga rejects `enable f16`). Positions are only accurate to
+/// about 1/1000 of their magnitude: slow motion far from the origin gets
+/// rounded away, so keep `F32` (the default) when accuracy matters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Precision {
+    F32,
+    F16,
+}
+
+impl Precision {
+    /// Largest position and velocity differences `validate_step` accepts. GPU
+    /// float math (fused multiply-adds, sqrt) is not bit-exact, F16 also rounds.
+    pub fn validation_tolerance(self) -> (f32, f32) {
+        match self {
+            Precision::F32 => (1e-4, 0.02),
+            Precision::F16 => (5e-3, 0.05),
+        }
+    }
+
+    fn vertex_desc(self) -> wgpu::VertexBufferLayout<'static> {
+        match self {
+            Precision::F32 => Vertex::desc(),
+            Precision::F16 => PackedVertex::desc(),
+        }
+    }
+
+    // The fabric vertex buffer read per instance as one end of a spring, the
+    // position at location `end` plus the intact springs of the start, see vs_spring
+    fn spring_end_desc(self, end: u32) -> wgpu::VertexBufferLayout<'static> {
+        let attributes: &'static [wgpu::VertexAttribute] = match (self, end) {
+            (Precision::F32, 0) => &[
+                wgpu::VertexAttribute { offset: 0, shader_location: 0, format: wgpu::VertexFormat::Float32x4 },
+                wgpu::VertexAttribute { offset: SPRINGS_OFFSET, shader_location: 2, format: wgpu::VertexFormat::Uint32 },
+            ],
+            (Precision::F32, _) => &[wgpu::VertexAttribute { offset: 0, shader_location: 1, format: wgpu::VertexFormat::Float32x4 }],
+            (Precision::F16, 0) => &[
+                wgpu::VertexAttribute { offset: 0, shader_location: 0, format: wgpu::VertexFormat::Float16x4 },
+                wgpu::VertexAttribute { offset: PACKED_SPRINGS_OFFSET, shader_location: 2, format: wgpu::VertexFormat::Uint32 },
+            ],
+            (Precision::F16, _) => &[wgpu::VertexAttribute { offset: 0, shader_location: 1, format: wgpu::VertexFormat::Float16x4 }],
+        };
+        wgpu::VertexBufferLayout {
+            array_stride: self.vertex_size(),
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes,
+        }
+    }
+
+    // Every FORCE_GLYPH_STEP-th vertex of a row read per instance, its position
+    // and net force, see vs_force. `None` in half precision, which doesn't
+    // store the force.
+    fn force_glyph_desc(self) -> Option<wgpu::VertexBufferLayout<'static>> {
+        match self {
+            Precision::F32 => Some(wgpu::VertexBufferLayout {
+                array_stride: self.vertex_size() * FORCE_GLYPH_STEP as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &[
+                    wgpu::VertexAttribute { offset: 0, shader_location: 0, format: wgpu::VertexFormat::Float32x4 },
+                    wgpu::VertexAttribute { offset: FORCE_OFFSET, shader_location: 1, format: wgpu::VertexFormat::Float32x3 },
+                ],
+            }),
+            Precision::F16 => None,
+        }
+    }
+
+    fn storage_shader(self) -> &'static str {
+        match self {
+            Precision::F32 => include_str!("vertexStorageF32.wgsl"),
+            Precision::F16 => include_str!("vertexStorageF16.wgsl"),
+        }
+    }
+
+    fn vertices_from_bytes(self, bytes: &[u8]) -> Vec<Vertex> {
+        match self {
+            Precision::F32 => bytemuck::cast_slice(bytes).to_vec(),
+            Precision::F16 => bytemuck::cast_slice::<u8, PackedVertex>(bytes).iter().map(Vertex::from).collect(),
+        }
+    }
+
+    /// Bytes per fabric vertex in the vertex buffers, 112 or 48.
+    pub fn vertex_size(self) -> wgpu::BufferAddress {
+        match self {
+            Precision::F32 => std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            Precision::F16 => std::mem::size_of::<PackedVertex>() as wgpu::BufferAddress,
+        }
+    }
+
+    // Bytes of the position field, at the start of the vertex in both layouts.
+    // The velocity and the pin target are stored the same way.
+    fn position_bytes(self, position: [f32; 4]) -> Vec<u8> {
+        match self {
+            Precision::F32 => bytemuck::cast_slice(&position).to_vec(),
+            Precision::F16 => bytemuck::cast_slice(&pack_half4(position)).to_vec(),
+        }
+    }
+
+    // Offsets of the velocity and of the fixed flag, an f32 in both layouts
+    fn velocity_offset(self) -> wgpu::BufferAddress {
+        match self {
+            Precision::F32 => VELOCITY_OFFSET,
+            Precision::F16 => PACKED_VELOCITY_OFFSET,
+        }
+    }
+
+    fn fixed_offset(self) -> wgpu::BufferAddress {
+        match self {
+            Precision::F32 => FIXED_OFFSET,
+            Precision::F16 => PACKED_FIXED_OFFSET,
+        }
+    }
+
+    fn pin_target_offset(self) -> wgpu::BufferAddress {
+        match self {
+            Precision::F32 => PIN_TARGET_OFFSET,
+            Precision::F16 => PACKED_PIN_TARGET_OFFSET,
+        }
+    }
+
+    fn vertex_bytes(self, vertices: &[Vertex]) -> Vec<u8> {
+        match self {
+            Precision::F32 => bytemuck::cast_slice(vertices).to_vec(),
+            Precision::F16 => {
+                let packed: Vec<PackedVertex> = vertices.iter().map(PackedVertex::from).collect();
+                bytemuck::cast_slice(&packed).to_vec()
+            }
+        }
+    }
+}
 
+/// Workgroups of the per-vertex compute passes.
+///
+/// `Linear` is the 1D layout: 256 threads per workgroup along the vertex
+/// index, so a workgroup covers a strip of 2.5 rows of a 100 wide grid. The
+/// tiled layouts cover the grid with square workgroups of 8x8 or 16x16
+/// threads, one per column and row, dispatched as
+/// `(cols / side, rows / side)`: the neighbors a vertex reads are mostly
+/// in its own tile. `Linear` is the default, see `benches/workgroups.rs`. The
+/// soft sphere and the tearing pass stay linear.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WorkgroupLayout {
+    Linear,
+    Tiled8,
+    Tiled16,
+}
+
+impl WorkgroupLayout {
+    pub const NAMES: [(&'static str, WorkgroupLayout); 3] = [
+        ("linear", WorkgroupLayout::Linear),
+        ("8x8", WorkgroupLayout::Tiled8),
+        ("16x16", WorkgroupLayout::Tiled16),
+    ];
+
+    // Threads per workgroup along x and y, 256 or a power of two in total for
+    // the reduction of cs_reduce_stats
+    fn size(self) -> [u32; 2] {
+        match self {
+            WorkgroupLayout::Linear => [256, 1],
+            WorkgroupLayout::Tiled8 => [8, 8],
+            WorkgroupLayout::Tiled16 => [16, 16],
+        }
+    }
+
+    // Prepended to the compute shader, see vertex_index in computeShader.wgsl
+    fn wgsl(self) -> String {
+        let [width, height] = self.size();
+        format!(
+            "const WORKGROUP_WIDTH = {}u;\nconst WORKGROUP_HEIGHT = {}u;\nconst WORKGROUP_INVOCATIONS = {}u;\n",
+            width,
+            height,
+            width * height
+        )
+    }
+
+    fn workgroup_count(self, rows: u32, cols: u32) -> [u32; 2] {
+        match self.size() {
+            [width, 1] => [(rows * cols).div_ceil(width), 1],
+            [width, height] => [cols.div_ceil(width), rows.div_ceil(height)],
+        }
+    }
+}
+
+// Operation applied to a selection by cs_apply_selection
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 4],    // 16 bytes (0-15)
-    color: [f32; 4],       // 16 bytes (16-31)
-    mass: f32,             // 4 bytes  (32-35)
-    padding1: [f32; 3],    // 12 bytes padding to align velocity
-    velocity: [f32; 4],    // 16 bytes (48-63)
-    fixed: f32,            // 4 bytes  (64-67)
-    padding2: [f32; 3],    // 12 bytes final padding
+struct SelectionOp {
+    kind: u32,            // 4 bytes, see SELECTION_* in computeShader.wgsl
+    _padding: [u32; 3],   // 12 bytes to align value
+    value: [f32; 4],      // 16 bytes, impulse, color or offset, unused for mass
 }
 
-// Simulation parameters
-#[repr(C, align(16))]  // Added align(16) to force 16-byte alignment
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct SimParams1 {
-    grid_k_radius: [f32; 4],  // grid_rows, grid_cols, k_spring and sphere_radius 16 bytes
-    sphere_center: [f32; 4],  // 16 bytes
+impl SelectionOp {
+    const PIN: u32 = 0;
+    const RELEASE: u32 = 1;
+    const IMPULSE: u32 = 2;
+    const RECOLOR: u32 = 3;
+    const TRANSLATE: u32 = 4;
+    // The selection weights are the new masses
+    const MASS: u32 = 5;
+
+    fn new(kind: u32, value: [f32; 4]) -> Self {
+        SelectionOp { kind, _padding: [0; 3], value }
+    }
+}
+
+/// Most colliders a scene can have, see `InstanceApp::set_colliders`.
+pub const MAX_COLLIDERS: usize = 16;
+// Radius of the default sphere, centered on the origin
+const DEFAULT_COLLIDER_RADIUS: f32 = 1.4;
+// Share of the speed into a collider a vertex bounces back with
+const DEFAULT_SPHERE_RESTITUTION: f32 = 0.7;
+// Half length and radius of the capsule of the "Bar instead of sphere" checkbox, along x
+const DEFAULT_BAR: [f32; 2] = [4.0, 0.3];
+// Self collision radius the panel checkbox starts with, a bit under the default rest length
+const DEFAULT_SELF_COLLISION_RADIUS: f32 = 0.05;
+
+// Half the side of the floor quad drawn at the ground plane
+const GROUND_HALF_SIZE: f32 = 20.0;
+// The floor is drawn this far below the plane so cloth resting on it doesn't z-fight
+const GROUND_DRAW_OFFSET: f32 = 0.01;
+// Ground plane the panel checkbox turns on, just under the default sphere
+const DEFAULT_GROUND_PLANE: [f32; 2] = [-1.5, 0.2];
+// Warm shade multiplied into the back faces of the fabric
+const DEFAULT_BACKFACE_TINT: [f32; 3] = [1.0, 0.8, 0.6];
+
+// Orbit radius of the camera at startup, and the range the mouse wheel keeps it in
+const DEFAULT_CAMERA_RADIUS: f32 = 7.0;
+const MIN_CAMERA_RADIUS: f32 = 5.0;
+const MAX_CAMERA_RADIUS: f32 = 500.0;
+// Range of `set_fov`, in degrees
+const FOV_RANGE: RangeInclusive<f32> = 10.0..=120.0;
+// Closest near plane `set_clip_planes` accepts
+const MIN_NEAR_PLANE: f32 = 0.01;
+
+// Physics step used when the motion is calm
+const DEFAULT_TIME_STEP: f32 = 0.0016;
+// A step may move the fastest vertex by at most this fraction of the structural
+// rest length before the frame is split into substeps
+const MAX_STEP_DISPLACEMENT: f32 = 0.25;
+const MAX_SUBSTEPS: u32 = 8;
+// Rim light of the sphere, white at half strength
+const SPHERE_RIM: [f32; 4] = [1.0, 1.0, 1.0, 0.5];
+// Velocity change of the gust the W key triggers
+const GUST_STRENGTH: f32 = 3.0;
+// Amplitude (world units) and frequency (per second) of the "Bob sphere" checkbox
+const SPHERE_BOB: (f32, f32) = (0.8, 0.5);
+// Distance of the `ClothPreset::Flag` plane behind the ball center, and its wind
+// velocity and turbulence
+const FLAG_DEPTH: f32 = 2.0;
+const FLAG_WIND: ([f32; 3], f32) = ([4.0, 0.0, 1.5], 4.0);
+// Rows and columns between two force glyphs
+const FORCE_GLYPH_STEP: u32 = 8;
+// Distance the arrow keys move the nudged corner per frame
+const CORNER_NUDGE_STEP: f32 = 0.02;
+// Range of `set_iterations`
+const SOLVER_ITERATIONS: RangeInclusive<u32> = 1..=32;
+// Range of `set_time_scale`, the `[` and `]` keys halve and double it
+const TIME_SCALE_RANGE: RangeInclusive<f32> = 0.05..=8.0;
+// Settle damping added while `set_auto_stabilize` reacts to an energy spike,
+// and the frames it lasts
+const STABILIZE_DAMPING: f32 = 30.0;
+const STABILIZE_FRAMES: u32 = 60;
+// Kinetic energy below which no growth counts as a spike, a cloth starting to
+// fall from rest grows many times over from almost nothing
+const STABILIZE_MIN_ENERGY: f32 = 1.0;
+// Default of `set_energy_growth_limit`
+const DEFAULT_ENERGY_GROWTH_LIMIT: f32 = 4.0;
+// Defaults of `set_settle_threshold`: max speed, and the frames in a row it
+// has to stay below it
+const DEFAULT_SETTLE_SPEED: f32 = 0.05;
+const DEFAULT_SETTLE_FRAMES: u32 = 30;
+// Substep limit the "Real time" checkbox turns on, see `set_max_substep_dt`
+const DEFAULT_MAX_SUBSTEP_DT: f32 = 0.004;
+// Step the "Fixed rate" checkbox turns on, see `set_fixed_step`
+const DEFAULT_FIXED_STEP: f32 = 1.0 / 120.0;
+// Size of the SimStats struct in computeShader.wgsl
+const SIM_STATS_SIZE: wgpu::BufferAddress = 16;
+
+/// Per-frame statistics reduced on the GPU, see `InstanceApp::last_stats`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SimStats {
+    /// Highest speed of a free vertex
+    pub max_speed: f32,
+    /// Longest structural spring over its rest length, 1 when none is stretched
+    pub max_stretch: f32,
+    /// Sum of `0.5 * mass * speed²` over the free vertices
+    pub kinetic_energy: f32,
+}
+
+impl SimStats {
+    // Layout of SimStats in computeShader.wgsl
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let [max_speed, max_stretch, kinetic_energy]: [u32; 3] = bytemuck::pod_read_unaligned(&bytes[..12]);
+        SimStats {
+            max_speed: f32::from_bits(max_speed),
+            max_stretch: f32::from_bits(max_stretch),
+            kinetic_energy: f32::from_bits(kinetic_energy),
+        }
+    }
+
+    // Maxima of the maxima, the energies add up
+    fn combine(self, other: SimStats) -> SimStats {
+        SimStats {
+            max_speed: self.max_speed.max(other.max_speed),
+            max_stretch: self.max_stretch.max(other.max_stretch),
+            kinetic_energy: self.kinetic_energy + other.kinetic_energy,
+        }
+    }
 }
-#[repr(C, align(16))]  // Added align(16) to force 16-byte alignment
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct SimParams2 {
-    stiffness: [f32; 4],    // 16 bytes, aligned to 16
-    rest_length: [f32; 4],  // 16 bytes, aligned to 16
-    gravity: [f32; 4],      // 16 bytes, aligned to 16
-    _padding: [f32; 4]      // 16-byte alignment
+
+const MAX_CONSTRAINT_ITERATIONS: u32 = 16;
+
+/// Parts of the solver that can be switched off to see what each one does.
+/// Everything is enabled by default.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StageToggles {
+    pub gravity: bool,
+    pub springs: bool,
+    pub constraints: bool,
+    pub collision: bool,
+    /// Holds fixed vertices on their pin targets, see `cs_hold_pins`
+    pub pins: bool,
 }
 
-impl Vertex {
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                // Position
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                // Color
-                wgpu::VertexAttribute {
-                    offset: 16,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                // Mass
-                wgpu::VertexAttribute {
-                    offset: 32,
-                    shader_location: 2,
-                    format: wgpu::VertexFormat::Float32,
-                },
-                // Velocity
-                wgpu::VertexAttribute {
-                    offset: 48,
-                    shader_location: 3,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                // Fixed
-                wgpu::VertexAttribute {
-                    offset: 64,
-                    shader_location: 4,
-                    format: wgpu::VertexFormat::Float32,
-                },
-            ],
+impl Default for StageToggles {
+    fn default() -> Self {
+        StageToggles {
+            gravity: true,
+            springs: true,
+            constraints: true,
+            collision: true,
+            pins: true,
+        }
+    }
+}
+
+/// What the fabric color shows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    /// The vertex colors (default)
+    Material,
+    /// A color per compute workgroup that integrated the vertex, shows the dispatch tiling
+    Workgroup,
+    /// Heatmap of the vertex speed
+    Speed,
+    /// Heatmap of the largest stretch of the vertex's structural springs, relative to the rest length
+    Strain,
+    /// The row and column each vertex was built at, red across the columns and green along the
+    /// rows, blue where the compute shader's `row * grid_cols + col` indexing disagrees
+    Grid,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 5] = [ColorMode::Material, ColorMode::Workgroup, ColorMode::Speed, ColorMode::Strain, ColorMode::Grid];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Material => "Material",
+            ColorMode::Workgroup => "Workgroup",
+            ColorMode::Speed => "Speed",
+            ColorMode::Strain => "Strain",
+            ColorMode::Grid => "Grid",
+        }
+    }
+
+    /// Quantity and default value range of the heatmap modes, `None` for the others.
+    pub fn heatmap(self) -> Option<(&'static str, [f32; 2])> {
+        match self {
+            ColorMode::Material | ColorMode::Workgroup | ColorMode::Grid => None,
+            ColorMode::Speed => Some(("speed", [0.0, 2.0])),
+            ColorMode::Strain => Some(("strain", [0.0, 0.1])),
+        }
+    }
+
+    // Matches the COLOR_MODE_* constants of the shaders
+    fn index(self) -> f32 {
+        match self {
+            ColorMode::Material => 0.0,
+            ColorMode::Workgroup => 1.0,
+            ColorMode::Speed => 2.0,
+            ColorMode::Strain => 3.0,
+            ColorMode::Grid => 4.0,
+        }
+    }
+}
+
+/// Named combination of the rendering toggles, picked from the UI.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenderPreset {
+    /// Perspective camera over the usual background, vertex colors (default)
+    Standard,
+    /// High-contrast technical look: orthographic camera, unlit flat fill with
+    /// the structural edges drawn over it, on a flat blue background
+    Blueprint,
+}
+
+impl RenderPreset {
+    pub const ALL: [RenderPreset; 2] = [RenderPreset::Standard, RenderPreset::Blueprint];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderPreset::Standard => "Standard",
+            RenderPreset::Blueprint => "Blueprint",
+        }
+    }
+}
+
+/// Clear color of the window, passed to `Runner::new` by `main`. It is fixed
+/// once the window exists, `InstanceApp::set_background` paints over it.
+pub const CLEAR_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 206, 27);
+// Backgrounds the B key cycles through after the clear color: dark gray, white and black
+const BACKGROUND_PRESETS: [[f32; 3]; 3] = [[0.08, 0.08, 0.1], [1.0, 1.0, 1.0], [0.0, 0.0, 0.0]];
+
+// Colors of the blueprint preset
+const BLUEPRINT_BACKGROUND: [f32; 3] = [0.04, 0.16, 0.36];
+const BLUEPRINT_FILL: [f32; 3] = [0.08, 0.24, 0.48];
+const BLUEPRINT_EDGE: [f32; 3] = [0.85, 0.93, 1.0];
+
+// Vertices per side of the default cloth, and the sizes accepted for it
+const DEFAULT_GRID_SIZE: u32 = 100;
+const GRID_SIZE_RANGE: RangeInclusive<u32> = 2..=512;
+
+// Width of the default cloth
+const DEFAULT_SIDE_LENGTH: f32 = 6.0;
+/// Largest starting offset of a vertex along each axis with `ClothBuilder::seed`,
+/// as a share of the grid spacing.
+pub const SEED_JITTER: f32 = 0.05;
+
+// Trailing copies drawn by the motion blur
+const MOTION_BLUR_SAMPLES: u32 = 4;
+
+// Side of the built-in weave normal map, in texels
+const DETAIL_MAP_SIZE: u32 = 64;
+// Side of the default checkerboard fabric texture, in texels
+const FABRIC_TEXTURE_SIZE: u32 = 256;
+
+/// How the stretch-limiting constraint projection is dispatched.
+///
+/// After integration, every structural spring stretched past 110% of its rest
+/// length is projected back, moving both ends by their inverse mass.
+///
+/// - `Jacobi` computes all corrections from the same positions into a scratch
+///   buffer and applies them in a second dispatch (ping-pong). Fully parallel
+///   and order independent, but a correction only reaches the next vertex in the
+///   next iteration, so long chains need many iterations to converge.
+/// - `RedBlack` is Gauss-Seidel on a checkerboard coloring: the two colors are
+///   projected in place one after the other, and the second half already sees
+///   the first half's corrections. It converges about twice as fast per
+///   iteration and needs no scratch copy, but the result depends on the color
+///   order, which can show as a slight drift on regular grids.
+///
+/// Every iteration costs two dispatches either way. Too few iterations leave
+/// the cloth stretchy; the corrections also change the velocities, so many
+/// iterations on a violently stretched cloth can add visible energy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintSolver {
+    Jacobi,
+    RedBlack,
+}
+
+/// How `cs_main` advances the fabric vertices.
+///
+/// - `Euler` is semi-implicit Euler on the stored velocity (the default). The
+///   collision pass sets the velocity response of contacts explicitly.
+/// - `Verlet` is position Verlet: each vertex moves by its last displacement,
+///   damped, plus `a * dt²`. The previous positions are the other vertex
+///   buffer, and a last pass per step derives the velocity from the positions
+///   before and after it. The position corrections of the constraints and the
+///   collisions therefore become motion, which keeps stiff springs (structural
+///   stiffness well above 25) from building up energy. Collision contacts are
+///   inelastic, the contact velocity settings have no lasting effect.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IntegrationMethod {
+    Euler,
+    Verlet,
+}
+
+impl IntegrationMethod {
+    pub const ALL: [IntegrationMethod; 2] = [IntegrationMethod::Euler, IntegrationMethod::Verlet];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntegrationMethod::Euler => "Euler",
+            IntegrationMethod::Verlet => "Verlet",
+        }
+    }
+
+    // Matches the INTEGRATION_* constants of the compute shader
+    fn index(self) -> f32 {
+        match self {
+            IntegrationMethod::Euler => 0.0,
+            IntegrationMethod::Verlet => 1.0,
+        }
+    }
+}
+
+// Frames the frame time overlay averages and plots, about two seconds at 60 FPS
+const FRAME_TIME_SAMPLES: usize = 120;
+
+// Vertex id labels are only drawn for grids up to this size (32x32)
+const MAX_LABELED_VERTICES: usize = 1024;
+
+// Largest distance in world units between the picking ray and a vertex the ruler snaps to
+const RULER_PICK_DISTANCE: f32 = 0.1;
+
+// Structural stiffness of each cloth of the "Stiffness strip" scene
+const STIFFNESS_STRIP_VALUES: [f32; 5] = [5.0, 15.0, 25.0, 50.0, 100.0];
+
+/// How the fabric faces are drawn, see `InstanceApp::set_fabric_view`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FabricView {
+    #[default]
+    Solid,
+    /// Every triangle edge, or the structural edges when the device can't draw
+    /// polygons as lines
+    Wireframe,
+    Points,
+}
+
+impl FabricView {
+    pub const ALL: [FabricView; 3] = [FabricView::Solid, FabricView::Wireframe, FabricView::Points];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FabricView::Solid => "Solid",
+            FabricView::Wireframe => "Wireframe",
+            FabricView::Points => "Points",
+        }
+    }
+
+    fn next(self) -> FabricView {
+        match self {
+            FabricView::Solid => FabricView::Wireframe,
+            FabricView::Wireframe => FabricView::Points,
+            FabricView::Points => FabricView::Solid,
         }
     }
 }
 
+// A fabric vertex held by the mouse, see `InstanceApp::set_grab_enabled`
+struct Grab {
+    cloth: usize,
+    vertex: usize,
+    // Where the vertex goes next, on the plane facing the camera through the
+    // point it was grabbed at
+    target: cgmath::Vector3<f32>,
+    plane_point: cgmath::Vector3<f32>,
+    // Pinned before the grab, it stays pinned when let go
+    was_fixed: bool,
+    held: bool,
+}
+
+// A running `PinTrack` with the positions its vertices started from
+struct PinAnimation {
+    track: PinTrack,
+    base_positions: Vec<[f32; 4]>,
+    elapsed: f32,
+}
 
 pub struct InstanceApp {
     sphere_vertex_buffer: wgpu::Buffer,
     sphere_index_buffer: wgpu::Buffer,
     render_pipeline: wgpu::RenderPipeline,
+    fabric_pipeline: wgpu::RenderPipeline,
+    fabric_culled_pipeline: wgpu::RenderPipeline,
+    prepass_pipelines: DepthPrepassPipelines,
+    // `None` without `Features::POLYGON_MODE_LINE`, `lines_pipeline` stands in
+    wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    lines_pipeline: wgpu::RenderPipeline,
+    points_pipeline: wgpu::RenderPipeline,
+    fabric_view: FabricView,
+    smear_pipeline: wgpu::RenderPipeline,
+    edge_pipeline: wgpu::RenderPipeline,
+    spring_pipeline: wgpu::RenderPipeline,
+    background_pipeline: wgpu::RenderPipeline,
     compute_pipeline: wgpu::ComputePipeline,
+    collide_pipeline: wgpu::ComputePipeline,
+    stats_pipeline: wgpu::ComputePipeline,
+    selection_pipeline: wgpu::ComputePipeline,
+    heatmap_pipeline: wgpu::ComputePipeline,
+    normals_pipeline: wgpu::ComputePipeline,
+    tear_pipeline: wgpu::ComputePipeline,
+    // Clear, bin and collide, see cs_self_collide
+    self_collision_pipelines: [wgpu::ComputePipeline; 3],
+    verlet_velocity_pipeline: wgpu::ComputePipeline,
+    hold_pins_pipeline: wgpu::ComputePipeline,
+    jacobi_pipelines: [wgpu::ComputePipeline; 2],
+    red_black_pipelines: [wgpu::ComputePipeline; 2],
+    // Step and push, see cs_soft_step
+    soft_pipelines: [wgpu::ComputePipeline; 2],
+    soft_sphere_layout: wgpu::BindGroupLayout,
+    soft_sphere: Option<SoftSphere>,
     num_sphere_indices: u32,
+    // Colliders, uploaded to `ClothBindings::collider_buffer` when dirty
+    colliders: Vec<Collider>,
+    colliders_dirty: bool,
+    // Moves the first collider each simulated frame, `None` keeps it still
+    sphere_motion: Option<SphereMotion>,
+    // Height and restitution of the ground plane, `None` without one
+    ground_plane: Option<[f32; 2]>,
+    ground_pipeline: wgpu::RenderPipeline,
+    // Floor quad, moved to the plane height when `ground_dirty`
+    ground_vertex_buffer: wgpu::Buffer,
+    ground_dirty: bool,
+    // Wind xyz and turbulence amplitude, see `set_wind`
+    wind: [f32; 4],
+    wind_direction: [f32; 3],
+    // Simulated seconds since the scene started, drives the turbulence
+    elapsed_time: f32,
+    // Frames simulated since the app was built, stamps the position readbacks
+    simulated_frames: u64,
+    sphere_material_buffer: wgpu::Buffer,
+    sphere_debug_color_buffer: wgpu::Buffer,
+    sphere_material_bind_group: wgpu::BindGroup,
+    // Material of the flat background, its tint is the color
+    background_material_buffer: wgpu::Buffer,
+    background_material_bind_group: wgpu::BindGroup,
+    ground_material_buffer: wgpu::Buffer,
+    ground_material_bind_group: wgpu::BindGroup,
     camera: OrbitCamera,
-    compute_bind_group: wgpu::BindGroup,
-    sim_params1_buffer: wgpu::Buffer,
-    sim_params2_buffer: wgpu::Buffer,
-    fabric_vertex_buffer: wgpu::Buffer,
-    fabric_index_buffer: wgpu::Buffer,
-    sim_params1: SimParams1,
-    sim_params2: SimParams2,
+    // Radius, longitude and latitude the camera starts with, see `reset_camera`
+    camera_home: (f32, f32, f32),
+    lens: Lens,
+    // Surface size `lens.aspect` was computed for, see `resize`
+    last_size: cgmath::Vector2<f32>,
+    // The camera is rebuilt with the new lens at the start of the next `update`
+    lens_dirty: bool,
+    // Orthographic camera following the orbit camera, used instead of it when `orthographic`
+    orthographic: bool,
+    ortho_camera_buffer: wgpu::Buffer,
+    ortho_camera_bind_group: wgpu::BindGroup,
+    cloth_bindings: ClothBindings,
+    // The first cloth is the one selections, readback and export work on
+    cloths: Vec<Cloth>,
+    // Which of the two vertex buffers of every cloth holds the current state
+    frame_parity: bool,
+    gravity_direction: [f32; 3],
+    precision: Precision,
+    workgroup_layout: WorkgroupLayout,
+    pending_selection_ops: Vec<(Selection, SelectionOp)>,
+    profiler: Option<Profiler>,
+    profiling: bool,
+    show_vertex_ids: bool,
+    frame_times: FrameTimes,
+    show_frame_overlay: bool,
+    time_step: f32,
+    // Substep limit when the frame time is simulated, 0 steps by `time_step`
+    max_substep_dt: f32,
+    // Simulated seconds of every step when the real time is accumulated, 0 off
+    fixed_step: f32,
+    // Real time not simulated yet, less than one `fixed_step`
+    step_accumulator: f32,
+    // Simulated seconds per real (or fixed step) second, see `set_time_scale`
+    time_scale: f32,
+    auto_stabilize: bool,
+    energy_growth_limit: f32,
+    // Total kinetic energy of the last stats that arrived, 0 before the first
+    last_kinetic_energy: f32,
+    // Simulated frames the stabilize boost still lasts
+    stabilize_frames_left: u32,
+    // See `set_settle_threshold`
+    settle_speed: f32,
+    settle_frames: u32,
+    // Stats readbacks in a row with the max speed below `settle_speed`
+    settled_frames: u32,
+    // Fewest substeps per frame, see `set_iterations`
+    iterations: u32,
+    // Velocity change of the gust of the next simulated frame
+    pending_gust: Option<cgmath::Vector3<f32>>,
+    // Simulated seconds of the current frame, split over the substeps
+    frame_time: f32,
+    substeps: u32,
+    log_substeps: bool,
+    integration_method: IntegrationMethod,
+    constraint_solver: ConstraintSolver,
+    constraint_iterations: u32,
+    lighting: bool,
+    detail_enabled: bool,
+    textured: bool,
+    detail_tiling: f32,
+    detail_strength: f32,
+    backface_culling: bool,
+    depth_prepass: bool,
+    // Fabric corner the arrow keys move, 0 to 3 clockwise from the top-left one
+    nudged_corner: usize,
+    motion_blur: bool,
+    motion_blur_time: f32,
+    motion_blur_opacity: f32,
+    render_preset: RenderPreset,
+    edge_overlay: bool,
+    edge_color: [f32; 3],
+    // `None` in half precision, see `set_force_glyphs`
+    force_pipeline: Option<wgpu::RenderPipeline>,
+    force_glyphs: bool,
+    // Spring diagram instead of the fabric faces, with or without the shear springs
+    render_springs: bool,
+    render_shear_springs: bool,
+    // Flat colors replacing the vertex colors and the clear color, `None` keeps them
+    fill_tint: Option<[f32; 3]>,
+    backface_tint: Option<[f32; 3]>,
+    background: Option<[f32; 3]>,
+    materials_dirty: bool,
+    divergence: Option<DivergenceRun>,
+    // `simulated_frames` when the divergence run started
+    divergence_start_frame: u64,
+    pin_animations: Vec<PinAnimation>,
+    validation: Option<StepComparison>,
+    stages: StageToggles,
+    // Self collision radius, kept while the pass is off
+    self_collision_radius: f32,
+    self_collision: bool,
+    color_mode: ColorMode,
+    heatmap_range: [f32; 2],
+    // Scroll velocity of the flow texture, in tiles per simulated second
+    flow_scroll: [f32; 2],
+    flow_path: String,
+    flow_status: String,
+    builder: ClothBuilder,
+    // Frames left with gravity suppressed, see `ClothBuilder::relax_frames`
+    relax_frames_left: u32,
+    ruler_enabled: bool,
+    ruler: Ruler,
+    grab_enabled: bool,
+    grab: Option<Grab>,
+    // Whether the pointer was over a window in the last `ui`, clicks there don't place ruler points
+    pointer_over_ui: bool,
+    // Whether a text field had the keyboard in the last `ui`, keys typed there aren't shortcuts
+    keyboard_over_ui: bool,
+    // No steps are run while paused, except one when `step_once` is set
+    paused: bool,
+    step_once: bool,
 }
 
 impl InstanceApp {
     pub fn new(context: &Context) -> Self {
+        Self::with_precision(context, Precision::F32)
+    }
 
-        // Fabric properties
-        let fabric_side_length = 6.0;
-        let grid_rows: u32 = 100;
-        let grid_cols: u32 = 100;
-        let k_spring = 0.12;
-        let ball_radius = 1.0;
-
-        // Generate fabric vertices
-        let fabric_vertices: Vec<Vertex> = (0..grid_rows)
-            .flat_map(|row| {
-                (0..grid_cols).map(move |col| {
-                    let x = (col as f32 / (grid_cols - 1) as f32) * fabric_side_length - fabric_side_length / 2.0;
-                    let y = 2.0;
-                    let z = (row as f32 / (grid_rows - 1) as f32) * fabric_side_length - fabric_side_length / 2.0;
-
-                    Vertex {
-                        position: [x, y, z, 1.0],
-                        color: [0.26, 0.65, 0.96, 1.0], // Green for the fabric
-                        mass: 0.1,
-                        padding1: [0.0; 3],
-                        velocity: [0.0, 0.0, 0.0, 1.0],
-                        fixed: 0.0,
-                        padding2: [0.0; 3],
-                    }
-                })
-            })
-            .collect();
+    pub fn with_precision(context: &Context, precision: Precision) -> Self {
+        ClothBuilder::new().precision(precision).build(context)
+    }
 
-         // Generate fabric indices (two triangles per grid cell)
-        let mut fabric_indices: Vec<u32> = Vec::new();
-        for row in 0..grid_rows - 1 {
-            for col in 0..grid_cols - 1 {
-                let top_left = row * grid_cols + col;
-                let top_right = top_left + 1;
-                let bottom_left = top_left + grid_cols;
-                let bottom_right = bottom_left + 1;
+    /// Default scene with the cloth built from `config` (grid resolution, size
+    /// and starting height), see `ClothBuilder::fabric`. Fails when the grid
+    /// has fewer than 2 or more than 512 rows or columns or exceeds the device
+    /// limits, see `ClothBuilder::try_build`.
+    pub fn with_config(context: &Context, config: FabricConfig) -> Result<Self, GridLimitError> {
+        ClothBuilder::new().fabric(config).try_build(context)
+    }
 
-                // Add two triangles for the cell
-                fabric_indices.extend_from_slice(&[
-                    top_left, bottom_left, bottom_right, // Triangle 1
-                    top_left, bottom_right, top_right,  // Triangle 2
-                ]);
-            }
-        }
+    /// Default scene with the cloth built from `config` and its starting
+    /// positions jittered by `seed`, see `ClothBuilder::seed`. Fails like
+    /// `with_config`.
+    pub fn with_seed(context: &Context, config: FabricConfig, seed: u64) -> Result<Self, GridLimitError> {
+        ClothBuilder::new().fabric(config).seed(Some(seed)).try_build(context)
+    }
 
-        println!("Fabric vertices: {}", fabric_vertices.len());
-        println!("Fabric indices: {}", fabric_indices.len());
+    /// Default scene with the single cloth laid out by `preset`, see
+    /// `ClothBuilder::preset`.
+    pub fn with_preset(context: &Context, preset: ClothPreset) -> Self {
+        ClothBuilder::new().preset(preset).build(context)
+    }
 
-        let (ball_positions, ball_indices) = icosphere(5);
-        let ball_vertices: Vec<Vertex> = ball_positions
-            .iter()
-            .map(|position| Vertex {
-                position: [position.x * ball_radius, position.y * ball_radius, position.z * ball_radius, 1.0],
-                color: [1.0, 0.0, 0.0, 1.0], // Red for the ball
-                mass: 1.0,
-                padding1: [0.0; 3],
-                velocity: [0.0, 0.0, 0.0, 1.0],
-                fixed: 1.0,
-                padding2: [0.0; 3],
-            })
-            .collect();
+    /// Default scene with an arbitrary triangle mesh in place of the ball, see
+    /// `ClothBuilder::collider_mesh`.
+    pub fn with_collider_mesh(context: &Context, positions: &[cgmath::Vector3<f32>], indices: &[u32]) -> Self {
+        ClothBuilder::new().collider_mesh(positions, indices).build(context)
+    }
 
-        // Combine vertices and indices for rendering
-        let mut vertices: Vec<Vertex> = Vec::new();
-        vertices.extend(&ball_vertices);
+    /// Whether cloth `index` applies gravity in its own rotated frame (see
+    /// `ClothTransform`) instead of world space. Out of range indices are ignored.
+    pub fn set_local_gravity(&mut self, index: usize, local: bool) {
+        if let Some(cloth) = self.cloths.get_mut(index) {
+            cloth.sim_params1.options[0] = if local { 1.0 } else { 0.0 };
+            cloth.default_sim_params1.options[0] = cloth.sim_params1.options[0];
+            cloth.params_dirty = true;
+        }
+    }
 
-        let mut indices = Vec::new();
-        indices.extend(ball_indices.clone());
+    /// Starts a divergence run: the scene is reduced to the first cloth plus an
+    /// exact copy of its current state (buffers and parameters) with `vertex`
+    /// moved by `nudge` along x. Both are stepped together for `frames` frames,
+    /// recording the largest distance between them whenever their positions
+    /// come back from the GPU, see `DivergenceRun`. Read the curve with
+    /// `divergence_run` or save it with `export_divergence_csv`.
+    ///
+    /// Selections only apply to the first cloth and would skew the result. In
+    /// `Precision::F16` mode nudges below the half float resolution of the
+    /// position (about 0.002 around 3.0) are rounded away.
+    pub fn start_divergence_run(&mut self, context: &Context, vertex: u32, nudge: f32, frames: u32) {
+        let mut vertices = self.read_vertices(context);
+        let Some(nudged) = vertices.get_mut(vertex as usize) else {
+            return;
+        };
+        nudged.position[0] += nudge;
 
-        let sim_params1 = SimParams1 {
-            grid_k_radius: [grid_rows as f32, grid_cols as f32, k_spring, 1.4],
-            sphere_center: [0.0, 0.0, 0.0, 0.0],
+        self.cloths.truncate(1);
+        let reference = self.primary();
+        let orientation = reference.sim_params1.orientation;
+        let desc = ClothDesc {
+            label: "Perturbed".to_string(),
+            vertices,
+            grid_rows: reference.grid_rows,
+            grid_cols: reference.grid_cols,
+            sim_params1: reference.sim_params1,
+            sim_params2: reference.sim_params2,
+            transform: ClothTransform {
+                translation: reference.anchor,
+                rotation: cgmath::Quaternion::new(orientation[3], orientation[0], orientation[1], orientation[2]),
+            },
+            anchor: reference.anchor,
         };
-        let sim_params2 = SimParams2 {
-            stiffness: [25.0, 15.0, 5.0, 0.0],
-            rest_length: [0.06, 0.085, 0.12, 0.0],
-            gravity: [0.0, -6.8, 0.0, 0.0],
-            _padding: [0.0; 4]
+        let mut twin = Cloth::new(context, self.precision, &self.cloth_bindings, desc);
+        twin.default_sim_params1 = reference.default_sim_params1;
+        twin.default_sim_params2 = reference.default_sim_params2;
+        self.cloths.push(twin);
+        self.materials_dirty = true;
+        self.divergence = Some(DivergenceRun::new(vertex, nudge, frames));
+        self.divergence_start_frame = self.simulated_frames;
+    }
+
+    /// Runs one step of the first cloth on the GPU and on the CPU
+    /// (`cpu_reference::cpu_step`) from the same state and compares them. The
+    /// step is the integration and collision passes at the current dt, without
+    /// substeps, constraints, wind, gusts, air drag, tearing or sticky
+    /// collision, and it advances the simulation.
+    /// Springs that already tore are not known to the CPU side, so compare
+    /// before tearing or after a `reset`.
+    pub fn validate_step(&mut self, context: &Context) -> StepComparison {
+        let to_cpu = |vertex: &Vertex| CpuVertex {
+            position: vertex.position,
+            velocity: vertex.velocity,
+            mass: vertex.mass,
+            fixed: vertex.fixed > 0.5,
         };
+        let mut state: Vec<CpuVertex> = self.read_vertices(context).iter().map(to_cpu).collect();
 
-        println!("SimParams1 -- Size: {}, Alignment: {}", std::mem::size_of::<SimParams1>(), std::mem::align_of::<SimParams1>());
-        println!("SimParams2 -- Size: {}, Alignment: {}", std::mem::size_of::<SimParams2>(), std::mem::align_of::<SimParams2>());
+        // `cpu_step` has no wind, air drag, tearing or sticky collision: they
+        // are switched off in the uniforms of this step. Substeps, constraints,
+        // the pin hold and the normals are passes of their own that aren't
+        // dispatched below.
+        let cloth = self.primary();
+        let (mut sim_params1, mut sim_params2) = (cloth.sim_params1, cloth.sim_params2);
+        sim_params1.colliders[2] = 0.0; // sticky collision
+        sim_params2.flow[1] = 0.0; // wind flow texture
+        sim_params2.wind = [0.0; 4]; // steady wind and turbulence
+        sim_params2.gust = [0.0; 4];
+        sim_params2.damping[1] = 0.0; // air drag
+        sim_params2.rest_length[3] = 0.0; // tear length
+        let [x, y, z, w] = sim_params1.orientation;
+        let gravity = if sim_params1.options[0] > 0.5 {
+            let rotated = cgmath::Quaternion::new(w, x, y, z).rotate_vector(cgmath::Vector3::new(sim_params2.gravity[0], sim_params2.gravity[1], sim_params2.gravity[2]));
+            [rotated.x, rotated.y, rotated.z, sim_params2.gravity[3]]
+        } else {
+            sim_params2.gravity
+        };
+        let soft_sphere = self.soft_sphere.is_some() as usize;
+        let colliders = self.colliders.iter().skip(soft_sphere).map(|collider| collider.to_gpu()).map(|gpu| (gpu.center_radius, gpu.half_axis)).collect();
+        let params = StepParams {
+            grid_rows: cloth.grid_rows,
+            grid_cols: cloth.grid_cols,
+            dt: sim_params2.time[0],
+            stiffness: [sim_params2.stiffness[0], sim_params2.stiffness[1], sim_params2.stiffness[2]],
+            warp_stiffness: sim_params2.warp[0],
+            rest_length: [sim_params2.rest_length[0], sim_params2.rest_length[1], sim_params2.rest_length[2]],
+            max_spring_force: sim_params2.stiffness[3],
+            damping: sim_params1.grid_k_radius[2],
+            gravity,
+            gravity_enabled: sim_params1.options[1] < 0.5,
+            springs_enabled: sim_params1.options[2] < 0.5,
+            collision_enabled: self.stages.collision,
+            colliders,
+            ground_plane: self.ground_plane,
+            sphere_inverted: sim_params1.collision[0] > 0.5,
+            contact_damping: sim_params1.collision[1],
+            sphere_friction: sim_params1.collision[2],
+            sphere_restitution: sim_params1.collision[3],
+            settle_damping: sim_params2.damping[0] + sim_params2.damping[2],
+            sleep_threshold: sim_params2.damping[3],
+            verlet: self.integration_method == IntegrationMethod::Verlet,
+        };
 
-        let sim_params1_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Sim Param 1 Buffer"),
-            contents: bytemuck::cast_slice(&[sim_params1]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        // Same uniforms as the CPU step, restored by the next upload
+        context.queue().write_buffer(&cloth.sim_params1_buffer, 0, bytemuck::cast_slice(&[sim_params1]));
+        context.queue().write_buffer(&cloth.sim_params2_buffer, 0, bytemuck::cast_slice(&[sim_params2]));
+        let mut encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Validation Encoder"),
+        });
+        let parity = self.frame_parity;
+        let mut pipelines = vec![(&self.compute_pipeline, cloth.step_bind_group(parity))];
+        if self.stages.collision {
+            pipelines.push((&self.collide_pipeline, cloth.compute_bind_group(!parity)));
+        }
+        if self.integration_method == IntegrationMethod::Verlet {
+            pipelines.push((&self.verlet_velocity_pipeline, cloth.compute_bind_group(!parity)));
+        }
+        for (pipeline, bind_group) in pipelines {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Validation Pass"),
+                timestamp_writes: None,
             });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            cloth.dispatch_vertices(&mut compute_pass, self.workgroup_layout);
+        }
+        // Only this cloth was stepped: copy the result back instead of flipping
+        // the parity shared with the other cloths
+        let size = cloth.vertex_buffer(parity).size();
+        encoder.copy_buffer_to_buffer(cloth.vertex_buffer(!parity), 0, cloth.vertex_buffer(parity), 0, size);
+        context.queue().submit(Some(encoder.finish()));
+        self.cloths[0].params_dirty = true;
 
-        let sim_params2_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Sim Param 2 Buffer"),
-            contents: bytemuck::cast_slice(&[sim_params2]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            });
+        let gpu: Vec<CpuVertex> = self.read_vertices(context).iter().map(to_cpu).collect();
+        cpu_reference::cpu_step(&mut state, &params);
+        let comparison = StepComparison::new(&state, &gpu);
+        self.validation = Some(comparison);
+        comparison
+    }
 
-        let fabric_vertex_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Fabric Vertex Buffer"),
-            contents: bytemuck::cast_slice(&fabric_vertices),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        });
+    /// The current or last divergence run.
+    pub fn divergence_run(&self) -> Option<&DivergenceRun> {
+        self.divergence.as_ref()
+    }
 
-        let fabric_index_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Fabric Index Buffer"),
-            contents: bytemuck::cast_slice(&fabric_indices),
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
-        });
+    // Samples the divergence once the positions of both cloths came back from
+    // the same frame, see `position_readback_frame`
+    fn record_divergence(&mut self) {
+        let Some(run) = self.divergence.as_mut().filter(|run| !run.is_done()) else {
+            return;
+        };
+        let [reference, perturbed] = &self.cloths[..] else {
+            return;
+        };
+        let frame = reference.positions_frame;
+        if perturbed.positions_frame != frame || frame <= self.divergence_start_frame {
+            return;
+        }
+        let [reference, perturbed] = [reference, perturbed].map(|cloth| cloth.positions.iter().map(|p| [p.x, p.y, p.z]).collect::<Vec<_>>());
+        run.record((frame - self.divergence_start_frame) as u32, &reference, &perturbed);
+    }
 
-        let sphere_vertex_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Sphere Vertex Buffer"),
-            contents: bytemuck::cast_slice(&ball_vertices),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE| wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-        });
+    /// Writes the samples of the current or last divergence run as CSV
+    /// (`frame,max_divergence`), see `DivergenceRun::write_csv`. Fails with
+    /// `io::ErrorKind::NotFound` when no run was started.
+    pub fn export_divergence_csv(&self, path: &Path) -> io::Result<()> {
+        let run = self.divergence.as_ref().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no divergence run"))?;
+        run.write_csv(BufWriter::new(File::create(path)?))
+    }
 
-        let sphere_index_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Sphere Index Buffer"),
-            contents: bytemuck::cast_slice(&ball_indices),
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE| wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-        });
+    /// Puts the orbit camera back where it started: the default radius and the
+    /// initial longitude and latitude. Bound to the Home key.
+    pub fn reset_camera(&mut self, context: &Context) {
+        let (radius, longitude, latitude) = self.camera_home;
+        self.camera
+            .set_radius(radius.clamp(MIN_CAMERA_RADIUS, MAX_CAMERA_RADIUS))
+            .set_longitude(longitude)
+            .set_latitude(latitude)
+            .update(context);
+    }
 
-        println!("Buffer size: {}", std::mem::size_of::<Vertex>() * fabric_vertices.len());
+    /// Recomputes the aspect ratio of the lens from the surface size and
+    /// rebuilds the orbit camera with it, keeping its radius, longitude and
+    /// latitude. `App` has no resize callback, so `update` calls it whenever
+    /// the size changed since the last frame. A minimized window (zero area)
+    /// keeps the previous aspect.
+    pub fn resize(&mut self, context: &Context) {
+        let size = context.size();
+        self.last_size = size;
+        if size.x <= 0.0 || size.y <= 0.0 {
+            return;
+        }
+        self.lens.aspect = size.x / size.y;
+        self.rebuild_camera(context);
+    }
 
-        // Shaders and pipeline
-        let shader = context.device().create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
+    /// Vertical field of view of the camera in degrees, clamped to 10..=120
+    /// and 45 by default. The orthographic camera frames the same height at
+    /// the orbit center. Takes effect on the next `update`.
+    pub fn set_fov(&mut self, degrees: f32) {
+        self.lens.fovy = degrees.clamp(*FOV_RANGE.start(), *FOV_RANGE.end());
+        self.lens_dirty = true;
+    }
 
-        // Create the compute shader
-        let compute_shader = context.device().create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Compute Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("computeShader.wgsl").into()),
-        });
+    /// Distances of the near and far clipping planes, 0.5 and 100 by default.
+    /// `near` is at least 0.01 and `far` at least one unit beyond it. Raise
+    /// `far` when zooming out on a large cloth clips it, the mouse wheel goes
+    /// out to a radius of 500. Takes effect on the next `update`.
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+        self.lens.near = near.max(MIN_NEAR_PLANE);
+        self.lens.far = far.max(self.lens.near + 1.0);
+        self.lens_dirty = true;
+    }
 
-        let camera_bind_group_layout = context.device().create_bind_group_layout(&CameraUniform::desc());
+    // `OrbitCamera` takes its projection at construction, so a new lens means
+    // a new camera at the same place
+    fn rebuild_camera(&mut self, context: &Context) {
+        let (radius, longitude, latitude) = (self.camera.radius(), self.camera.longitude(), self.camera.latitude());
+        self.camera = OrbitCamera::new(context, self.lens.fovy, self.lens.aspect, self.lens.near, self.lens.far);
+        self.camera.set_radius(radius).set_longitude(longitude).set_latitude(latitude).update(context);
+        self.lens_dirty = false;
+    }
 
-        let pipeline_layout = context.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&camera_bind_group_layout],
-            push_constant_ranges: &[],
-        });
+    // Drops what belongs to the previous scene or run: queued selection
+    // operations, pin animations, the grab, the ruler, the divergence run and
+    // the adaptive substep, settle and energy tracking
+    fn clear_transient_state(&mut self) {
+        self.pending_selection_ops.clear();
+        self.substeps = 1;
+        self.materials_dirty = true;
+        self.divergence = None;
+        self.last_kinetic_energy = 0.0;
+        self.stabilize_frames_left = 0;
+        self.settled_frames = 0;
+        self.pin_animations.clear();
+        self.ruler.clear();
+        self.grab = None;
+    }
 
-        let compute_bind_group_layout = context
-        .device()
-        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Compute Bind Group Layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
+    /// Puts every cloth back in the state it was created in: initial positions,
+    /// zero velocities and the original pins. Also drops the pending selection
+    /// operations and pin animations and restarts the simulated time. The
+    /// parameters are kept, see `reset_params`. Bound to the R key.
+    pub fn reset(&mut self, context: &Context) {
+        for cloth in &self.cloths {
+            cloth.restore_initial_vertices(context, self.precision);
+        }
+        self.elapsed_time = 0.0;
+        self.step_accumulator = 0.0;
+        self.clear_transient_state();
+        if let Some(motion) = self.sphere_motion.as_mut() {
+            motion.elapsed = 0.0;
+        }
+        self.advance_sphere_motion(0.0);
+        if let Some(soft_sphere) = &self.soft_sphere {
+            soft_sphere.restore_initial_vertices(context);
+        }
+    }
 
-        let compute_bind_group = context.device().create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Compute Bind Group"),
-            layout: &compute_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: fabric_vertex_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: sim_params1_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: sim_params2_buffer.as_entire_binding(),
-                },
-            ],
-        });
+    /// Freezes the simulation while `paused`. The camera, the panel and the
+    /// rendering keep running, and selection operations still apply. Toggled
+    /// with the space bar.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
 
-        // Create the compute pipeline
-        let compute_pipeline = context
-        .device()
-        .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            module: &compute_shader,
-            entry_point: "cs_main",
-            layout: Some(&context.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Compute Pipeline Layout"),
-                bind_group_layouts: &[&compute_bind_group_layout],
-                push_constant_ranges: &[],
-            })),
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-            cache: None,
-            label: Some("Compute Pipeline"),
-        });
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
 
-        // Create render pipeline
-        let render_pipeline =
-        context
-            .device()
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[Vertex::desc()],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: context.format(),
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: None,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: context.depth_stencil_format(),
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
-            });
+    /// Shows the frame rate, the average frame time over the last
+    /// `FRAME_TIME_SAMPLES` frames, the vertex and substep counts and a frame
+    /// time plot in the top right corner. On by default, toggled with F.
+    pub fn set_frame_overlay(&mut self, shown: bool) {
+        self.show_frame_overlay = shown;
+    }
 
-        // Camera setup
-        let aspect = context.size().x / context.size().y;
-        let mut camera = OrbitCamera::new(context, 45.0, aspect, 0.5, 100.0);
-        camera.set_radius(7.0).update(context);
-
-        let num_sphere_indices = ball_indices.len() as u32;
-
-        InstanceApp {
-            sphere_vertex_buffer,
-            sphere_index_buffer,
-            render_pipeline,
-            compute_pipeline,
-            num_sphere_indices,
-            camera,
-            compute_bind_group,
-            sim_params1_buffer,
-            sim_params2_buffer,
-            fabric_vertex_buffer,
-            fabric_index_buffer,
-            sim_params1,
-            sim_params2,
+    /// Runs a single frame of the simulation on the next update while paused,
+    /// with its substeps. The `.` key does the same.
+    pub fn request_step(&mut self) {
+        self.step_once = true;
+    }
+
+    fn primary(&self) -> &Cloth {
+        &self.cloths[0]
+    }
+
+    // Applies a scene-wide parameter change to every cloth
+    fn edit_params(&mut self, edit: impl Fn(&mut SimParams1, &mut SimParams2)) {
+        for cloth in &mut self.cloths {
+            edit(&mut cloth.sim_params1, &mut cloth.sim_params2);
+            cloth.params_dirty = true;
         }
     }
-}
 
-impl App for InstanceApp {
-    fn input(&mut self, input: egui::InputState, context: &Context) {
-        self.camera.input(input.clone(), context);
-        if input.raw_scroll_delta.y != 0.0 {
-            let new_radius = (self.camera.radius() - input.raw_scroll_delta.y / 10.0).max(5.0).min(500.0);
-            self.camera.set_radius(new_radius).update(context);
+    fn fabric_vertex_count(&self) -> usize {
+        self.primary().vertex_count()
+    }
+
+    fn view_projection(&self) -> cgmath::Matrix4<f32> {
+        let (radius, longitude, latitude) = (self.camera.radius(), self.camera.longitude(), self.camera.latitude());
+        if self.orthographic {
+            return projection::orbit_orthographic(radius, self.lens) * projection::orbit_view(radius, longitude, latitude);
         }
+        projection::orbit_view_projection(radius, longitude, latitude, self.lens)
     }
 
-    fn update(&mut self, delta_time: f32, context: &Context) {
-        let mut encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Compute Encoder"),
-        });
-    
-        let total_vertices = self.sim_params1.grid_k_radius[0] as u32 * self.sim_params1.grid_k_radius[1] as u32;
-        let thread_group_size = 256u32;
-        let thread_group_count = (total_vertices + thread_group_size - 1) / thread_group_size;
-        
-        {
-            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: Some("Compute Pass"),
-                timestamp_writes: None,
-            });
-    
-            compute_pass.set_pipeline(&self.compute_pipeline);
-            compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
-            compute_pass.dispatch_workgroups(thread_group_count, 1, 1);
+    // Whether the CPU copy of the cloth positions has to be kept up to date
+    fn mirrors_positions(&self, cloth: &Cloth) -> bool {
+        self.ruler_enabled
+            || self.grab_enabled
+            || (self.show_vertex_ids && cloth.vertex_count() <= MAX_LABELED_VERTICES)
+            || self.divergence.as_ref().is_some_and(|run| !run.is_done())
+    }
+
+    /// When enabled, clicks in the viewport place the two ends of a distance
+    /// measurement, see `ruler_distance`.
+    pub fn set_ruler_enabled(&mut self, enabled: bool) {
+        self.ruler_enabled = enabled;
+        if !enabled {
+            self.ruler.clear();
         }
-        context.queue().submit(Some(encoder.finish()));
     }
-    
-    fn render(&self, render_pass: &mut wgpu::RenderPass<'_>) {
-        // Draw the sphere
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, self.camera.bind_group(), &[]);
-        render_pass.set_vertex_buffer(0, self.sphere_vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.sphere_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.draw_indexed(0..self.num_sphere_indices, 0, 0..1);
-    
-        // Draw the fabric
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, self.camera.bind_group(), &[]);
-        render_pass.set_vertex_buffer(0, self.fabric_vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.fabric_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        
-        // Calculate total indices for grid
-        let indices_per_cell = 6; // 2 triangles * 3 vertices
-        let cells = (self.sim_params1.grid_k_radius[0] as u32 - 1) * (self.sim_params1.grid_k_radius[1] as u32- 1);
-        let total_indices = indices_per_cell * cells;
-        
-        render_pass.draw_indexed(0..total_indices, 0, 0..1);
-    }
-}
\ No newline at end of file
+
+    /// World space distance between the two ruler points, `None` until both are placed.
+    pub fn ruler_distance(&self) -> Option<f32> {
+        self.ruler.distance(|point| self.ruler_position(point))
+    }
+
+    fn ruler_position(&self, point: RulerPoint) -> Option<cgmath::Vector3<f32>> {
+        match point {
+            RulerPoint::Vertex { cloth, vertex } => self.cloths.get(cloth)?.positions.get(vertex).copied(),
+            RulerPoint::Fixed(position) => Some(position),
+        }
+    }
+
+    // Cloth and index of the mirrored fabric vertex closest to the camera along the ray
+    fn pick_vertex(&self, ray: &projection::Ray) -> Option<(usize, usize)> {
+        self.cloths
+            .iter()
+            .enumerate()
+            .filter_map(|(cloth_index, cloth)| {
+                let vertex = ray.nearest_point(&cloth.positions, RULER_PICK_DISTANCE)?;
+                Some((cloth_index, vertex, (cloth.positions[vertex] - ray.origin).magnitude2()))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(cloth, vertex, _)| (cloth, vertex))
+    }
+
+    // Snaps to the fabric vertex closest to the camera along the ray, falls back
+    // to the ground plane through the sphere center
+    fn pick_ruler_point(&self, screen: egui::Pos2, viewport: egui::Rect) -> Option<RulerPoint> {
+        let ray = projection::screen_ray(self.view_projection(), screen, viewport)?;
+        match self.pick_vertex(&ray) {
+            Some((cloth, vertex)) => Some(RulerPoint::Vertex { cloth, vertex }),
+            None => ray.plane_hit(self.colliders.first().map_or(0.0, |collider| collider.center().y)).map(RulerPoint::Fixed),
+        }
+    }
+
+    /// When enabled, pressing the mouse on the fabric grabs the vertex under
+    /// the cursor: it is pinned and follows the cursor on the plane facing the
+    /// camera until the button is released. Elsewhere dragging still orbits the
+    /// camera. Picking uses the positions read back from the GPU, a frame or two
+    /// behind. Off while the ruler is on.
+    pub fn set_grab_enabled(&mut self, enabled: bool) {
+        self.grab_enabled = enabled;
+        if !enabled {
+            self.release_grab();
+        }
+    }
+
+    // Lets go of the held vertex, the next update restores its pin
+    fn release_grab(&mut self) {
+        if let Some(grab) = self.grab.as_mut() {
+            grab.held = false;
+        }
+    }
+
+    // Starts, moves or ends a grab from the pointer. True while a vertex is
+    // held, the camera then ignores the drag.
+    fn grab_input(&mut self, input: &egui::InputState) -> bool {
+        if !self.grab_enabled || self.ruler_enabled {
+            return false;
+        }
+        if input.pointer.primary_released() {
+            self.release_grab();
+            return false;
+        }
+        let Some(ray) = input.pointer.latest_pos().and_then(|pos| projection::screen_ray(self.view_projection(), pos, input.screen_rect())) else {
+            return self.grab.as_ref().is_some_and(|grab| grab.held);
+        };
+        if input.pointer.primary_pressed() && !self.pointer_over_ui {
+            if let Some((cloth, vertex)) = self.pick_vertex(&ray) {
+                let point = self.cloths[cloth].positions[vertex];
+                self.grab = Some(Grab {
+                    cloth,
+                    vertex,
+                    target: point,
+                    plane_point: point,
+                    was_fixed: self.cloths[cloth].fixed.get(vertex).copied().unwrap_or(false),
+                    held: true,
+                });
+            }
+        }
+        let forward = projection::orbit_forward(self.camera.longitude(), self.camera.latitude());
+        match self.grab.as_mut().filter(|grab| grab.held) {
+            Some(grab) => {
+                let facing = ray.direction.dot(forward);
+                if facing.abs() > 1e-6 {
+                    grab.target = ray.origin + ray.direction * ((grab.plane_point - ray.origin).dot(forward) / facing);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Writes the held vertex at its target, pinned and at rest, or restores the
+    // pin it had once it is let go
+    fn advance_grab(&mut self, context: &Context) {
+        let Some(grab) = self.grab.as_ref() else {
+            return;
+        };
+        let Some(cloth) = self.cloths.get(grab.cloth).filter(|cloth| grab.vertex < cloth.vertex_count()) else {
+            self.grab = None;
+            return;
+        };
+        let buffer = cloth.vertex_buffer(self.frame_parity);
+        let offset = grab.vertex as wgpu::BufferAddress * self.precision.vertex_size();
+        let fixed = if grab.held || grab.was_fixed { 1.0f32 } else { 0.0 };
+        if grab.held {
+            let target = [grab.target.x, grab.target.y, grab.target.z, 1.0];
+            context.queue().write_buffer(buffer, offset, &self.precision.position_bytes(target));
+            // cs_hold_pins would put it back on its old pin target otherwise
+            context.queue().write_buffer(buffer, offset + self.precision.pin_target_offset(), &self.precision.position_bytes(target));
+        }
+        context.queue().write_buffer(buffer, offset + self.precision.velocity_offset(), &self.precision.position_bytes([0.0; 4]));
+        context.queue().write_buffer(buffer, offset + self.precision.fixed_offset(), bytemuck::bytes_of(&fixed));
+        if !grab.held {
+            self.grab = None;
+        }
+    }
+
+    /// Turns on the GPU timestamps around the physics compute passes, see
+    /// `last_compute_ms`. Off by default, the "Profile compute passes"
+    /// checkbox.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+    }
+
+    /// GPU time of the physics compute passes (every stage of every substep)
+    /// of the last profiled frame, in milliseconds. It arrives a frame or more
+    /// late. `None` while profiling is off, before the first result, or when
+    /// the device doesn't support `Features::TIMESTAMP_QUERY`.
+    pub fn last_compute_ms(&self) -> Option<f32> {
+        self.profiler.as_ref().filter(|_| self.profiling).and_then(|profiler| profiler.last_frame_ms())
+    }
+
+    /// Copies the vertex buffer of the first cloth back to the CPU, see
+    /// `Cloth::read_vertices`.
+    fn read_vertices(&self, context: &Context) -> Vec<Vertex> {
+        self.primary().read_vertices(context, self.precision, self.frame_parity)
+    }
+
+    /// Positions of the first cloth's vertices as simulated so far, row by row.
+    ///
+    /// Copies the current vertex buffer into a staging buffer and waits for
+    /// the GPU to map it, so it stalls until all submitted work is done. Meant
+    /// for tests and export, not for every frame.
+    pub fn read_positions(&self, context: &Context) -> Vec<cgmath::Vector3<f32>> {
+        self.read_vertices(context)
+            .iter()
+            .map(|vertex| cgmath::Vector3::new(vertex.position[0], vertex.position[1], vertex.position[2]))
+            .collect()
+    }
+
+    /// Writes the current fabric as an ASCII PLY file with per-vertex colors (the
+    /// colors currently in the vertex buffer, so any debug coloring is kept).
+    /// See `export::write_ply` for the exact layout.
+    pub fn export_ply(&self, context: &Context, path: &Path) -> io::Result<()> {
+        let vertices = self.read_vertices(context);
+        let positions: Vec<[f32; 3]> = vertices.iter().map(|v| [v.position[0], v.position[1], v.position[2]]).collect();
+        let colors: Vec<[u8; 3]> = vertices
+            .iter()
+            .map(|v| [v.color[0], v.color[1], v.color[2]].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect();
+        export::write_ply(BufWriter::new(File::create(path)?), &positions, &colors, &self.primary().indices)
+    }
+
+    /// Writes the current fabric as a Wavefront OBJ, e.g. to render a draped
+    /// cloth in Blender: positions, the vertex normals computed by `cs_normals`
+    /// and the triangles of the fabric. See `export::write_obj`.
+    pub fn export_obj(&self, context: &Context, path: &Path) -> io::Result<()> {
+        let vertices = self.read_vertices(context);
+        let positions: Vec<[f32; 3]> = vertices.iter().map(|v| [v.position[0], v.position[1], v.position[2]]).collect();
+        let normals: Vec<[f32; 3]> = vertices.iter().map(|v| [v.normal[0], v.normal[1], v.normal[2]]).collect();
+        export::write_obj(BufWriter::new(File::create(path)?), &positions, Some(&normals), &self.primary().indices)
+    }
+
+    /// Selects the fabric vertices in the given row and column ranges.
+    pub fn select_rect(&self, rows: Range<u32>, cols: Range<u32>) -> Selection {
+        Selection::rect(self.primary().grid_rows, self.primary().grid_cols, rows, cols)
+    }
+
+    /// Selects fabric vertices by index (`row * grid_cols + col`).
+    pub fn select_indices(&self, indices: &[u32]) -> Selection {
+        Selection::indices(self.fabric_vertex_count(), indices)
+    }
+
+    /// Selects the fabric vertices currently inside the world space box `min..=max`.
+    /// Reads the positions back from the GPU, see `read_vertices`.
+    pub fn select_region(&self, context: &Context, min: cgmath::Vector3<f32>, max: cgmath::Vector3<f32>) -> Selection {
+        let positions: Vec<cgmath::Vector3<f32>> = self
+            .read_vertices(context)
+            .iter()
+            .map(|v| cgmath::Vector3::new(v.position[0], v.position[1], v.position[2]))
+            .collect();
+        Selection::region(&positions, min, max)
+    }
+
+    /// Pins the selected vertices of the first cloth in place.
+    ///
+    /// Panics if `selection` doesn't hold one weight per vertex of the first
+    /// cloth, e.g. when it was made before `single_cloth` changed the grid.
+    pub fn pin_selection(&mut self, selection: &Selection) {
+        self.queue_selection_op(selection, SelectionOp::new(SelectionOp::PIN, [0.0; 4]));
+    }
+
+    /// Lets the selected vertices of the first cloth move freely again.
+    pub fn release_selection(&mut self, selection: &Selection) {
+        self.queue_selection_op(selection, SelectionOp::new(SelectionOp::RELEASE, [0.0; 4]));
+    }
+
+    /// Pins (`fixed`) or releases the fabric vertex at `row`, `col` of the first
+    /// cloth, like `pin_selection` on that single vertex. Vertices outside the
+    /// grid are ignored.
+    pub fn set_fixed(&mut self, row: u32, col: u32, fixed: bool) {
+        let cloth = self.primary();
+        if row >= cloth.grid_rows || col >= cloth.grid_cols {
+            return;
+        }
+        let selection = self.select_indices(&[row * cloth.grid_cols + col]);
+        if fixed {
+            self.pin_selection(&selection);
+        } else {
+            self.release_selection(&selection);
+        }
+    }
+
+    /// Pins both ends of the first row of the first cloth, so it hangs like a flag.
+    pub fn pin_top_corners(&mut self) {
+        let last_col = self.primary().grid_cols - 1;
+        self.set_fixed(0, 0, true);
+        self.set_fixed(0, last_col, true);
+    }
+
+    /// Pins the selected vertices of the first cloth and moves them by
+    /// `offset`, scaled by the selection weight. The rest of the cloth follows
+    /// through the springs.
+    pub fn translate_selection(&mut self, selection: &Selection, offset: cgmath::Vector3<f32>) {
+        self.queue_selection_op(selection, SelectionOp::new(SelectionOp::TRANSLATE, [offset.x, offset.y, offset.z, 0.0]));
+    }
+
+    /// Sets the mass of the fabric vertex at `row`, `col` of the first cloth, 0.1
+    /// by default. See `set_mass_map`.
+    pub fn set_mass(&mut self, row: u32, col: u32, mass: f32) {
+        let cloth = self.primary();
+        if row >= cloth.grid_rows || col >= cloth.grid_cols {
+            return;
+        }
+        let mut masses = vec![0.0; cloth.vertex_count()];
+        masses[(row * cloth.grid_cols + col) as usize] = guarded_mass(mass);
+        self.queue_masses(masses);
+    }
+
+    /// Sets the mass of every fabric vertex of the first cloth to `mass(row, col)`,
+    /// for a weighted hem or a fabric of varying density. Gravity, wind and
+    /// impulses scale with the mass, so heavier vertices fall as fast but are
+    /// harder to push around. Masses are clamped to `ParamBounds::MASS`, a zero or
+    /// negative mass would divide by zero, and NaN or infinite ones leave the
+    /// vertex as it is. `reset` keeps the masses.
+    pub fn set_mass_map(&mut self, mass: impl Fn(u32, u32) -> f32) {
+        let cloth = self.primary();
+        let grid_cols = cloth.grid_cols;
+        let masses = (0..cloth.vertex_count() as u32).map(|index| guarded_mass(mass(index / grid_cols, index % grid_cols))).collect();
+        self.queue_masses(masses);
+    }
+
+    // One mass per vertex of the first cloth, 0 leaving the vertex as it is. The
+    // initial vertices get them too, so a reset keeps them.
+    fn queue_masses(&mut self, masses: Vec<f32>) {
+        for (vertex, mass) in self.cloths[0].initial_vertices.iter_mut().zip(&masses) {
+            if *mass > 0.0 {
+                vertex.mass = *mass;
+            }
+        }
+        self.queue_selection_op(&Selection::from_weights(masses), SelectionOp::new(SelectionOp::MASS, [0.0; 4]));
+    }
+
+    /// Index of fabric corner `corner` of the first cloth, clockwise from the
+    /// top-left one (0) and wrapping around.
+    pub fn corner_index(&self, corner: usize) -> u32 {
+        let cloth = self.primary();
+        let (last_row, last_col) = (cloth.grid_rows - 1, cloth.grid_cols - 1);
+        let (row, col) = [(0, 0), (0, last_col), (last_row, last_col), (last_row, 0)][corner % 4];
+        row * cloth.grid_cols + col
+    }
+
+    /// Pins the corner the arrow keys move (see `corner_index`) and moves it by
+    /// `offset`. Tab cycles through the corners.
+    pub fn nudge_corner(&mut self, offset: cgmath::Vector3<f32>) {
+        let selection = self.select_indices(&[self.corner_index(self.nudged_corner)]);
+        self.translate_selection(&selection, offset);
+    }
+
+    /// Adds `impulse` (scaled by the selection weight) to the momentum of every
+    /// selected free vertex of the first cloth.
+    ///
+    /// Panics if `selection` doesn't hold one weight per vertex of the first
+    /// cloth, see `pin_selection`.
+    pub fn impulse_selection(&mut self, selection: &Selection, impulse: cgmath::Vector3<f32>) {
+        self.queue_selection_op(selection, SelectionOp::new(SelectionOp::IMPULSE, [impulse.x, impulse.y, impulse.z, 0.0]));
+    }
+
+    /// Blends the color of the selected vertices of the first cloth towards
+    /// `color` by their weight.
+    pub fn recolor_selection(&mut self, selection: &Selection, color: [f32; 4]) {
+        self.queue_selection_op(selection, SelectionOp::new(SelectionOp::RECOLOR, color));
+    }
+
+    /// Moves the vertices of `track` of the first cloth along its path, in
+    /// simulated time, while the rest of the cloth follows through the springs.
+    /// The vertices are pinned first; other pins are left where they are.
+    /// Reads the start positions back from the GPU, see `read_vertices`.
+    pub fn animate_pins(&mut self, context: &Context, mut track: PinTrack) {
+        let vertices = self.read_vertices(context);
+        track.vertices.retain(|index| (*index as usize) < vertices.len());
+        self.pin_selection(&self.select_indices(&track.vertices));
+        let base_positions = track.vertices.iter().map(|index| vertices[*index as usize].position).collect();
+        self.pin_animations.push(PinAnimation {
+            track,
+            base_positions,
+            elapsed: 0.0,
+        });
+    }
+
+    /// Stops all pin animations, the pins stay where the animations left them.
+    pub fn clear_pin_animations(&mut self) {
+        self.pin_animations.clear();
+    }
+
+    // Writes the animated pin positions for this frame straight into the vertex
+    // buffer, and as the pin targets cs_hold_pins holds them at
+    fn advance_pin_animations(&mut self, context: &Context) {
+        let cloth = &self.cloths[0];
+        let buffer = cloth.vertex_buffer(self.frame_parity);
+        let vertex_size = self.precision.vertex_size();
+        for animation in &mut self.pin_animations {
+            animation.elapsed += self.frame_time;
+            let offset = animation.track.offset_at(animation.elapsed);
+            for (index, base) in animation.track.vertices.iter().zip(&animation.base_positions) {
+                let position = [base[0] + offset.x, base[1] + offset.y, base[2] + offset.z, base[3]];
+                let vertex_offset = *index as wgpu::BufferAddress * vertex_size;
+                context.queue().write_buffer(buffer, vertex_offset, &self.precision.position_bytes(position));
+                let target = [position[0], position[1], position[2], 1.0];
+                context.queue().write_buffer(buffer, vertex_offset + self.precision.pin_target_offset(), &self.precision.position_bytes(target));
+            }
+        }
+    }
+
+    /// Restores the physics parameters of every cloth to the values it was
+    /// created with: the spring stiffnesses and force limit, the rest and tear
+    /// lengths, gravity, and the damping, settle damping, air drag and sleep
+    /// threshold. The cloth geometry, collision options, color mode, flow,
+    /// ground and wind are left as is. The parameters are uploaded on the next
+    /// `update`.
+    pub fn reset_params(&mut self) {
+        for cloth in &mut self.cloths {
+            let (defaults1, defaults2) = (&cloth.default_sim_params1, &cloth.default_sim_params2);
+            cloth.sim_params1.grid_k_radius[2] = defaults1.grid_k_radius[2];
+            let sim_params2 = &mut cloth.sim_params2;
+            sim_params2.stiffness = defaults2.stiffness;
+            sim_params2.warp = defaults2.warp;
+            sim_params2.rest_length = defaults2.rest_length;
+            sim_params2.gravity = defaults2.gravity;
+            // damping[2] is the stabilize boost, `sync_stabilize` owns it
+            for i in [0, 1, 3] {
+                sim_params2.damping[i] = defaults2.damping[i];
+            }
+            cloth.params_dirty = true;
+        }
+    }
+
+    /// Every tunable parameter of the first cloth, its grid and the scene
+    /// around it (wind and colliders), see `SimConfig`. The pins are the ones
+    /// the cloth starts from after a `reset`, not the ones added since with
+    /// `pin_selection`, which only live on the GPU.
+    pub fn sim_config(&self) -> SimConfig {
+        let cloth = self.primary();
+        let (sim_params1, sim_params2) = (&cloth.sim_params1, &cloth.sim_params2);
+        let [stiffness, shear, bending, _] = sim_params2.stiffness;
+        let [structural_length, shear_length, bending_length, _] = sim_params2.rest_length;
+        let [x, y, z, _] = sim_params2.gravity;
+        SimConfig {
+            grid: FabricConfig {
+                rows: cloth.grid_rows,
+                cols: cloth.grid_cols,
+                ..self.builder.fabric
+            },
+            preset: self.builder.preset,
+            seed: self.builder.seed,
+            time_step: Some(self.time_step),
+            stiffness: Some([stiffness, shear, bending]),
+            warp_stiffness: Some(sim_params2.warp[0]),
+            rest_lengths: Some([structural_length, shear_length, bending_length]),
+            gravity: Some([x, y, z]),
+            wind: Some(self.wind),
+            damping: Some(sim_params1.grid_k_radius[2]),
+            settle_damping: sim_params2.damping[0],
+            air_drag: sim_params2.damping[1],
+            sleep_threshold: sim_params2.damping[3],
+            colliders: Some(self.colliders.iter().copied().map(ColliderConfig::from).collect()),
+            sphere_inverted: sim_params1.collision[0] > 0.5,
+            sticky_collision: sim_params1.colliders[2] > 0.5,
+            sphere_friction: sim_params1.collision[2],
+            sphere_restitution: Some(sim_params1.collision[3]),
+            contact_damping: sim_params1.collision[1],
+            pinned: Some((0..).zip(&cloth.initial_vertices).filter(|(_, vertex)| vertex.fixed > 0.5).map(|(index, _)| index).collect()),
+        }
+    }
+
+    /// Replaces the scene with a single cloth built from `config`: its grid,
+    /// preset and seed, then its parameters, pins, wind and colliders. The
+    /// parameters become the ones `reset_params` goes back to, and the pins
+    /// the ones `reset` restores. Fails, leaving the scene as it is, when the
+    /// grid has fewer than 2 or more than 512 rows or columns or exceeds the
+    /// device limits, see `ClothBuilder::try_build`.
+    pub fn apply_config(&mut self, context: &Context, config: &SimConfig) -> Result<(), GridLimitError> {
+        let mut builder = self.builder.clone();
+        builder.fabric = FabricConfig {
+            rows: config.grid.rows,
+            cols: config.grid.cols,
+            side_length: if config.grid.side_length > 0.0 { config.grid.side_length } else { DEFAULT_SIDE_LENGTH },
+            initial_height: config.grid.initial_height,
+        };
+        builder.preset = config.preset;
+        builder.seed = config.seed;
+        if let Some(time_step) = config.time_step {
+            builder.time_step = ParamBounds::TIME_STEP.clamp(time_step);
+        }
+        builder.check_limits(&context.device().limits())?;
+        self.time_step = builder.time_step;
+        self.builder = builder;
+        self.single_cloth(context);
+
+        let cloth = &mut self.cloths[0];
+        write_config_params(config, &mut cloth.sim_params1, &mut cloth.sim_params2);
+        cloth.default_sim_params1 = cloth.sim_params1;
+        cloth.default_sim_params2 = cloth.sim_params2;
+        cloth.params_dirty = true;
+        if let Some(pinned) = &config.pinned {
+            for vertex in &mut cloth.initial_vertices {
+                vertex.fixed = 0.0;
+            }
+            for &index in pinned {
+                if let Some(vertex) = cloth.initial_vertices.get_mut(index as usize) {
+                    vertex.fixed = 1.0;
+                }
+            }
+            cloth.restore_initial_vertices(context, self.precision);
+        }
+
+        let preset_wind = match config.preset {
+            ClothPreset::Flag => {
+                let ([x, y, z], turbulence) = FLAG_WIND;
+                [x, y, z, turbulence]
+            }
+            _ => [0.0; 4],
+        };
+        let [x, y, z, turbulence] = config.wind.unwrap_or(preset_wind);
+        self.set_wind(cgmath::Vector3::new(x, y, z), turbulence);
+        match &config.colliders {
+            Some(colliders) => self.set_colliders(colliders.clone()),
+            None => self.set_colliders(self.builder.colliders.clone()),
+        }
+        Ok(())
+    }
+
+    /// Writes `sim_config` as pretty-printed JSON, to share a parameter set
+    /// or seed a test from it. See `load_config`.
+    pub fn save_config(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, &self.sim_config())?;
+        writer.flush()
+    }
+
+    /// Reads a JSON file written by `save_config`, or by hand with any subset
+    /// of the fields, and applies it with `apply_config`.
+    pub fn load_config(&mut self, context: &Context, path: &Path) -> Result<(), ConfigError> {
+        let config: SimConfig = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        self.apply_config(context, &config)?;
+        Ok(())
+    }
+
+    /// Stiffness of the structural, shear and bending springs of every cloth,
+    /// taken into account from the next step. The structural stiffness applies
+    /// to both directions, see `set_structural_stiffness`.
+    pub fn set_stiffness(&mut self, stiffness: [f32; 3]) {
+        let stiffness = stiffness.map(|k| ParamBounds::STIFFNESS.clamp(k));
+        self.edit_params(|_, sim_params2| {
+            sim_params2.stiffness[..3].copy_from_slice(&stiffness);
+            sim_params2.set_structural_stiffness(stiffness[0]);
+        });
+    }
+
+    /// Separate stiffness of the structural springs along the columns (`warp`)
+    /// and along the rows (`weft`) of every cloth, like the two thread
+    /// directions of a woven fabric. A high ratio gives a stiff denim-like
+    /// cloth in one direction, close values a drapey silk.
+    pub fn set_structural_stiffness(&mut self, warp: f32, weft: f32) {
+        let [warp, weft] = [warp, weft].map(|k| ParamBounds::STIFFNESS.clamp(k));
+        self.edit_params(|_, sim_params2| {
+            sim_params2.stiffness[0] = weft;
+            sim_params2.warp[0] = warp;
+        });
+    }
+
+    /// Rest lengths of the structural, shear and bending springs of every cloth,
+    /// clamped to `ParamBounds::REST_LENGTH`. Cloths start with the lengths of
+    /// their grid spacing, shorter ones pre-stretch the cloth and longer ones
+    /// leave it slack. `reset_params` goes back to the grid spacing.
+    pub fn set_rest_lengths(&mut self, rest_lengths: [f32; 3]) {
+        let rest_lengths = rest_lengths.map(|length| ParamBounds::REST_LENGTH.clamp(length));
+        self.edit_params(|_, sim_params2| sim_params2.rest_length[..3].copy_from_slice(&rest_lengths));
+    }
+
+    /// Gravity acceleration of every cloth, any direction. The magnitude is
+    /// clamped to `ParamBounds::GRAVITY`, a zero vector switches gravity off.
+    /// Only xyz changes, the w component of the uniform is kept. Cloths with
+    /// local gravity apply it in their own frame.
+    pub fn set_gravity(&mut self, gravity: cgmath::Vector3<f32>) {
+        let gravity = clamp_gravity(gravity);
+        self.edit_params(|_, sim_params2| sim_params2.gravity[..3].copy_from_slice(&[gravity.x, gravity.y, gravity.z]));
+    }
+
+    /// Gravity acceleration of the first cloth.
+    pub fn gravity(&self) -> cgmath::Vector3<f32> {
+        let [x, y, z, _] = self.primary().sim_params2.gravity;
+        cgmath::Vector3::new(x, y, z)
+    }
+
+    /// Global velocity damping (`k_spring`) of every cloth.
+    pub fn set_damping(&mut self, damping: f32) {
+        let damping = ParamBounds::DAMPING.clamp(damping);
+        self.edit_params(|sim_params1, _| sim_params1.grid_k_radius[2] = damping);
+    }
+
+    /// Velocity damping per second of every cloth, applied at the end of each
+    /// substep after the collision response: the velocity is scaled by
+    /// `1 - damping * dt`, floored at 0. Unlike `set_damping` it doesn't go
+    /// through the integration, so it is a single knob to make the cloth settle
+    /// faster. 0 (the default) turns it off. Under Euler integration it runs in
+    /// the collision pass and stops with the "Collision" stage.
+    pub fn set_settle_damping(&mut self, damping: f32) {
+        let damping = ParamBounds::SETTLE_DAMPING.clamp(damping);
+        self.edit_params(|_, sim_params2| sim_params2.damping[0] = damping);
+    }
+
+    /// Speed below which a vertex of every cloth is stopped, checked after the
+    /// settle damping at the end of each substep, so a cloth at rest stops
+    /// jittering instead of creeping by tiny amounts forever. The forces are
+    /// integrated before the check, so a vertex wakes as soon as its springs
+    /// pull it faster than the threshold in a single substep. 0 (the default)
+    /// turns it off. Runs where the settle damping does.
+    pub fn set_sleep_threshold(&mut self, threshold: f32) {
+        let threshold = ParamBounds::SLEEP_THRESHOLD.clamp(threshold);
+        self.edit_params(|_, sim_params2| sim_params2.damping[3] = threshold);
+    }
+
+    /// Air drag of every cloth: a force of `-drag * n * dot(n, velocity - wind)`
+    /// for the vertex normal `n`, so broadside motion through the air is damped
+    /// and edge-on motion is not, and the wind carries the cloth rather than
+    /// fighting it. 0 (the default) turns it off.
+    pub fn set_air_drag(&mut self, drag: f32) {
+        let drag = ParamBounds::AIR_DRAG.clamp(drag);
+        self.edit_params(|_, sim_params2| sim_params2.damping[1] = drag);
+    }
+
+    /// Lets springs tear once stretched past `tear_length` times their rest
+    /// length, 0 (the default) turns tearing off. A torn spring stops pulling
+    /// for good, bending springs across it go with it, and the triangles that
+    /// lost an edge are no longer drawn. Only `reset` mends the cloth.
+    pub fn set_tear_length(&mut self, tear_length: f32) {
+        let tear_length = ParamBounds::TEAR_LENGTH.clamp(tear_length);
+        self.edit_params(|_, sim_params2| sim_params2.rest_length[3] = tear_length);
+    }
+
+    /// Turns the first collider into a soft ball: a mass-spring network along
+    /// the edges of an icosphere of its radius (642 vertices), stepped by the
+    /// compute shader once per substep after the cloth stages. The springs
+    /// along the edges hold the surface together and core springs pull every
+    /// vertex back to its place around the collider's center, which still
+    /// follows `set_sphere_motion`, so the ball sags under gravity and wobbles.
+    /// Instead of the rigid sphere, the cloth is kept an edge length away from
+    /// the ball's vertices, and the vertices of the first cloth within reach
+    /// push the ball in, so a draped cloth dents it.
+    ///
+    /// Only a `Collider::Sphere` can turn soft, built with its current center
+    /// and radius, and `set_colliders` turns it off once the first collider
+    /// isn't one. Experimental, off by default.
+    pub fn set_soft_sphere(&mut self, context: &Context, enabled: bool) {
+        self.soft_sphere = match self.colliders.first() {
+            Some(&Collider::Sphere { center, radius }) if enabled => Some(SoftSphere::new(context, &self.soft_sphere_layout, center, radius)),
+            _ => None,
+        };
+    }
+
+    /// When set, the cloth is confined inside the first collision sphere instead
+    /// of draping over it. The other spheres stay solid.
+    pub fn set_sphere_inverted(&mut self, inverted: bool) {
+        self.edit_params(|sim_params1, _| sim_params1.collision[0] = if inverted { 1.0 } else { 0.0 });
+    }
+
+    /// When set, a vertex stops for good the first time it touches a collider:
+    /// the collision pass puts it on the surface with zero velocity and makes
+    /// it fixed, so it is drawn and holds its springs like a pinned vertex.
+    /// Gives a shrink-wrapped cloth that conforms to the colliders. Turning it
+    /// off doesn't release the stuck vertices, only `reset` does. Off by default.
+    pub fn set_sticky_collision(&mut self, sticky: bool) {
+        self.edit_params(|sim_params1, _| sim_params1.colliders[2] = if sticky { 1.0 } else { 0.0 });
+    }
+
+    /// Adds a ground plane at height `y` that the cloth can't fall through, or
+    /// moves it. Vertices below it are put back on it and their downward
+    /// velocity is reversed and scaled by `restitution` (0 stops them, 1
+    /// bounces them back at full speed). With `IntegrationMethod::Verlet` the
+    /// velocity follows the corrected positions instead, so landings don't
+    /// bounce. The plane is drawn as a large floor quad.
+    pub fn set_ground_plane(&mut self, y: f32, restitution: f32) {
+        self.ground_plane = Some([y, ParamBounds::RESTITUTION.clamp(restitution)]);
+        self.ground_dirty = true;
+    }
+
+    /// Removes the ground plane, the cloth falls past the spheres again.
+    pub fn clear_ground_plane(&mut self) {
+        self.ground_plane = None;
+    }
+
+    /// Wind blowing along `dir`, with a strength of its length, plus value noise
+    /// of amplitude `turbulence` that drifts with the simulated time. Each
+    /// vertex only feels the part along its normal, so faces square to the wind
+    /// catch the most and the cloth flutters. A zero vector and turbulence
+    /// turn the wind off, which is the default.
+    pub fn set_wind(&mut self, dir: cgmath::Vector3<f32>, turbulence: f32) {
+        let strength = dir.magnitude();
+        let dir = if strength > ParamBounds::WIND.max { dir * (ParamBounds::WIND.max / strength) } else { dir };
+        self.wind = [dir.x, dir.y, dir.z, ParamBounds::TURBULENCE.clamp(turbulence)];
+    }
+
+    /// Replaces the colliders, `Collider`s or spheres given as centers and
+    /// radii, from the next update. Only the first `MAX_COLLIDERS` are kept, an
+    /// empty list turns collisions off.
+    pub fn set_colliders<C: Into<Collider>>(&mut self, colliders: Vec<C>) {
+        self.colliders = colliders.into_iter().take(MAX_COLLIDERS).map(Into::into).collect();
+        self.colliders_dirty = true;
+        if !matches!(self.colliders.first(), Some(Collider::Sphere { .. })) {
+            self.soft_sphere = None;
+        }
+        if let (Some(motion), Some(collider)) = (self.sphere_motion.as_mut(), self.colliders.first()) {
+            motion.base_center = collider.center();
+        }
+    }
+
+    /// Moves the first collider back and forth along `axis`, `amplitude` world
+    /// units either side of where it is now, `frequency` times per simulated
+    /// second. The collision test and the drawn shape both follow.
+    /// An amplitude or frequency of 0 stops it at its resting place.
+    pub fn set_sphere_motion(&mut self, amplitude: f32, frequency: f32, axis: cgmath::Vector3<f32>) {
+        let base_center = match (self.sphere_motion.take(), self.colliders.first()) {
+            (Some(motion), _) => motion.base_center,
+            (None, Some(collider)) => collider.center(),
+            (None, None) => return,
+        };
+        let offset = if axis.magnitude2() > 0.0 { axis.normalize() * amplitude } else { cgmath::Vector3::new(0.0, 0.0, 0.0) };
+        if offset.magnitude2() > 0.0 && frequency != 0.0 {
+            self.sphere_motion = Some(SphereMotion {
+                base_center,
+                offset,
+                frequency,
+                elapsed: 0.0,
+            });
+        } else {
+            self.colliders[0].set_center(base_center);
+            self.colliders_dirty = true;
+        }
+    }
+
+    // Advances the sphere motion by `delta_time` simulated seconds and moves the
+    // first collider to match
+    fn advance_sphere_motion(&mut self, delta_time: f32) {
+        let Some(motion) = self.sphere_motion.as_mut() else {
+            return;
+        };
+        motion.elapsed += delta_time;
+        if let Some(collider) = self.colliders.first_mut() {
+            collider.set_center(motion.center());
+            self.colliders_dirty = true;
+        }
+    }
+
+    /// Friction of the collision spheres: the share of its sliding velocity a
+    /// vertex in contact loses, from 0 (the default, it slides off freely) to 1
+    /// (it sticks where it lands), so a draped cloth can stay put.
+    pub fn set_sphere_friction(&mut self, friction: f32) {
+        let friction = ParamBounds::FRICTION.clamp(friction);
+        self.edit_params(|sim_params1, _| sim_params1.collision[2] = friction);
+    }
+
+    /// Bounciness of the collision spheres: the share of its speed into the
+    /// surface a vertex in contact bounces back with, from 0 (it lands dead)
+    /// to 1 (it keeps all of it). 0.7 by default.
+    pub fn set_sphere_restitution(&mut self, restitution: f32) {
+        let restitution = ParamBounds::RESTITUTION.clamp(restitution);
+        self.edit_params(|sim_params1, _| sim_params1.collision[3] = restitution);
+    }
+
+    /// Velocity damping (per second) applied only to vertices touching the sphere,
+    /// on top of the global damping. Stops resting cloth from jittering and creeping.
+    pub fn set_contact_damping(&mut self, damping: f32) {
+        let damping = ParamBounds::CONTACT_DAMPING.clamp(damping);
+        self.edit_params(|sim_params1, _| sim_params1.collision[1] = damping);
+    }
+
+    /// Selects the integrator of every cloth, see `IntegrationMethod`. Takes
+    /// effect from the next step, the velocities carry over.
+    pub fn set_integration_method(&mut self, method: IntegrationMethod) {
+        self.integration_method = method;
+    }
+
+    /// Selects how the stretch-limiting constraint projection is dispatched,
+    /// see `ConstraintSolver`.
+    pub fn set_constraint_solver(&mut self, solver: ConstraintSolver) {
+        self.constraint_solver = solver;
+    }
+
+    /// Constraint projection iterations per step, 0 (the default) turns the
+    /// projection off. Clamped to 16.
+    pub fn set_constraint_iterations(&mut self, iterations: u32) {
+        self.constraint_iterations = iterations.min(MAX_CONSTRAINT_ITERATIONS);
+    }
+
+    /// Upper bound of the force a single spring can exert, applied to each spring
+    /// before the forces on a vertex are summed (default 100).
+    ///
+    /// Unlike the per-step displacement limit and the contact speed limit, which
+    /// cut the motion of a vertex whatever caused it, this only tames the spring
+    /// that is over-tensioned, so the rest of the cloth keeps its normal
+    /// response. Lower it when a few springs blow up (sharp pins, deep sphere
+    /// penetration); keep the displacement limit for spikes coming from
+    /// everything at once, such as a too large time step.
+    pub fn set_max_spring_force(&mut self, force: f32) {
+        let force = ParamBounds::MAX_SPRING_FORCE.clamp(force);
+        self.edit_params(|_, sim_params2| sim_params2.stiffness[3] = force);
+    }
+
+    /// Loads the wind flow field from a Portable Float Map (see
+    /// `flow_map::read_pfm`). Each texel is a wind acceleration, scaled by the
+    /// flow strength and tiled over the world xz plane.
+    pub fn set_flow_texture(&mut self, context: &Context, path: impl AsRef<Path>) -> io::Result<()> {
+        let map = flow_map::read_pfm(BufReader::new(File::open(path)?))?;
+        self.set_flow_field(context, map.width, map.height, &map.texels);
+        Ok(())
+    }
+
+    /// Replaces the wind flow field with `width` x `height` xyz texels, row by row.
+    pub fn set_flow_field(&mut self, context: &Context, width: u32, height: u32, texels: &[[f32; 3]]) {
+        assert_eq!(texels.len(), (width * height) as usize, "flow field must have width * height texels");
+        self.cloth_bindings.flow_view = create_flow_texture(context, width, height, texels);
+        for cloth in &mut self.cloths {
+            cloth.compute_bind_groups = self.cloth_bindings.compute_bind_groups(context, &cloth.vertex_buffers, cloth.compute_buffers());
+        }
+    }
+
+    /// Scales the wind read from the flow texture, 0 disables it.
+    pub fn set_flow_strength(&mut self, strength: f32) {
+        let strength = ParamBounds::FLOW_STRENGTH.clamp(strength);
+        self.edit_params(|_, sim_params2| sim_params2.flow[1] = strength);
+    }
+
+    /// World space size of one tile of the flow texture.
+    pub fn set_flow_tile_size(&mut self, tile_size: f32) {
+        let tile_size = tile_size.max(0.01);
+        self.edit_params(|_, sim_params2| sim_params2.flow[0] = tile_size);
+    }
+
+    /// Scroll velocity of the flow texture over the xz plane, in tiles per simulated second.
+    pub fn set_flow_scroll(&mut self, scroll: [f32; 2]) {
+        self.flow_scroll = scroll;
+    }
+
+    // Scrolls the flow texture by one frame of simulated time
+    fn advance_flow(&mut self) {
+        let [du, dv] = self.flow_scroll.map(|speed| speed * self.frame_time);
+        for cloth in self.cloths.iter_mut().filter(|cloth| cloth.sim_params2.flow[1] > 0.0) {
+            let flow = &mut cloth.sim_params2.flow;
+            flow[2] = (flow[2] + du).rem_euclid(1.0);
+            flow[3] = (flow[3] + dv).rem_euclid(1.0);
+            cloth.params_dirty = true;
+        }
+    }
+
+    /// Enables or disables solver stages for debugging. Collision and constraints
+    /// skip their dispatches, gravity and springs are switched off in the
+    /// integration shader.
+    pub fn set_stage_toggles(&mut self, stages: StageToggles) {
+        self.stages = stages;
+    }
+
+    /// Keeps the fabric from passing through itself: vertices closer than
+    /// `radius` are pushed apart, unless they are within 2 rows and columns of
+    /// each other, which the springs already handle. The vertices are binned
+    /// into a spatial hash of `radius` sized cells each substep, so a pair is
+    /// only caught while their distance is under `radius`: fast folds can still
+    /// tunnel through. Only vertices of the same cloth collide. Off by default,
+    /// it costs four dispatches per substep.
+    pub fn set_self_collision(&mut self, radius: f32, enabled: bool) {
+        self.self_collision_radius = ParamBounds::SELF_COLLISION_RADIUS.clamp(radius);
+        self.self_collision = enabled;
+    }
+
+    /// Selects what the fabric color shows, see `ColorMode`. The heatmap modes
+    /// start with their default value range, except `Strain` on a cloth that
+    /// can tear: its range then ends at the tear length, so the top of the
+    /// gradient marks the springs about to break.
+    pub fn set_color_mode(&mut self, mode: ColorMode) {
+        self.color_mode = mode;
+        if let Some((_, range)) = mode.heatmap() {
+            let tear_length = self.primary().sim_params2.rest_length[3];
+            self.heatmap_range = if mode == ColorMode::Strain && tear_length > 1.0 { [0.0, tear_length - 1.0] } else { range };
+        }
+        self.materials_dirty = true;
+    }
+
+    /// Values mapped to the low and high end of the heatmap gradient.
+    pub fn set_heatmap_range(&mut self, min: f32, max: f32) {
+        self.heatmap_range = [min, max.max(min)];
+    }
+
+    // Stage toggles, the startup relaxation, the color mode, the collider count,
+    // the ground plane and the wind live in the uniforms of every cloth
+    fn sync_debug_options(&mut self) {
+        let gravity_disabled = if self.stages.gravity && self.relax_frames_left == 0 { 0.0 } else { 1.0 };
+        let springs_disabled = if self.stages.springs { 0.0 } else { 1.0 };
+        let color_mode = self.color_mode.index();
+        let integration_method = self.integration_method.index();
+        let collider_count = self.colliders.len() as f32;
+        let self_collision_radius = self.self_collision_radius;
+        let first_collider = if self.soft_sphere.is_some() { 1.0 } else { 0.0 };
+        let ground = self.ground_plane.map_or([0.0; 4], |[y, restitution]| [y, restitution, 1.0, 0.0]);
+        let wind = self.wind;
+        let clock = [self.elapsed_time, 0.0, 0.0, 0.0];
+        let wind_enabled = wind != [0.0; 4];
+        for cloth in &mut self.cloths {
+            let options = &mut cloth.sim_params1.options;
+            if options[1..] != [gravity_disabled, springs_disabled, color_mode] {
+                options[1] = gravity_disabled;
+                options[2] = springs_disabled;
+                options[3] = color_mode;
+                cloth.params_dirty = true;
+            }
+            if cloth.sim_params2.time[1..3] != self.heatmap_range {
+                cloth.sim_params2.time[1..3].copy_from_slice(&self.heatmap_range);
+                cloth.params_dirty = true;
+            }
+            if cloth.sim_params2.time[3] != integration_method {
+                cloth.sim_params2.time[3] = integration_method;
+                cloth.params_dirty = true;
+            }
+            let colliders = &mut cloth.sim_params1.colliders;
+            if [colliders[0], colliders[1], colliders[3]] != [collider_count, self_collision_radius, first_collider] {
+                colliders[0] = collider_count;
+                colliders[1] = self_collision_radius;
+                colliders[3] = first_collider;
+                cloth.params_dirty = true;
+            }
+            if cloth.sim_params2.ground != ground {
+                cloth.sim_params2.ground = ground;
+                cloth.params_dirty = true;
+            }
+            if cloth.sim_params2.wind != wind {
+                cloth.sim_params2.wind = wind;
+                cloth.params_dirty = true;
+            }
+            // The clock only matters to the turbulence, don't upload it every frame without wind
+            if wind_enabled {
+                cloth.sim_params2.clock = clock;
+                cloth.params_dirty = true;
+            }
+        }
+    }
+
+    /// Lambert lighting of the fabric and the sphere from their vertex normals,
+    /// on by default. When off they are drawn in flat colors, unless the detail
+    /// map is enabled.
+    pub fn set_lighting(&mut self, enabled: bool) {
+        self.lighting = enabled;
+        self.materials_dirty = true;
+    }
+
+    /// Shades the fabric with a tiled detail normal map (a plain weave unless
+    /// replaced with `set_detail_normal_map`), on top of the vertex normals.
+    /// Off by default.
+    pub fn set_detail_enabled(&mut self, enabled: bool) {
+        self.detail_enabled = enabled;
+        self.materials_dirty = true;
+    }
+
+    /// Repetitions of the detail normal map across each side of a cloth.
+    pub fn set_detail_tiling(&mut self, tiling: f32) {
+        self.detail_tiling = tiling.max(0.0);
+        self.materials_dirty = true;
+    }
+
+    /// Scale of the detail normals' tangent part, 0 leaves the surface normal as is.
+    pub fn set_detail_strength(&mut self, strength: f32) {
+        self.detail_strength = strength.max(0.0);
+        self.materials_dirty = true;
+    }
+
+    /// Skips the back faces of the fabric, about half the fragment work. The
+    /// underside of a folded or draped cloth then shows holes, keep it off for
+    /// thin double-sided cloth.
+    pub fn set_backface_culling(&mut self, enabled: bool) {
+        self.backface_culling = enabled;
+    }
+
+    /// Draws the sphere and the fabric twice: first writing only depth, then
+    /// shading with an `Equal` depth test, so `fs_main` runs once per pixel
+    /// however many layers a folded cloth stacks up. Pays off on dense grids
+    /// with lighting and the detail map on; off by default.
+    pub fn set_depth_prepass(&mut self, enabled: bool) {
+        self.depth_prepass = enabled;
+    }
+
+    /// Draws fading copies of the fabric trailing each vertex along its
+    /// velocity, a cheap motion blur for fast moving cloth.
+    pub fn set_motion_blur(&mut self, enabled: bool) {
+        self.motion_blur = enabled;
+    }
+
+    /// Length of the motion blur trail, in seconds of motion at the current
+    /// velocity, and opacity of its first copy.
+    pub fn set_motion_blur_shape(&mut self, time: f32, opacity: f32) {
+        self.motion_blur_time = time.max(0.0);
+        self.motion_blur_opacity = opacity.clamp(0.0, 1.0);
+        self.materials_dirty = true;
+    }
+
+    /// Views the scene through an orthographic camera framing what the orbit
+    /// camera does at the orbit center, keeping its orbit and zoom controls.
+    pub fn set_orthographic(&mut self, enabled: bool) {
+        self.orthographic = enabled;
+    }
+
+    /// Draws the fabric as filled triangles (the default), a wireframe or one
+    /// point per vertex, to look at the mesh topology. The wireframe shows every
+    /// triangle edge when the device supports `Features::POLYGON_MODE_LINE`,
+    /// otherwise only the structural edges. Bound to the M key, which cycles.
+    pub fn set_fabric_view(&mut self, view: FabricView) {
+        self.fabric_view = view;
+    }
+
+    /// Draws the structural edges of the fabric over it in `color`.
+    pub fn set_edge_overlay(&mut self, enabled: bool, color: [f32; 3]) {
+        self.edge_overlay = enabled;
+        self.edge_color = color;
+        self.materials_dirty = true;
+    }
+
+    /// Draws the springs of the fabric as lines instead of its faces, green at
+    /// rest length and red when stretched by 10% or more. The structural springs
+    /// are always drawn, the shear diagonals with `shear`. Toggled with the S key.
+    pub fn set_render_springs(&mut self, enabled: bool, shear: bool) {
+        self.render_springs = enabled;
+        self.render_shear_springs = shear;
+    }
+
+    /// Draws a line from every 8th vertex of every 8th row of each fabric along
+    /// the net force of its last step (springs, gravity, wind, drag and gusts),
+    /// yellow at the vertex and red at the tip, up to 0.4 units long. Shows
+    /// why the cloth moves the way it does. The compute pass stores the force
+    /// in the padding of the full precision vertex, so the glyphs stay off in
+    /// half precision. Toggled with the V key.
+    pub fn set_force_glyphs(&mut self, enabled: bool) {
+        self.force_glyphs = enabled && self.force_pipeline.is_some();
+    }
+
+    /// Flat color replacing the vertex colors of the fabric, and a darker shade
+    /// of it for the sphere. `None` restores the vertex colors.
+    pub fn set_fill_tint(&mut self, tint: Option<[f32; 3]>) {
+        self.fill_tint = tint;
+        self.materials_dirty = true;
+    }
+
+    /// Color multiplied into the back faces of the fabric, so the two sides
+    /// of a fold tell apart even unlit. Defaults to a warm shade, `None` draws
+    /// both sides alike. Back faces are only drawn without backface culling.
+    pub fn set_backface_tint(&mut self, tint: Option<[f32; 3]>) {
+        self.backface_tint = tint;
+        self.materials_dirty = true;
+    }
+
+    /// Flat color drawn behind the scene, `None` shows the clear color.
+    pub fn set_background(&mut self, color: Option<[f32; 3]>) {
+        self.background = color;
+        self.materials_dirty = true;
+    }
+
+    /// Color behind the scene: the background if there is one, else `CLEAR_COLOR`.
+    pub fn clear_color(&self) -> egui::Color32 {
+        match self.background {
+            Some(color) => egui::Rgba::from_rgb(color[0], color[1], color[2]).into(),
+            None => CLEAR_COLOR,
+        }
+    }
+
+    /// Moves on to the next of a few backgrounds for screenshots, from the
+    /// clear color through dark gray, white and black back to the clear color.
+    /// Bound to the B key.
+    pub fn cycle_background(&mut self) {
+        let next = match self.background.and_then(|color| BACKGROUND_PRESETS.iter().position(|preset| *preset == color)) {
+            Some(index) => BACKGROUND_PRESETS.get(index + 1).copied(),
+            None if self.background.is_some() => None,
+            None => Some(BACKGROUND_PRESETS[0]),
+        };
+        self.set_background(next);
+    }
+
+    /// Sets all the rendering toggles `preset` bundles. They can still be
+    /// changed one by one afterwards.
+    pub fn apply_render_preset(&mut self, preset: RenderPreset) {
+        self.render_preset = preset;
+        match preset {
+            RenderPreset::Standard => {
+                self.set_orthographic(false);
+                self.set_edge_overlay(false, BLUEPRINT_EDGE);
+                self.set_fill_tint(None);
+                self.set_background(None);
+                self.set_lighting(true);
+            }
+            RenderPreset::Blueprint => {
+                self.set_orthographic(true);
+                self.set_edge_overlay(true, BLUEPRINT_EDGE);
+                self.set_fill_tint(Some(BLUEPRINT_FILL));
+                self.set_background(Some(BLUEPRINT_BACKGROUND));
+                self.set_detail_enabled(false);
+                self.set_textured(false);
+                self.set_lighting(false);
+                self.set_motion_blur(false);
+                self.set_color_mode(ColorMode::Material);
+            }
+        }
+    }
+
+    fn upload_ortho_camera(&self, context: &Context) {
+        let (radius, longitude, latitude) = (self.camera.radius(), self.camera.longitude(), self.camera.latitude());
+        let matrices = CameraUniform {
+            view: projection::orbit_view(radius, longitude, latitude).into(),
+            proj: projection::orbit_orthographic(radius, self.lens).into(),
+        };
+        context.queue().write_buffer(&self.ortho_camera_buffer, 0, bytemuck::cast_slice(&[matrices]));
+    }
+
+    /// Draws the fabric with the color texture instead of the vertex colors,
+    /// stretched once over each cloth (u along the columns, v along the rows).
+    /// Off by default. The debug color modes still win over the texture.
+    pub fn set_textured(&mut self, textured: bool) {
+        self.textured = textured;
+        self.materials_dirty = true;
+    }
+
+    /// Replaces the fabric texture (a checkerboard by default) with a `width` x
+    /// `height` RGBA8 image in sRGB, rows from the top, and turns it on.
+    pub fn set_texture(&mut self, context: &Context, rgba: &[u8], width: u32, height: u32) {
+        assert_eq!(rgba.len(), (width * height * 4) as usize, "fabric texture must be width * height RGBA8 texels");
+        self.cloth_bindings.fabric_texture_view = create_fabric_texture(context, width, height, rgba);
+        self.rebuild_material_bind_groups(context);
+        self.set_textured(true);
+    }
+
+    // After a texture of the material bind group was replaced
+    fn rebuild_material_bind_groups(&mut self, context: &Context) {
+        for cloth in &mut self.cloths {
+            cloth.material_bind_group = self.cloth_bindings.material_bind_group(context, &cloth.material_buffer, &cloth.debug_color_buffer);
+        }
+        self.sphere_material_bind_group =
+            self.cloth_bindings.material_bind_group(context, &self.sphere_material_buffer, &self.sphere_debug_color_buffer);
+        self.background_material_bind_group =
+            self.cloth_bindings.material_bind_group(context, &self.background_material_buffer, &self.sphere_debug_color_buffer);
+        self.ground_material_bind_group =
+            self.cloth_bindings.material_bind_group(context, &self.ground_material_buffer, &self.sphere_debug_color_buffer);
+    }
+
+    /// Replaces the detail normal map with a `width` x `height` RGBA8 tangent-space
+    /// normal map (xyz mapped from -1..1 to 0..255, z up).
+    pub fn set_detail_normal_map(&mut self, context: &Context, width: u32, height: u32, rgba: &[u8]) {
+        assert_eq!(rgba.len(), (width * height * 4) as usize, "detail normal map must be width * height RGBA8 texels");
+        self.cloth_bindings.detail_view = create_detail_texture(context, width, height, rgba);
+        self.rebuild_material_bind_groups(context);
+    }
+
+    // The soft sphere follows the first collider's center and falls with the
+    // first cloth's gravity and substep
+    fn upload_soft_sphere(&self, context: &Context) {
+        let (Some(soft_sphere), Some(collider)) = (&self.soft_sphere, self.colliders.first()) else {
+            return;
+        };
+        let sim_params2 = &self.primary().sim_params2;
+        let gravity_enabled = self.stages.gravity && self.relax_frames_left == 0;
+        let gravity = if gravity_enabled { [sim_params2.gravity[0], sim_params2.gravity[1], sim_params2.gravity[2]] } else { [0.0; 3] };
+        soft_sphere.upload_params(context, collider.center(), gravity, sim_params2.time[0]);
+    }
+
+    fn upload_colliders(&mut self, context: &Context) {
+        if !self.colliders_dirty {
+            return;
+        }
+        let colliders: Vec<GpuCollider> = self.colliders.iter().map(|collider| collider.to_gpu()).collect();
+        context.queue().write_buffer(&self.cloth_bindings.collider_buffer, 0, bytemuck::cast_slice(&colliders));
+        self.colliders_dirty = false;
+    }
+
+    fn upload_ground(&mut self, context: &Context) {
+        let Some([y, _]) = self.ground_plane.filter(|_| self.ground_dirty) else {
+            return;
+        };
+        let y = y - GROUND_DRAW_OFFSET;
+        // Counter-clockwise seen from above, so the top is the front face
+        let vertices: Vec<Vertex> = [(-1.0, -1.0), (-1.0, 1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, 1.0), (1.0, -1.0)]
+            .iter()
+            .map(|(x, z)| Vertex {
+                position: [x * GROUND_HALF_SIZE, y, z * GROUND_HALF_SIZE, 1.0],
+                color: [0.45, 0.45, 0.45, 1.0],
+                mass: 1.0,
+                force: [0.0; 3],
+                velocity: [0.0; 4],
+                fixed: 1.0,
+                springs: INTACT_SPRINGS,
+                grid: [0; 2],
+                normal: [0.0, 1.0, 0.0, 0.0],
+                pin_target: [0.0; 4],
+            })
+            .collect();
+        context.queue().write_buffer(&self.ground_vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.ground_dirty = false;
+    }
+
+    fn upload_materials(&mut self, context: &Context) {
+        if !self.materials_dirty {
+            return;
+        }
+        let enabled = if self.detail_enabled { 1.0 } else { 0.0 };
+        let textured = if self.textured { 1.0 } else { 0.0 };
+        let lighting = if self.lighting { 1.0 } else { 0.0 };
+        let tint = self.fill_tint.map_or([0.0; 4], |[r, g, b]| [r, g, b, 1.0]);
+        let [r, g, b] = self.edge_color;
+        let edge = [r, g, b, 1.0];
+        let backface = self.backface_tint.map_or([0.0; 4], |[r, g, b]| [r, g, b, 1.0]);
+        for cloth in &self.cloths {
+            let material = FabricMaterial {
+                grid: [cloth.grid_rows as f32, cloth.grid_cols as f32, self.color_mode.index(), textured],
+                detail: [self.detail_tiling, self.detail_strength, enabled, lighting],
+                motion: [self.motion_blur_time, self.motion_blur_opacity, MOTION_BLUR_SAMPLES as f32, 0.0],
+                tint,
+                edge,
+                springs: [cloth.sim_params2.rest_length[0], cloth.sim_params2.rest_length[1], 0.0, 0.0],
+                backface,
+                rim: [0.0; 4],
+            };
+            context.queue().write_buffer(&cloth.material_buffer, 0, bytemuck::cast_slice(&[material]));
+        }
+
+        // A darker shade of the fill keeps the sphere apart from the fabric
+        let sphere = FabricMaterial {
+            grid: [0.0; 4],
+            detail: [0.0, 0.0, 0.0, lighting],
+            motion: [0.0; 4],
+            tint: self.fill_tint.map_or([0.0; 4], |[r, g, b]| [r * 0.7, g * 0.7, b * 0.7, 1.0]),
+            edge,
+            springs: [0.0; 4],
+            backface: [0.0; 4],
+            rim: SPHERE_RIM,
+        };
+        context.queue().write_buffer(&self.sphere_material_buffer, 0, bytemuck::cast_slice(&[sphere]));
+        let [r, g, b] = self.background.unwrap_or([0.0; 3]);
+        let background = FabricMaterial {
+            tint: [r, g, b, 1.0],
+            ..sphere
+        };
+        context.queue().write_buffer(&self.background_material_buffer, 0, bytemuck::cast_slice(&[background]));
+        // The sphere's colors without the rim, which would light up the far end of the flat floor
+        let ground = FabricMaterial { rim: [0.0; 4], ..sphere };
+        context.queue().write_buffer(&self.ground_material_buffer, 0, bytemuck::cast_slice(&[ground]));
+        self.materials_dirty = false;
+    }
+
+    /// Enables the one-line warning printed when a velocity spike makes a frame
+    /// fall back to substeps.
+    pub fn set_substep_logging(&mut self, enabled: bool) {
+        self.log_substeps = enabled;
+    }
+
+    // Highest speed read back over all cloths
+    fn max_speed(&self) -> f32 {
+        self.last_stats().max_speed
+    }
+
+    /// Max vertex speed and max structural stretch over all cloths, reduced on
+    /// the GPU at the end of every frame. The readback is asynchronous, so the
+    /// values lag a frame or two behind the simulation, and they stay at zero
+    /// until the first one arrives.
+    pub fn last_stats(&self) -> SimStats {
+        self.cloths.iter().map(|cloth| cloth.stats).fold(SimStats::default(), SimStats::combine)
+    }
+
+    /// Simulates the real frame time (the `delta_time` of `update`) instead of
+    /// a fixed `time_step` per frame. Each frame is split into as many equal
+    /// substeps as needed to keep them under `max_substep_dt`, but never more
+    /// than 8: a longer frame, e.g. while the window is dragged, is cut short
+    /// and the simulation falls behind real time instead of exploding or
+    /// asking for ever more work. 0 (the default) goes back to fixed steps.
+    pub fn set_max_substep_dt(&mut self, max_substep_dt: f32) {
+        self.max_substep_dt = if max_substep_dt > 0.0 { ParamBounds::TIME_STEP.clamp(max_substep_dt) } else { 0.0 };
+    }
+
+    /// Decouples the simulation from the frame rate: `update` accumulates the
+    /// real frame time and simulates it in steps of exactly `fixed_step`
+    /// seconds, 1 / 120 for the "Fixed rate" checkbox. A frame runs as many
+    /// whole steps as the accumulated time covers, possibly none on a fast
+    /// display, and the leftover carries over to the next frame. The state after
+    /// N steps doesn't depend on how the frames grouped them, so runs can be
+    /// compared step for step. At most 8 steps catch up per frame: after a
+    /// longer stall the backlog is dropped and the simulation falls behind real
+    /// time. The most recent state is drawn, not interpolated between steps.
+    /// Takes precedence over `set_max_substep_dt`, 0 (the default) goes back to
+    /// a fixed `time_step` per frame.
+    pub fn set_fixed_step(&mut self, fixed_step: f32) {
+        self.fixed_step = if fixed_step > 0.0 { ParamBounds::TIME_STEP.clamp(fixed_step) } else { 0.0 };
+        self.step_accumulator = 0.0;
+    }
+
+    /// Slow motion below 1 and fast forward above, clamped to 0.05..=8 and 1
+    /// by default. The simulated time of every frame is multiplied by it: the
+    /// fixed `time_step`, the real frame time or the time fed to the fixed
+    /// step accumulator. Rendering is unaffected. A fixed `time_step` sped up
+    /// is split into up to 8 substeps so each one stays within it, slowed
+    /// down it runs one shorter step per frame. The `[` and `]` keys halve
+    /// and double it.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.clamp(*TIME_SCALE_RANGE.start(), *TIME_SCALE_RANGE.end());
+    }
+
+    /// Watches the total kinetic energy of the stats readback and, when it
+    /// grows more than `set_energy_growth_limit` times from one readback to
+    /// the next (or stops being finite), adds a strong settle damping to every
+    /// cloth for the next 60 simulated frames. Energies below 1 never count as
+    /// a spike. The readback lags a frame or two, so the boost comes a little
+    /// late. Off by default.
+    pub fn set_auto_stabilize(&mut self, enabled: bool) {
+        self.auto_stabilize = enabled;
+        if !enabled {
+            self.stabilize_frames_left = 0;
+        }
+    }
+
+    /// Growth factor of the kinetic energy between two readbacks past which
+    /// `set_auto_stabilize` boosts the damping, at least 1 and 4 by default.
+    pub fn set_energy_growth_limit(&mut self, limit: f32) {
+        self.energy_growth_limit = limit.max(1.0);
+    }
+
+    /// Whether the simulation has come to rest: the max vertex speed of the
+    /// stats readback stayed below the settle speed for the last
+    /// `settle_frames` frames that were read back, see `set_settle_threshold`.
+    /// False until enough stats have arrived, and again after a reset or a new
+    /// scene. The readback lags a frame or two behind the simulation.
+    pub fn is_settled(&self) -> bool {
+        self.settled_frames >= self.settle_frames
+    }
+
+    /// Max vertex speed below which `is_settled` counts a frame as at rest,
+    /// and how many frames in a row it takes, 0.05 and 30 by default. A
+    /// speed that isn't a positive number never settles. Starts the count
+    /// over.
+    pub fn set_settle_threshold(&mut self, speed: f32, frames: u32) {
+        self.settle_speed = speed.max(0.0);
+        self.settle_frames = frames.max(1);
+        self.settled_frames = 0;
+    }
+
+    /// Runs the whole solver (forces and integration, constraints, collisions)
+    /// at least `iterations` times per frame, clamped to 1..=32 and 1 by
+    /// default. The frame is split into that many substeps of equal dt, each
+    /// one reading the vertex buffer the previous one wrote, so the springs
+    /// relax several times per frame: the cloth gets visibly stiffer and less
+    /// stretchy at the same stiffness. The compute cost grows linearly with the
+    /// count. Real time stepping and velocity spikes can still ask for more.
+    pub fn set_iterations(&mut self, iterations: u32) {
+        self.iterations = iterations.clamp(*SOLVER_ITERATIONS.start(), *SOLVER_ITERATIONS.end());
+    }
+
+    /// Pushes every free fabric vertex of every cloth by `velocity` over the
+    /// next simulated frame, a one-off burst on top of the steady wind. It acts
+    /// as a force for that frame only, spread over its substeps like gravity.
+    pub fn gust(&mut self, velocity: cgmath::Vector3<f32>) {
+        self.pending_gust = Some(velocity);
+    }
+
+    /// A `gust` of `strength` along the camera's view, away from the viewer.
+    /// Bound to W.
+    pub fn gust_from_camera(&mut self, strength: f32) {
+        self.gust(projection::orbit_forward(self.camera.longitude(), self.camera.latitude()) * strength);
+    }
+
+}
+
+// <name>-<unix seconds>.<extension> in the directory of the executable
+fn output_path(name: &str, extension: &str) -> io::Result<PathBuf> {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    Ok(std::env::current_exe()?.with_file_name(format!("{}-{}.{}", name, seconds, extension)))
+}
+
+impl App for InstanceApp {
+    fn input(&mut self, input: egui::InputState, context: &wgpu_bootstrap::Context) {
+        if !self.grab_input(&input) {
+            self.camera.input(input.clone(), context);
+        }
+        if input.raw_scroll_delta.y != 0.0 {
+            let new_radius = (self.camera.radius() - input.raw_scroll_delta.y / 10.0).clamp(MIN_CAMERA_RADIUS, MAX_CAMERA_RADIUS);
+            self.camera.set_radius(new_radius).update(context);
+        }
+        if self.ruler_enabled && !self.pointer_over_ui && input.pointer.primary_clicked() {
+            if let Some(point) = input.pointer.interact_pos().and_then(|pos| self.pick_ruler_point(pos, input.screen_rect())) {
+                self.ruler.click(point);
+            }
+        }
+        if !self.keyboard_over_ui {
+            if input.key_pressed(egui::Key::R) {
+                self.reset(context);
+            }
+            if input.key_pressed(egui::Key::Space) {
+                self.set_paused(!self.paused);
+            }
+            if input.key_pressed(egui::Key::Period) {
+                self.request_step();
+            }
+            if input.key_pressed(egui::Key::S) {
+                self.set_render_springs(!self.render_springs, self.render_shear_springs);
+            }
+            if input.key_pressed(egui::Key::Home) {
+                self.reset_camera(context);
+            }
+            if input.key_pressed(egui::Key::W) {
+                self.gust_from_camera(GUST_STRENGTH);
+            }
+            if input.key_pressed(egui::Key::G) {
+                self.set_gravity(-self.gravity());
+            }
+            if input.key_pressed(egui::Key::F) {
+                self.set_frame_overlay(!self.show_frame_overlay);
+            }
+            if input.key_pressed(egui::Key::OpenBracket) {
+                self.set_time_scale(self.time_scale / 2.0);
+            }
+            if input.key_pressed(egui::Key::CloseBracket) {
+                self.set_time_scale(self.time_scale * 2.0);
+            }
+            if input.key_pressed(egui::Key::V) {
+                self.set_force_glyphs(!self.force_glyphs);
+            }
+            if input.key_pressed(egui::Key::M) {
+                self.set_fabric_view(self.fabric_view.next());
+            }
+            if input.key_pressed(egui::Key::B) {
+                self.cycle_background();
+            }
+            if input.key_pressed(egui::Key::Tab) {
+                self.nudged_corner = (self.nudged_corner + 1) % 4;
+            }
+            // Arrows move the corner along x and y, or along z with shift held
+            let axis = |negative, positive| (input.key_down(positive) as i32 - input.key_down(negative) as i32) as f32;
+            let horizontal = axis(egui::Key::ArrowLeft, egui::Key::ArrowRight);
+            let vertical = axis(egui::Key::ArrowDown, egui::Key::ArrowUp);
+            if horizontal != 0.0 || vertical != 0.0 {
+                let offset = if input.modifiers.shift {
+                    cgmath::Vector3::new(horizontal, 0.0, -vertical)
+                } else {
+                    cgmath::Vector3::new(horizontal, vertical, 0.0)
+                };
+                self.nudge_corner(offset * CORNER_NUDGE_STEP);
+            }
+            if input.key_pressed(egui::Key::P) {
+                match output_path("capture", "png").and_then(|path| self.save_capture(context, &path).map(|_| path)) {
+                    Ok(path) => eprintln!("saved {}", path.display()),
+                    Err(error) => eprintln!("warning: could not save the capture: {}", error),
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, delta_time: f32, context: &wgpu_bootstrap::Context) {
+        self.advance(delta_time, context);
+    }
+    
+    fn ui(&mut self, ctx: &egui::Context, context: &wgpu_bootstrap::Context) {
+        self.panel(ctx, context);
+        if self.show_vertex_ids {
+            self.draw_vertex_ids(ctx);
+        }
+        self.draw_cloth_labels(ctx);
+        if self.show_frame_overlay {
+            self.draw_frame_overlay(ctx);
+        }
+        if self.ruler_enabled {
+            self.draw_ruler(ctx);
+        }
+        self.pointer_over_ui = ctx.is_pointer_over_area();
+        self.keyboard_over_ui = ctx.wants_keyboard_input();
+    }
+
+    fn render(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        self.draw_scene(render_pass);
+    }
+}
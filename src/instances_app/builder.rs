@@ -0,0 +1,511 @@
+use std::fmt;
+
+use super::cloth::{default_sim_params, fabric_grid, jitter_positions, ClothDesc};
+use super::{
+    ClothPreset, Collider, ColliderMesh, InstanceApp, MaterialParam, Precision, Scene, WorkgroupLayout, DEFAULT_COLLIDER_RADIUS, DEFAULT_GRID_SIZE,
+    DEFAULT_SIDE_LENGTH, DEFAULT_TIME_STEP, FLAG_WIND, GRID_SIZE_RANGE, MAX_COLLIDERS, MAX_CONSTRAINT_ITERATIONS, SEED_JITTER, STIFFNESS_STRIP_VALUES,
+};
+use crate::cli_args::{self, ArgsError};
+use crate::env_config::{self, EnvError};
+use crate::gpu_context::Context;
+use crate::param_bounds::ParamBounds;
+use wgpu_bootstrap::{cgmath, wgpu};
+use cgmath::{One, Rotation};
+use serde::{Deserialize, Serialize};
+
+/// Placement of a cloth instance: its grid is built flat in the local xz plane
+/// and then rotated and translated into the world.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClothTransform {
+    pub translation: cgmath::Vector3<f32>,
+    pub rotation: cgmath::Quaternion<f32>,
+}
+
+impl ClothTransform {
+    pub fn from_translation(translation: cgmath::Vector3<f32>) -> Self {
+        ClothTransform {
+            translation,
+            rotation: cgmath::Quaternion::one(),
+        }
+    }
+
+    pub fn transform_point(&self, point: cgmath::Vector3<f32>) -> cgmath::Vector3<f32> {
+        self.rotation.rotate_vector(point) + self.translation
+    }
+
+    // Quaternion in the (x, y, z, w) layout of SimParams1::orientation
+    pub(super) fn orientation(&self) -> [f32; 4] {
+        [self.rotation.v.x, self.rotation.v.y, self.rotation.v.z, self.rotation.s]
+    }
+}
+
+/// A cloth added to the scene by `InstanceApp::add_cloth`.
+///
+/// The grid is `rows` x `cols` vertices, `side_length` wide along x with the
+/// rows spaced the same, built flat in the local xz plane with row 0 at -z and
+/// placed in the world by `transform`, like the cloths of the built-in scenes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClothConfig {
+    pub rows: u32,
+    pub cols: u32,
+    pub side_length: f32,
+    pub transform: ClothTransform,
+    /// Indices of the pinned vertices, row by row. Out of range ones are ignored.
+    pub pinned: Vec<u32>,
+    /// Drawn above the cloth when not empty
+    pub label: String,
+}
+
+impl Default for ClothConfig {
+    fn default() -> Self {
+        ClothConfig {
+            rows: DEFAULT_GRID_SIZE,
+            cols: DEFAULT_GRID_SIZE,
+            side_length: DEFAULT_SIDE_LENGTH,
+            transform: ClothTransform::from_translation(cgmath::Vector3::new(0.0, 2.0, 0.0)),
+            pinned: Vec::new(),
+            label: String::new(),
+        }
+    }
+}
+
+/// Size and placement of the single cloth scene.
+///
+/// The cloth is `rows` x `cols` vertices, `side_length` wide along x with the
+/// rows spaced the same, and starts flat at `initial_height` above the sphere.
+/// The rest lengths follow the grid spacing.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FabricConfig {
+    pub rows: u32,
+    pub cols: u32,
+    pub side_length: f32,
+    pub initial_height: f32,
+}
+
+impl Default for FabricConfig {
+    fn default() -> Self {
+        FabricConfig {
+            rows: DEFAULT_GRID_SIZE,
+            cols: DEFAULT_GRID_SIZE,
+            side_length: DEFAULT_SIDE_LENGTH,
+            initial_height: 2.0,
+        }
+    }
+}
+
+/// A cloth grid that can't be built, see `ClothBuilder::try_build`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GridLimitError {
+    /// Fewer than 2 or more than 512 rows or columns
+    Size { rows: u32, cols: u32 },
+    /// A grid larger than the device can simulate
+    Device {
+        rows: u32,
+        cols: u32,
+        /// The `wgpu::Limits` field the grid exceeds
+        limit_name: &'static str,
+        /// What `required` and `limit` count, bytes or workgroups along one axis
+        unit: &'static str,
+        required: u64,
+        limit: u64,
+        /// Side of the largest square grid within the limit
+        max_grid_size: u32,
+    },
+}
+
+impl fmt::Display for GridLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridLimitError::Size { rows, cols } => write!(
+                f,
+                "a {} x {} cloth is not supported, rows and columns must be within {}..={}",
+                rows,
+                cols,
+                GRID_SIZE_RANGE.start(),
+                GRID_SIZE_RANGE.end()
+            ),
+            GridLimitError::Device { rows, cols, limit_name, unit, required, limit, max_grid_size } => write!(
+                f,
+                "a {} x {} cloth needs {} {} but the device's {} is {}, use a grid of at most {} x {}",
+                rows, cols, required, unit, limit_name, limit, max_grid_size, max_grid_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GridLimitError {}
+
+/// Startup settings of an `InstanceApp`, see `from_env` for batch runs.
+///
+/// The grid, stiffness and gravity apply to the single cloth scene, also when
+/// it is rebuilt with `single_cloth`.
+#[derive(Clone, Debug)]
+pub struct ClothBuilder {
+    pub(super) precision: Precision,
+    pub(super) workgroup_layout: WorkgroupLayout,
+    pub(super) fabric: FabricConfig,
+    stiffness: [f32; 3],
+    gravity: f32,
+    pub(super) time_step: f32,
+    pub(super) constraint_iterations: u32,
+    pub(super) relax_frames: u32,
+    pub(super) colliders: Vec<Collider>,
+    pub(super) collider_mesh: Option<ColliderMesh>,
+    scene: Scene,
+    pub(super) preset: ClothPreset,
+    pub(super) seed: Option<u64>,
+    soft_sphere: bool,
+}
+
+impl Default for ClothBuilder {
+    fn default() -> Self {
+        ClothBuilder {
+            precision: Precision::F32,
+            workgroup_layout: WorkgroupLayout::Linear,
+            fabric: FabricConfig::default(),
+            stiffness: [25.0, 15.0, 5.0],
+            gravity: 6.8,
+            time_step: DEFAULT_TIME_STEP,
+            constraint_iterations: 0,
+            relax_frames: 0,
+            colliders: vec![Collider::Sphere {
+                center: cgmath::Vector3::new(0.0, 0.0, 0.0),
+                radius: DEFAULT_COLLIDER_RADIUS,
+            }],
+            collider_mesh: None,
+            scene: Scene::SingleCloth,
+            preset: ClothPreset::DrapeOverSphere,
+            seed: None,
+            soft_sphere: false,
+        }
+    }
+}
+
+impl ClothBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Workgroups of the per-vertex compute passes, `Linear` by default.
+    pub fn workgroup_layout(mut self, layout: WorkgroupLayout) -> Self {
+        self.workgroup_layout = layout;
+        self
+    }
+
+    /// Vertices per side of the square cloth, the cloth keeps its size so the
+    /// rest lengths follow the grid spacing. Sizes outside 2..=512 make
+    /// `try_build` fail.
+    pub fn grid_size(mut self, grid_size: u32) -> Self {
+        self.fabric.rows = grid_size;
+        self.fabric.cols = grid_size;
+        self
+    }
+
+    /// Grid resolution, size and starting height of the cloth. Rows or
+    /// columns outside 2..=512 make `try_build` fail.
+    pub fn fabric(mut self, config: FabricConfig) -> Self {
+        self.fabric = config;
+        self
+    }
+
+    /// Structural, shear and bending stiffness.
+    pub fn stiffness(mut self, stiffness: [f32; 3]) -> Self {
+        self.stiffness = stiffness.map(|value| ParamBounds::STIFFNESS.clamp(value));
+        self
+    }
+
+    /// Gravity magnitude, pointing down.
+    pub fn gravity(mut self, gravity: f32) -> Self {
+        self.gravity = ParamBounds::GRAVITY.clamp(gravity);
+        self
+    }
+
+    /// Simulated seconds per frame.
+    pub fn time_step(mut self, time_step: f32) -> Self {
+        self.time_step = ParamBounds::TIME_STEP.clamp(time_step);
+        self
+    }
+
+    pub fn constraint_iterations(mut self, iterations: u32) -> Self {
+        self.constraint_iterations = iterations.min(MAX_CONSTRAINT_ITERATIONS);
+        self
+    }
+
+    /// Frames run without gravity when the single cloth scene starts, so the
+    /// springs settle to their rest lengths before the cloth starts falling.
+    pub fn relax_frames(mut self, frames: u32) -> Self {
+        self.relax_frames = frames;
+        self
+    }
+
+    /// Colliders, `Collider`s or spheres as centers and radii, one sphere of
+    /// radius 1.4 at the origin by default. Only the first `MAX_COLLIDERS` are kept.
+    pub fn colliders<C: Into<Collider>>(mut self, colliders: Vec<C>) -> Self {
+        self.colliders = colliders.into_iter().take(MAX_COLLIDERS).map(Into::into).collect();
+        self
+    }
+
+    /// Draws the triangle mesh `positions` / `indices` instead of the ball. The
+    /// cloth collides with its bounding sphere, which replaces the colliders,
+    /// so the mesh shows where it was given. Colliders set afterwards each draw
+    /// the mesh fit to their sphere. Indices must be below `positions.len()`.
+    pub fn collider_mesh(mut self, positions: &[cgmath::Vector3<f32>], indices: &[u32]) -> Self {
+        let (mesh, bounds) = ColliderMesh::fit(positions, indices);
+        self.collider_mesh = Some(mesh);
+        self.colliders = vec![bounds.into()];
+        self
+    }
+
+    /// Breaks the symmetry of the single cloth: every vertex starts moved by a
+    /// random offset of up to `SEED_JITTER` of the grid spacing along each
+    /// axis, drawn from `seed`. The same seed gives the same cloth on every run
+    /// and after every reset. Without a seed (the default) the grid is exact.
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Scene the app starts with, the single cloth by default.
+    pub fn scene(mut self, scene: Scene) -> Self {
+        self.scene = scene;
+        self
+    }
+
+    /// Layout of the single cloth: where it starts, which vertices are pinned
+    /// and, for the flag, the wind. Draped over the sphere by default. The pins
+    /// are part of the initial state, so `reset` and `single_cloth` bring them
+    /// back.
+    pub fn preset(mut self, preset: ClothPreset) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    /// Starts with the first collider as a soft ball, see
+    /// `InstanceApp::set_soft_sphere`.
+    pub fn soft_sphere(mut self, soft: bool) -> Self {
+        self.soft_sphere = soft;
+        self
+    }
+
+    /// Overrides the settings with the command line options in `args`
+    /// (without the program name), see `cli_args::USAGE`. Fails on the first
+    /// unknown option or malformed value.
+    pub fn from_args<I: IntoIterator<Item = String>>(mut self, args: I) -> Result<Self, ArgsError> {
+        let flags = ["--grid", "--stiffness", "--gravity", "--time-step", "--iterations", "--relax-frames", "--precision", "--workgroup", "--scene", "--preset", "--seed"];
+        for (flag, value) in cli_args::flag_values(args, &flags)? {
+            self = match flag {
+                "--grid" => self.grid_size(env_config::parse_u32(flag, &value, GRID_SIZE_RANGE)?),
+                "--stiffness" => self.stiffness(env_config::parse_f32_list(flag, &value, ParamBounds::STIFFNESS)?),
+                "--gravity" => self.gravity(env_config::parse_f32(flag, &value, ParamBounds::GRAVITY)?),
+                "--time-step" => self.time_step(env_config::parse_f32(flag, &value, ParamBounds::TIME_STEP)?),
+                "--iterations" => self.constraint_iterations(env_config::parse_u32(flag, &value, 0..=MAX_CONSTRAINT_ITERATIONS)?),
+                "--relax-frames" => self.relax_frames(env_config::parse_u32(flag, &value, 0..=u32::MAX)?),
+                "--precision" => self.precision(env_config::parse_choice(flag, &value, &[("f32", Precision::F32), ("f16", Precision::F16)])?),
+                "--workgroup" => self.workgroup_layout(env_config::parse_choice(flag, &value, &WorkgroupLayout::NAMES)?),
+                "--seed" => self.seed(Some(env_config::parse_u64(flag, &value, 0..=u64::MAX)?)),
+                "--preset" => self.preset(env_config::parse_choice(flag, &value, &ClothPreset::NAMES)?),
+                _ => self.scene(env_config::parse_choice(flag, &value, &Scene::NAMES)?),
+            };
+        }
+        Ok(self)
+    }
+
+    /// Overrides the settings with the `CLOTH_*` environment variables that
+    /// are set (see the README), failing on the first malformed one.
+    pub fn from_env(mut self) -> Result<Self, EnvError> {
+        if let Some(precision) = env_config::choice_var("CLOTH_PRECISION", &[("f32", Precision::F32), ("f16", Precision::F16)])? {
+            self = self.precision(precision);
+        }
+        if let Some(layout) = env_config::choice_var("CLOTH_WORKGROUP", &WorkgroupLayout::NAMES)? {
+            self = self.workgroup_layout(layout);
+        }
+        if let Some(grid_size) = env_config::u32_var("CLOTH_GRID", GRID_SIZE_RANGE)? {
+            self = self.grid_size(grid_size);
+        }
+        if let Some(stiffness) = env_config::f32_list_var("CLOTH_STIFFNESS", ParamBounds::STIFFNESS)? {
+            self = self.stiffness(stiffness);
+        }
+        if let Some(gravity) = env_config::f32_var("CLOTH_GRAVITY", ParamBounds::GRAVITY)? {
+            self = self.gravity(gravity);
+        }
+        if let Some(time_step) = env_config::f32_var("CLOTH_TIME_STEP", ParamBounds::TIME_STEP)? {
+            self = self.time_step(time_step);
+        }
+        if let Some(iterations) = env_config::u32_var("CLOTH_ITERATIONS", 0..=MAX_CONSTRAINT_ITERATIONS)? {
+            self = self.constraint_iterations(iterations);
+        }
+        if let Some(frames) = env_config::u32_var("CLOTH_RELAX_FRAMES", 0..=u32::MAX)? {
+            self = self.relax_frames(frames);
+        }
+        if let Some(seed) = env_config::u64_var("CLOTH_SEED", 0..=u64::MAX)? {
+            self = self.seed(Some(seed));
+        }
+        Ok(self)
+    }
+
+    /// Checks the single cloth before building, so a grid that can't be
+    /// simulated is an error rather than a panic inside wgpu. Rows and columns
+    /// must be within 2..=512, the vertex buffer must fit the storage buffer
+    /// binding (and buffer) size of the device, and the one-thread-per-vertex
+    /// dispatches its workgroup count.
+    pub fn try_build(&self, context: &Context) -> Result<InstanceApp, GridLimitError> {
+        self.check_limits(&context.device().limits())?;
+        Ok(self.build_checked(context))
+    }
+
+    /// Builds the app, panicking with the error where `try_build` would fail.
+    pub fn build(&self, context: &Context) -> InstanceApp {
+        self.try_build(context).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    fn build_checked(&self, context: &Context) -> InstanceApp {
+        let mut app = InstanceApp::from_builder(context, self.clone());
+        if self.preset == ClothPreset::Flag {
+            let ([x, y, z], turbulence) = FLAG_WIND;
+            app.set_wind(cgmath::Vector3::new(x, y, z), turbulence);
+        }
+        match self.scene {
+            Scene::SingleCloth => {}
+            Scene::StiffnessStrip => app.material_strip(context, MaterialParam::StructuralStiffness, &STIFFNESS_STRIP_VALUES),
+            Scene::GravitySpace => app.gravity_space_demo(context),
+            Scene::Flags => app.flags_demo(context),
+        }
+        if self.soft_sphere {
+            app.set_soft_sphere(context, true);
+        }
+        app
+    }
+
+    pub(super) fn check_limits(&self, limits: &wgpu::Limits) -> Result<(), GridLimitError> {
+        let FabricConfig { rows, cols, .. } = self.fabric;
+        if !GRID_SIZE_RANGE.contains(&rows) || !GRID_SIZE_RANGE.contains(&cols) {
+            return Err(GridLimitError::Size { rows, cols });
+        }
+        let vertex_count = rows as u64 * cols as u64;
+        let error = |limit_name, unit, required, limit, max_grid_size: u64| GridLimitError::Device {
+            rows,
+            cols,
+            limit_name,
+            unit,
+            required,
+            limit,
+            max_grid_size: max_grid_size.min(u32::MAX as u64) as u32,
+        };
+
+        let vertex_size = self.precision.vertex_size();
+        let (limit_name, buffer_limit) = if limits.max_buffer_size < limits.max_storage_buffer_binding_size as u64 {
+            ("max_buffer_size", limits.max_buffer_size)
+        } else {
+            ("max_storage_buffer_binding_size", limits.max_storage_buffer_binding_size as u64)
+        };
+        if vertex_count * vertex_size > buffer_limit {
+            let max_grid_size = ((buffer_limit / vertex_size) as f64).sqrt() as u64;
+            return Err(error(limit_name, "bytes", vertex_count * vertex_size, buffer_limit, max_grid_size));
+        }
+
+        // The per-vertex passes dispatch `workgroup_count` groups, each
+        // dimension within the limit
+        let group_limit = limits.max_compute_workgroups_per_dimension as u64;
+        let [width, height] = self.workgroup_layout.size().map(u64::from);
+        let max_grid_size = match height {
+            1 => ((group_limit * width) as f64).sqrt() as u64,
+            _ => group_limit * width.min(height),
+        };
+        let counts = self.workgroup_layout.workgroup_count(rows, cols);
+        for (count, unit) in counts.into_iter().zip(["workgroups along x", "workgroups along y"]) {
+            if count as u64 > group_limit {
+                return Err(error("max_compute_workgroups_per_dimension", unit, count as u64, group_limit, max_grid_size));
+            }
+        }
+        Ok(())
+    }
+
+    // The single cloth dropped on the sphere, 100x100 vertices over 6 units by default
+    pub(super) fn cloth_desc(&self) -> ClothDesc {
+        let FabricConfig { rows, cols, side_length, initial_height } = self.fabric;
+        let spacing = side_length / (cols - 1) as f32;
+        let (sim_params1, mut sim_params2) = default_sim_params(rows, cols, spacing);
+        sim_params2.stiffness[..3].copy_from_slice(&self.stiffness);
+        sim_params2.set_structural_stiffness(self.stiffness[0]);
+        sim_params2.gravity[1] = -self.gravity;
+
+        let transform = self.preset.transform(initial_height);
+        let mut vertices = fabric_grid(rows, cols, side_length, transform);
+        for index in self.preset.pinned(rows, cols) {
+            vertices[index as usize].fixed = 1.0;
+        }
+        if let Some(seed) = self.seed {
+            jitter_positions(&mut vertices, SEED_JITTER * spacing, seed);
+        }
+        ClothDesc {
+            label: String::new(),
+            vertices,
+            grid_rows: rows,
+            grid_cols: cols,
+            sim_params1,
+            sim_params2,
+            transform,
+            anchor: transform.translation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fabric(rows: u32, cols: u32) -> ClothBuilder {
+        ClothBuilder::new().fabric(FabricConfig { rows, cols, ..FabricConfig::default() })
+    }
+
+    #[test]
+    fn grid_sizes_outside_the_range_fail() {
+        let limits = wgpu::Limits::default();
+        assert_eq!(fabric(1, 10).check_limits(&limits), Err(GridLimitError::Size { rows: 1, cols: 10 }));
+        assert_eq!(fabric(10, 513).check_limits(&limits), Err(GridLimitError::Size { rows: 10, cols: 513 }));
+        assert_eq!(ClothBuilder::new().grid_size(0).check_limits(&limits), Err(GridLimitError::Size { rows: 0, cols: 0 }));
+        assert_eq!(fabric(2, 2).check_limits(&limits), Ok(()));
+        assert_eq!(fabric(512, 3).check_limits(&limits), Ok(()));
+    }
+
+    #[test]
+    fn grid_larger_than_the_storage_binding_fails() {
+        let limits = wgpu::Limits {
+            max_storage_buffer_binding_size: 112 * 50 * 50,
+            ..wgpu::Limits::default()
+        };
+        assert_eq!(ClothBuilder::new().grid_size(50).check_limits(&limits), Ok(()));
+        match ClothBuilder::new().grid_size(51).check_limits(&limits) {
+            Err(GridLimitError::Device { limit_name, required, max_grid_size, .. }) => {
+                assert_eq!(limit_name, "max_storage_buffer_binding_size");
+                assert_eq!(required, 112 * 51 * 51);
+                assert_eq!(max_grid_size, 50);
+            }
+            other => panic!("expected a device limit error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn each_workgroup_dimension_is_checked() {
+        let limits = wgpu::Limits {
+            max_compute_workgroups_per_dimension: 4,
+            ..wgpu::Limits::default()
+        };
+        // 64 columns fit 4 tiles of 16, 65 rows need a fifth along y
+        let builder = fabric(65, 64).workgroup_layout(WorkgroupLayout::Tiled16);
+        match builder.check_limits(&limits) {
+            Err(GridLimitError::Device { unit, required, .. }) => {
+                assert_eq!(unit, "workgroups along y");
+                assert_eq!(required, 5);
+            }
+            other => panic!("expected a device limit error, got {:?}", other),
+        }
+        assert_eq!(fabric(64, 64).workgroup_layout(WorkgroupLayout::Tiled16).check_limits(&limits), Ok(()));
+    }
+}
@@ -0,0 +1,747 @@
+use super::{
+    ClothTransform, Precision, SimStats, WorkgroupLayout, DEFAULT_SPHERE_RESTITUTION, DEFAULT_TIME_STEP, MAX_STEP_DISPLACEMENT, MAX_SUBSTEPS, SIM_STATS_SIZE,
+};
+use crate::gpu_context::Context;
+use crate::param_bounds::ParamBounds;
+use crate::readback::AsyncReadback;
+use crate::sim_config::SimConfig;
+use crate::vertex::{Vertex, INTACT_SPRINGS};
+use wgpu_bootstrap::{
+    cgmath,
+    wgpu::{self, util::DeviceExt},
+};
+use cgmath::{InnerSpace, Rotation};
+use rand::{Rng, SeedableRng};
+
+// Simulation parameters
+#[repr(C, align(16))]  // Added align(16) to force 16-byte alignment
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct SimParams1 {
+    pub(super) grid_k_radius: [f32; 4],  // grid_rows, grid_cols, k_spring, unused  16 bytes
+    pub(super) colliders: [f32; 4],      // collider_count, self_collision_radius, sticky_collision, first_collider  16 bytes
+    pub(super) collision: [f32; 4],      // inverted (containment), contact_damping, sphere_friction, sphere_restitution  16 bytes
+    pub(super) orientation: [f32; 4],    // rotation of the cloth instance as a quaternion (x, y, z, w)  16 bytes
+    pub(super) options: [f32; 4],        // local_gravity, gravity_disabled, springs_disabled, color_mode  16 bytes
+    pub(super) counts: [u32; 4],         // vertex_count, unused x3  16 bytes
+}
+
+#[repr(C, align(16))]  // Added align(16) to force 16-byte alignment
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct SimParams2 {
+    pub(super) stiffness: [f32; 4],    // structural (weft), shear, bending, max_spring_force  16 bytes
+    pub(super) rest_length: [f32; 4],  // 16 bytes, aligned to 16
+    pub(super) gravity: [f32; 4],      // 16 bytes, aligned to 16
+    pub(super) time: [f32; 4],         // time_step, heatmap_min, heatmap_max, integration_method  16 bytes
+    pub(super) flow: [f32; 4],         // flow tile_size, flow_strength, flow_offset u and v  16 bytes
+    pub(super) ground: [f32; 4],       // plane_y, restitution, enabled, unused  16 bytes
+    pub(super) wind: [f32; 4],         // wind x, y, z and turbulence  16 bytes
+    pub(super) clock: [f32; 4],        // elapsed simulated time, unused x3  16 bytes
+    pub(super) damping: [f32; 4],      // settle_damping, air_drag, stabilize boost, sleep_threshold  16 bytes
+    pub(super) warp: [f32; 4],         // warp stiffness, unused x3  16 bytes
+    pub(super) gust: [f32; 4],         // gust acceleration x, y, z for the current frame, unused  16 bytes
+}
+
+impl SimParams2 {
+    // Same stiffness for the structural springs in both directions
+    pub(super) fn set_structural_stiffness(&mut self, stiffness: f32) {
+        self.stiffness[0] = stiffness;
+        self.warp[0] = stiffness;
+    }
+}
+
+// Shading parameters of shader.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct FabricMaterial {
+    pub(super) grid: [f32; 4],    // grid_rows, grid_cols, color_mode, textured
+    pub(super) detail: [f32; 4],  // detail_tiling, detail_strength, enabled, lighting
+    pub(super) motion: [f32; 4],  // blur_time, blur_opacity, blur_samples, unused
+    pub(super) tint: [f32; 4],    // flat fill color replacing the vertex color when a > 0
+    pub(super) edge: [f32; 4],    // color of the edge overlay
+    pub(super) springs: [f32; 4], // structural and shear rest length, unused x2
+    pub(super) backface: [f32; 4], // color multiplied into back faces when a > 0
+    pub(super) rim: [f32; 4],      // rim light color and strength
+}
+
+// Bind group layouts and shared resources every cloth binds against
+pub(super) struct ClothBindings {
+    pub(super) compute_layout: wgpu::BindGroupLayout,
+    pub(super) selection_layout: wgpu::BindGroupLayout,
+    pub(super) selection_op_buffer: wgpu::Buffer,
+    pub(super) material_layout: wgpu::BindGroupLayout,
+    pub(super) detail_view: wgpu::TextureView,
+    pub(super) detail_sampler: wgpu::Sampler,
+    pub(super) fabric_texture_view: wgpu::TextureView,
+    pub(super) flow_view: wgpu::TextureView,
+    pub(super) flow_sampler: wgpu::Sampler,
+    // `MAX_COLLIDERS` colliders, also the instance buffer of the sphere
+    pub(super) collider_buffer: wgpu::Buffer,
+    // The cloth vertices alone, the first group of the soft sphere pipelines
+    pub(super) soft_layout: wgpu::BindGroupLayout,
+}
+
+impl ClothBindings {
+    // The two bind groups of a cloth, the first one steps from vertex buffer 0
+    // to 1 and the second from 1 to 0. `buffers` are the cloth's buffers for
+    // bindings 1 to 5, 10 and 11, see `Cloth::compute_buffers`.
+    pub(super) fn compute_bind_groups(&self, context: &Context, vertex_buffers: &[wgpu::Buffer; 2], buffers: [&wgpu::Buffer; 7]) -> [wgpu::BindGroup; 2] {
+        [0, 1].map(|previous| self.compute_bind_group(context, &vertex_buffers[1 - previous], &vertex_buffers[previous], buffers))
+    }
+
+    // `next` is written at binding 0, `previous` read at binding 8
+    pub(super) fn compute_bind_group(&self, context: &Context, next: &wgpu::Buffer, previous: &wgpu::Buffer, buffers: [&wgpu::Buffer; 7]) -> wgpu::BindGroup {
+        let [buffers @ .., triangles, self_collision_grid] = buffers;
+        let mut entries: Vec<wgpu::BindGroupEntry> = std::iter::once(next)
+            .chain(buffers)
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        entries.push(wgpu::BindGroupEntry {
+            binding: 6,
+            resource: wgpu::BindingResource::TextureView(&self.flow_view),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 7,
+            resource: wgpu::BindingResource::Sampler(&self.flow_sampler),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 8,
+            resource: previous.as_entire_binding(),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 9,
+            resource: self.collider_buffer.as_entire_binding(),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 10,
+            resource: triangles.as_entire_binding(),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 11,
+            resource: self_collision_grid.as_entire_binding(),
+        });
+        context.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Compute Bind Group"),
+            layout: &self.compute_layout,
+            entries: &entries,
+        })
+    }
+
+    pub(super) fn material_bind_group(&self, context: &Context, material_buffer: &wgpu::Buffer, debug_color_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+        context.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Material Bind Group"),
+            layout: &self.material_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: material_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.detail_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.detail_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: debug_color_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&self.fabric_texture_view),
+                },
+            ],
+        })
+    }
+}
+
+// Tangent-space normal map, RGBA8 with xyz mapped to rgb
+pub(super) fn create_detail_texture(context: &Context, width: u32, height: u32, rgba: &[u8]) -> wgpu::TextureView {
+    create_rgba_texture(context, width, height, rgba, wgpu::TextureFormat::Rgba8Unorm, "Detail Normal Map")
+}
+
+// Fabric color texture, RGBA8 in sRGB like any image
+pub(super) fn create_fabric_texture(context: &Context, width: u32, height: u32, rgba: &[u8]) -> wgpu::TextureView {
+    create_rgba_texture(context, width, height, rgba, wgpu::TextureFormat::Rgba8UnormSrgb, "Fabric Texture")
+}
+
+fn create_rgba_texture(context: &Context, width: u32, height: u32, rgba: &[u8], format: wgpu::TextureFormat, label: &str) -> wgpu::TextureView {
+    let texture = context.device().create_texture_with_data(
+        context.queue(),
+        &wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        rgba,
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// Wind velocities sampled by the compute shader, RGBA16F with xyz in rgb
+pub(super) fn create_flow_texture(context: &Context, width: u32, height: u32, texels: &[[f32; 3]]) -> wgpu::TextureView {
+    let data: Vec<u8> = texels
+        .iter()
+        .flat_map(|[x, y, z]| [*x, *y, *z, 0.0])
+        .flat_map(|value| half::f16::from_f32(value).to_bits().to_le_bytes())
+        .collect();
+    let texture = context.device().create_texture_with_data(
+        context.queue(),
+        &wgpu::TextureDescriptor {
+            label: Some("Flow Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        &data,
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// CPU side description of a cloth, turned into GPU resources by `Cloth::new`
+pub(super) struct ClothDesc {
+    pub(super) label: String,
+    pub(super) vertices: Vec<Vertex>,
+    pub(super) grid_rows: u32,
+    pub(super) grid_cols: u32,
+    pub(super) sim_params1: SimParams1,
+    pub(super) sim_params2: SimParams2,
+    pub(super) transform: ClothTransform,
+    // World position of the label
+    pub(super) anchor: cgmath::Vector3<f32>,
+}
+
+// One simulated fabric grid with its own buffers and parameters. All cloths
+// share the pipelines of the app and are stepped in the same passes.
+pub(super) struct Cloth {
+    pub(super) label: String,
+    pub(super) anchor: cgmath::Vector3<f32>,
+    pub(super) grid_rows: u32,
+    pub(super) grid_cols: u32,
+    // Vertices in the buffers, also `counts.x` of the parameters so the
+    // compute passes skip the threads past the last one
+    pub(super) vertex_count: u32,
+    pub(super) indices: Vec<u32>,
+    // State of the vertices before and after a step, which one is current
+    // alternates with `InstanceApp::frame_parity`
+    pub(super) vertex_buffers: [wgpu::Buffer; 2],
+    pub(super) index_buffer: wgpu::Buffer,
+    // Line list of the structural edges, drawn by the edge overlay
+    pub(super) edge_index_buffer: wgpu::Buffer,
+    pub(super) edge_index_count: u32,
+    // Vertices the cloth was created with, restored by `InstanceApp::reset`
+    pub(super) initial_vertices: Vec<Vertex>,
+    pub(super) sim_params1: SimParams1,
+    pub(super) sim_params2: SimParams2,
+    pub(super) default_sim_params1: SimParams1,
+    pub(super) default_sim_params2: SimParams2,
+    pub(super) params_dirty: bool,
+    pub(super) sim_params1_buffer: wgpu::Buffer,
+    pub(super) sim_params2_buffer: wgpu::Buffer,
+    // Indexed by the parity of the state they read, see `ClothBindings::compute_bind_groups`
+    pub(super) compute_bind_groups: [wgpu::BindGroup; 2],
+    // The current vertex buffer for the soft sphere passes, indexed by parity
+    soft_bind_groups: [wgpu::BindGroup; 2],
+    // Scratch space of the Jacobi constraint iterations and the self collision
+    projected_buffer: wgpu::Buffer,
+    // Spatial hash of the self collision, see cs_bin_self_collision
+    self_collision_buffer: wgpu::Buffer,
+    pub(super) selection_buffer: wgpu::Buffer,
+    pub(super) selection_bind_group: wgpu::BindGroup,
+    pub(super) material_buffer: wgpu::Buffer,
+    pub(super) material_bind_group: wgpu::BindGroup,
+    // Per-vertex rgba8 colors of the debug color modes, written by the compute shader
+    pub(super) debug_color_buffer: wgpu::Buffer,
+    pub(super) stats_buffer: wgpu::Buffer,
+    pub(super) stats_readback: AsyncReadback,
+    pub(super) stats: SimStats,
+    pub(super) position_readback: AsyncReadback,
+    // `InstanceApp::simulated_frames` when the copy in flight was requested
+    pub(super) position_readback_frame: u64,
+    pub(super) positions: Vec<cgmath::Vector3<f32>>,
+    // Frame `positions` were simulated in, see `position_readback_frame`
+    pub(super) positions_frame: u64,
+    // Pin flags read back along with `positions`
+    pub(super) fixed: Vec<bool>,
+}
+
+impl Cloth {
+    pub(super) fn new(context: &Context, precision: Precision, bindings: &ClothBindings, mut desc: ClothDesc) -> Self {
+        let indices = grid_indices(desc.grid_rows, desc.grid_cols);
+        let vertex_count = desc.grid_rows * desc.grid_cols;
+        desc.sim_params1.orientation = desc.transform.orientation();
+        desc.sim_params1.counts[0] = vertex_count;
+
+        let sim_params1_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sim Param 1 Buffer"),
+            contents: bytemuck::cast_slice(&[desc.sim_params1]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let sim_params2_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sim Param 2 Buffer"),
+            contents: bytemuck::cast_slice(&[desc.sim_params2]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        // Both start from the same state, so either can be the current one
+        let vertex_bytes = precision.vertex_bytes(&desc.vertices);
+        let vertex_buffers = ["Fabric Vertex Buffer A", "Fabric Vertex Buffer B"].map(|label| {
+            context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: &vertex_bytes,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            })
+        });
+
+        let index_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fabric Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+        });
+        let edge_indices = grid_edge_indices(desc.grid_rows, desc.grid_cols);
+        let edge_index_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fabric Edge Index Buffer"),
+            contents: bytemuck::cast_slice(&edge_indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // Per-frame statistics written by cs_reduce_stats
+        let stats_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sim Stats Buffer"),
+            size: SIM_STATS_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let projected_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Projected Position Buffer"),
+            size: (desc.vertices.len() * std::mem::size_of::<[f32; 4]>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        // A bucket head and a next link per vertex
+        let self_collision_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Self Collision Buffer"),
+            size: (2 * desc.vertices.len() * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let debug_color_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Color Buffer"),
+            size: (desc.vertices.len() * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let compute_bind_groups = bindings.compute_bind_groups(
+            context,
+            &vertex_buffers,
+            [
+                &sim_params1_buffer,
+                &sim_params2_buffer,
+                &stats_buffer,
+                &projected_buffer,
+                &debug_color_buffer,
+                &index_buffer,
+                &self_collision_buffer,
+            ],
+        );
+
+        let soft_bind_groups = vertex_buffers.each_ref().map(|vertex_buffer| {
+            context.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Soft Sphere Cloth Bind Group"),
+                layout: &bindings.soft_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: vertex_buffer.as_entire_binding(),
+                }],
+            })
+        });
+
+        // Per-vertex selection weights, the operation itself is shared
+        let selection_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Selection Buffer"),
+            size: (desc.vertices.len() * std::mem::size_of::<f32>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let selection_bind_group = context.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Selection Bind Group"),
+            layout: &bindings.selection_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: selection_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: bindings.selection_op_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let material_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Fabric Material Buffer"),
+            size: std::mem::size_of::<FabricMaterial>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let material_bind_group = bindings.material_bind_group(context, &material_buffer, &debug_color_buffer);
+
+        Cloth {
+            label: desc.label,
+            anchor: desc.anchor,
+            grid_rows: desc.grid_rows,
+            grid_cols: desc.grid_cols,
+            vertex_count,
+            indices,
+            stats_readback: AsyncReadback::new(context, SIM_STATS_SIZE, "Sim Stats Readback Buffer"),
+            position_readback: AsyncReadback::new(context, vertex_buffers[0].size(), "Fabric Position Readback Buffer"),
+            vertex_buffers,
+            index_buffer,
+            edge_index_buffer,
+            edge_index_count: edge_indices.len() as u32,
+            initial_vertices: desc.vertices,
+            sim_params1: desc.sim_params1,
+            sim_params2: desc.sim_params2,
+            default_sim_params1: desc.sim_params1,
+            default_sim_params2: desc.sim_params2,
+            params_dirty: false,
+            sim_params1_buffer,
+            sim_params2_buffer,
+            compute_bind_groups,
+            soft_bind_groups,
+            projected_buffer,
+            self_collision_buffer,
+            selection_buffer,
+            selection_bind_group,
+            material_buffer,
+            material_bind_group,
+            debug_color_buffer,
+            stats_buffer,
+            stats: SimStats::default(),
+            position_readback_frame: 0,
+            positions: Vec::new(),
+            positions_frame: 0,
+            fixed: Vec::new(),
+        }
+    }
+
+    pub(super) fn vertex_count(&self) -> usize {
+        self.vertex_count as usize
+    }
+
+    // Index distance to the right, down, down-right and down-left neighbor, the
+    // directions of the structural and shear springs in the order vs_spring expects
+    pub(super) fn spring_offsets(&self) -> [u32; 4] {
+        [1, self.grid_cols, self.grid_cols + 1, self.grid_cols - 1]
+    }
+
+    // Both buffers get the initial state, so Verlet starts at rest too. The
+    // triangles are restored with it, in case the cloth tore.
+    pub(super) fn restore_initial_vertices(&self, context: &Context, precision: Precision) {
+        let bytes = precision.vertex_bytes(&self.initial_vertices);
+        for buffer in &self.vertex_buffers {
+            context.queue().write_buffer(buffer, 0, &bytes);
+        }
+        context.queue().write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&self.indices));
+    }
+
+    // 256 threads along the vertex index, for cs_soft_push_cloth which doesn't
+    // see the grid size
+    pub(super) fn thread_group_count(&self) -> u32 {
+        self.vertex_count.div_ceil(256)
+    }
+
+    // One thread per vertex in the workgroups of `layout`
+    pub(super) fn dispatch_vertices(&self, compute_pass: &mut wgpu::ComputePass<'_>, layout: WorkgroupLayout) {
+        let [x, y] = layout.workgroup_count(self.grid_rows, self.grid_cols);
+        compute_pass.dispatch_workgroups(x, y, 1);
+    }
+
+    // One thread per grid cell, see cs_tear_triangles
+    pub(super) fn cell_group_count(&self) -> u32 {
+        ((self.grid_rows - 1) * (self.grid_cols - 1)).div_ceil(256)
+    }
+
+    pub(super) fn tearing(&self) -> bool {
+        self.sim_params2.rest_length[3] > 0.0
+    }
+
+    // Vertex buffer holding the current state
+    pub(super) fn vertex_buffer(&self, parity: bool) -> &wgpu::Buffer {
+        &self.vertex_buffers[parity as usize]
+    }
+
+    // Bind group of the integration pass, reading the current state and writing
+    // the other buffer, which becomes current after the pass
+    pub(super) fn step_bind_group(&self, parity: bool) -> &wgpu::BindGroup {
+        &self.compute_bind_groups[parity as usize]
+    }
+
+    // Bind group of the passes updating the current state in place
+    pub(super) fn compute_bind_group(&self, parity: bool) -> &wgpu::BindGroup {
+        &self.compute_bind_groups[!parity as usize]
+    }
+
+    pub(super) fn soft_bind_group(&self, parity: bool) -> &wgpu::BindGroup {
+        &self.soft_bind_groups[parity as usize]
+    }
+
+    // Copies the current vertex buffer back to the CPU. This waits for the GPU
+    // to finish all submitted work, so keep it out of the per-frame path.
+    pub(super) fn read_vertices(&self, context: &Context, precision: Precision, parity: bool) -> Vec<Vertex> {
+        let vertex_buffer = self.vertex_buffer(parity);
+        let size = vertex_buffer.size();
+        let staging_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Fabric Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(vertex_buffer, 0, &staging_buffer, 0, size);
+        context.queue().submit(Some(encoder.finish()));
+
+        let slice = staging_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        context.device().poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("readback callback dropped")
+            .expect("failed to map the fabric readback buffer");
+
+        let vertices = precision.vertices_from_bytes(&slice.get_mapped_range());
+        staging_buffer.unmap();
+        vertices
+    }
+
+    pub(super) fn compute_buffers(&self) -> [&wgpu::Buffer; 7] {
+        [
+            &self.sim_params1_buffer,
+            &self.sim_params2_buffer,
+            &self.stats_buffer,
+            &self.projected_buffer,
+            &self.debug_color_buffer,
+            &self.index_buffer,
+            &self.self_collision_buffer,
+        ]
+    }
+
+    pub(super) fn upload_params(&mut self, context: &Context) {
+        if !self.params_dirty {
+            return;
+        }
+        context.queue().write_buffer(&self.sim_params1_buffer, 0, bytemuck::cast_slice(&[self.sim_params1]));
+        context.queue().write_buffer(&self.sim_params2_buffer, 0, bytemuck::cast_slice(&[self.sim_params2]));
+        self.params_dirty = false;
+    }
+
+    // Substeps keeping the fastest vertex under MAX_STEP_DISPLACEMENT rest
+    // lengths per step. Based on the latest max speed read back from the GPU.
+    pub(super) fn substeps_for_spike(&self, time_step: f32) -> u32 {
+        let displacement = self.stats.max_speed * time_step;
+        let limit = MAX_STEP_DISPLACEMENT * self.sim_params2.rest_length[0];
+        if !displacement.is_finite() {
+            return MAX_SUBSTEPS;
+        }
+        if displacement <= limit {
+            return 1;
+        }
+        ((displacement / limit).ceil() as u32).clamp(1, MAX_SUBSTEPS)
+    }
+}
+
+// Grid of `grid_rows` x `grid_cols` vertices, flat in the local xz plane of
+// `transform`. The columns span `side_length` along x, the rows are spaced the
+// same along z, so square grids are square.
+pub(super) fn fabric_grid(grid_rows: u32, grid_cols: u32, side_length: f32, transform: ClothTransform) -> Vec<Vertex> {
+    let spacing = side_length / (grid_cols - 1) as f32;
+    let depth = spacing * (grid_rows - 1) as f32;
+    (0..grid_rows)
+        .flat_map(|row| {
+            (0..grid_cols).map(move |col| {
+                let x = col as f32 * spacing - side_length / 2.0;
+                let z = row as f32 * spacing - depth / 2.0;
+                let position = transform.transform_point(cgmath::Vector3::new(x, 0.0, z));
+                let normal = transform.rotation.rotate_vector(cgmath::Vector3::unit_y());
+
+                Vertex {
+                    position: [position.x, position.y, position.z, 1.0],
+                    color: [0.26, 0.65, 0.96, 1.0], // Green for the fabric
+                    mass: 0.1,
+                    force: [0.0; 3],
+                    velocity: [0.0, 0.0, 0.0, 1.0],
+                    fixed: 0.0,
+                    springs: INTACT_SPRINGS,
+                    grid: [row, col],
+                    normal: [normal.x, normal.y, normal.z, 0.0],
+                    pin_target: [0.0; 4],
+                }
+            })
+        })
+        .collect()
+}
+
+// Mass within `ParamBounds::MASS`, or 0 (left unchanged) when it isn't a number
+pub(super) fn guarded_mass(mass: f32) -> f32 {
+    if mass.is_finite() { ParamBounds::MASS.clamp(mass) } else { 0.0 }
+}
+
+// Moves every vertex by a random offset of up to `amplitude` along each axis,
+// the same offsets for the same seed
+pub(super) fn jitter_positions(vertices: &mut [Vertex], amplitude: f32, seed: u64) {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    for vertex in vertices {
+        for coordinate in &mut vertex.position[..3] {
+            *coordinate += rng.gen_range(-amplitude..=amplitude);
+        }
+    }
+}
+
+// Two triangles per grid cell
+fn grid_indices(grid_rows: u32, grid_cols: u32) -> Vec<u32> {
+    let mut indices: Vec<u32> = Vec::new();
+    for row in 0..grid_rows - 1 {
+        for col in 0..grid_cols - 1 {
+            let top_left = row * grid_cols + col;
+            let top_right = top_left + 1;
+            let bottom_left = top_left + grid_cols;
+            let bottom_right = bottom_left + 1;
+
+            // Add two triangles for the cell
+            indices.extend_from_slice(&[
+                top_left, bottom_left, bottom_right, // Triangle 1
+                top_left, bottom_right, top_right,  // Triangle 2
+            ]);
+        }
+    }
+    indices
+}
+
+// Pairs of vertices along the rows and columns of the grid
+fn grid_edge_indices(grid_rows: u32, grid_cols: u32) -> Vec<u32> {
+    let mut indices: Vec<u32> = Vec::new();
+    for row in 0..grid_rows {
+        for col in 0..grid_cols {
+            let index = row * grid_cols + col;
+            if col + 1 < grid_cols {
+                indices.extend_from_slice(&[index, index + 1]);
+            }
+            if row + 1 < grid_rows {
+                indices.extend_from_slice(&[index, index + grid_cols]);
+            }
+        }
+    }
+    indices
+}
+
+// Structural, shear and bending rest lengths of a grid with `spacing` between
+// neighbors: one cell, its diagonal and two cells, so the cloth starts at rest
+fn grid_rest_lengths(spacing: f32) -> [f32; 3] {
+    [spacing, spacing * std::f32::consts::SQRT_2, 2.0 * spacing]
+}
+
+pub(super) fn default_sim_params(grid_rows: u32, grid_cols: u32, spacing: f32) -> (SimParams1, SimParams2) {
+    let k_spring = 0.12;
+    let [structural, shear, bending] = grid_rest_lengths(spacing);
+    let sim_params1 = SimParams1 {
+        grid_k_radius: [grid_rows as f32, grid_cols as f32, k_spring, 0.0],
+        colliders: [1.0, 0.0, 0.0, 0.0],
+        collision: [0.0, 0.0, 0.0, DEFAULT_SPHERE_RESTITUTION],
+        orientation: [0.0, 0.0, 0.0, 1.0],
+        options: [0.0; 4],
+        counts: [grid_rows * grid_cols, 0, 0, 0],
+    };
+    let sim_params2 = SimParams2 {
+        stiffness: [25.0, 15.0, 5.0, 100.0],
+        rest_length: [structural, shear, bending, 0.0],
+        gravity: [0.0, -6.8, 0.0, 0.0],
+        time: [DEFAULT_TIME_STEP, 0.0, 0.0, 0.0],
+        flow: [4.0, 0.0, 0.0, 0.0],
+        ground: [0.0; 4],
+        wind: [0.0; 4],
+        clock: [0.0; 4],
+        damping: [0.0; 4],
+        warp: [25.0, 0.0, 0.0, 0.0],
+        gust: [0.0; 4],
+    };
+    (sim_params1, sim_params2)
+}
+
+// Scales `gravity` to a magnitude within `ParamBounds::GRAVITY`, a zero vector stays zero
+pub(super) fn clamp_gravity(gravity: cgmath::Vector3<f32>) -> cgmath::Vector3<f32> {
+    let magnitude = gravity.magnitude();
+    if magnitude > 0.0 {
+        gravity * (ParamBounds::GRAVITY.clamp(magnitude) / magnitude)
+    } else {
+        gravity
+    }
+}
+
+// Packs the parameters of `config` into the uniforms of a cloth, over the
+// values already there for its `None` fields. Clamped like the setters.
+pub(super) fn write_config_params(config: &SimConfig, sim_params1: &mut SimParams1, sim_params2: &mut SimParams2) {
+    if let Some(stiffness) = config.stiffness {
+        let stiffness = stiffness.map(|k| ParamBounds::STIFFNESS.clamp(k));
+        sim_params2.stiffness[..3].copy_from_slice(&stiffness);
+        sim_params2.set_structural_stiffness(stiffness[0]);
+    }
+    if let Some(warp) = config.warp_stiffness {
+        sim_params2.warp[0] = ParamBounds::STIFFNESS.clamp(warp);
+    }
+    if let Some(rest_lengths) = config.rest_lengths {
+        sim_params2.rest_length[..3].copy_from_slice(&rest_lengths.map(|length| ParamBounds::REST_LENGTH.clamp(length)));
+    }
+    if let Some(gravity) = config.gravity {
+        let gravity = clamp_gravity(gravity.into());
+        sim_params2.gravity[..3].copy_from_slice(&[gravity.x, gravity.y, gravity.z]);
+    }
+    if let Some(damping) = config.damping {
+        sim_params1.grid_k_radius[2] = ParamBounds::DAMPING.clamp(damping);
+    }
+    sim_params2.damping[0] = ParamBounds::SETTLE_DAMPING.clamp(config.settle_damping);
+    sim_params2.damping[1] = ParamBounds::AIR_DRAG.clamp(config.air_drag);
+    sim_params2.damping[3] = ParamBounds::SLEEP_THRESHOLD.clamp(config.sleep_threshold);
+    sim_params1.colliders[2] = if config.sticky_collision { 1.0 } else { 0.0 };
+    sim_params1.collision[0] = if config.sphere_inverted { 1.0 } else { 0.0 };
+    sim_params1.collision[1] = ParamBounds::CONTACT_DAMPING.clamp(config.contact_damping);
+    sim_params1.collision[2] = ParamBounds::FRICTION.clamp(config.sphere_friction);
+    if let Some(restitution) = config.sphere_restitution {
+        sim_params1.collision[3] = ParamBounds::RESTITUTION.clamp(restitution);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::vertex::{Vertex, INTACT_SPRINGS};
+use wgpu_bootstrap::{cgmath, util::geometry::icosphere, wgpu};
+use cgmath::InnerSpace;
+
+// A collider as the GPU sees it, one element of the collider buffer. The buffer
+// holds `MAX_COLLIDERS` of them: the compute shader reads it as the uniform
+// `array<Collider, MAX_COLLIDERS>` at binding 9 and the sphere mesh is drawn once
+// per collider with it as the instance buffer. Only the first `colliders[0]` of
+// `SimParams1` are used.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct GpuCollider {
+    pub(super) center_radius: [f32; 4],  // center xyz and radius w  16 bytes
+    pub(super) half_axis: [f32; 4],      // capsule center to end, zero for a sphere, unused w  16 bytes
+}
+
+impl GpuCollider {
+    pub(super) fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GpuCollider>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                // Center and radius
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // Half axis
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// A solid the cloth collides with, see `InstanceApp::set_colliders`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Collider {
+    Sphere { center: cgmath::Vector3<f32>, radius: f32 },
+    /// The points within `radius` of the segment `a`-`b`: a cylinder with
+    /// rounded ends, e.g. a bar to hang the cloth over.
+    Capsule { a: cgmath::Vector3<f32>, b: cgmath::Vector3<f32>, radius: f32 },
+}
+
+impl Collider {
+    /// Center of the sphere, or the middle of the capsule's segment.
+    pub fn center(&self) -> cgmath::Vector3<f32> {
+        match *self {
+            Collider::Sphere { center, .. } => center,
+            Collider::Capsule { a, b, .. } => (a + b) / 2.0,
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Collider::Sphere { radius, .. } | Collider::Capsule { radius, .. } => radius,
+        }
+    }
+
+    // Moves the whole shape so its center lands on `center`
+    pub(super) fn set_center(&mut self, new_center: cgmath::Vector3<f32>) {
+        match self {
+            Collider::Sphere { center, .. } => *center = new_center,
+            Collider::Capsule { a, b, .. } => {
+                let half_axis = (*b - *a) / 2.0;
+                *a = new_center - half_axis;
+                *b = new_center + half_axis;
+            }
+        }
+    }
+
+    fn half_axis(&self) -> cgmath::Vector3<f32> {
+        match *self {
+            Collider::Sphere { .. } => cgmath::Vector3::new(0.0, 0.0, 0.0),
+            Collider::Capsule { a, b, .. } => (b - a) / 2.0,
+        }
+    }
+
+    pub(super) fn to_gpu(self) -> GpuCollider {
+        let (center, half_axis) = (self.center(), self.half_axis());
+        GpuCollider {
+            center_radius: [center.x, center.y, center.z, self.radius()],
+            half_axis: [half_axis.x, half_axis.y, half_axis.z, 0.0],
+        }
+    }
+}
+
+/// A sphere from its center and radius.
+impl From<(cgmath::Vector3<f32>, f32)> for Collider {
+    fn from((center, radius): (cgmath::Vector3<f32>, f32)) -> Self {
+        Collider::Sphere { center, radius }
+    }
+}
+
+// Sinusoidal oscillation of the first collision sphere around `base_center`,
+// see `set_sphere_motion`
+pub(super) struct SphereMotion {
+    pub(super) base_center: cgmath::Vector3<f32>,
+    // Unit direction scaled by the amplitude
+    pub(super) offset: cgmath::Vector3<f32>,
+    pub(super) frequency: f32,
+    pub(super) elapsed: f32,
+}
+
+impl SphereMotion {
+    pub(super) fn center(&self) -> cgmath::Vector3<f32> {
+        self.base_center + self.offset * (std::f32::consts::TAU * self.frequency * self.elapsed).sin()
+    }
+}
+
+pub(super) fn collider_vertex(position: cgmath::Vector3<f32>, normal: cgmath::Vector3<f32>) -> Vertex {
+    Vertex {
+        position: [position.x, position.y, position.z, 1.0],
+        color: [1.0, 0.0, 0.0, 1.0], // Red for the ball
+        mass: 1.0,
+        force: [0.0; 3],
+        velocity: [0.0, 0.0, 0.0, 1.0],
+        fixed: 1.0,
+        springs: INTACT_SPRINGS,
+        grid: [0; 2],
+        normal: [normal.x, normal.y, normal.z, 0.0],
+        pin_target: [0.0; 4],
+    }
+}
+
+// Mesh drawn at every collider, inside the unit sphere that vs_collider scales
+// and moves onto the collider
+#[derive(Clone, Debug)]
+pub(super) struct ColliderMesh {
+    pub(super) vertices: Vec<Vertex>,
+    pub(super) indices: Vec<u32>,
+}
+
+impl ColliderMesh {
+    pub(super) fn unit_sphere() -> Self {
+        let (positions, indices) = icosphere(5);
+        let vertices = positions.iter().map(|position| collider_vertex(*position, *position)).collect();
+        ColliderMesh { vertices, indices }
+    }
+
+    // `positions` fit into the unit sphere, along with the bounding sphere
+    // (center and radius) they were fit from. The center is the middle of the
+    // bounding box, which is close enough to the smallest sphere for colliding.
+    pub(super) fn fit(positions: &[cgmath::Vector3<f32>], indices: &[u32]) -> (Self, (cgmath::Vector3<f32>, f32)) {
+        assert!(
+            indices.iter().all(|&index| (index as usize) < positions.len()),
+            "collider mesh index out of range"
+        );
+        let infinity = cgmath::Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let (min, max) = positions.iter().fold((infinity, -infinity), |(min, max), p| {
+            (
+                cgmath::Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                cgmath::Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        });
+        let center = if positions.is_empty() { cgmath::Vector3::new(0.0, 0.0, 0.0) } else { (min + max) / 2.0 };
+        let radius = positions.iter().map(|p| (p - center).magnitude()).fold(0.0, f32::max).max(1e-6);
+
+        // Area weighted vertex normals, pointing out of counter-clockwise faces
+        let mut normals = vec![cgmath::Vector3::new(0.0, 0.0, 0.0); positions.len()];
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| index as usize);
+            let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+            for index in [a, b, c] {
+                normals[index] += normal;
+            }
+        }
+        let vertices = positions
+            .iter()
+            .zip(&normals)
+            .map(|(position, normal)| {
+                let normal = if normal.magnitude2() > 0.0 { normal.normalize() } else { *normal };
+                collider_vertex((position - center) / radius, normal)
+            })
+            .collect();
+        (ColliderMesh { vertices, indices: indices.to_vec() }, (center, radius))
+    }
+}
@@ -0,0 +1,630 @@
+use super::{
+    output_path, Collider, ColorMode, ConstraintSolver, FabricView, InstanceApp, IntegrationMethod, MaterialParam, RenderPreset, BACKGROUND_PRESETS,
+    DEFAULT_BACKFACE_TINT, DEFAULT_BAR, DEFAULT_COLLIDER_RADIUS, DEFAULT_FIXED_STEP, DEFAULT_GROUND_PLANE, DEFAULT_MAX_SUBSTEP_DT, FOV_RANGE,
+    MAX_CONSTRAINT_ITERATIONS, MAX_LABELED_VERTICES, SOLVER_ITERATIONS, SPHERE_BOB, STIFFNESS_STRIP_VALUES, TIME_SCALE_RANGE,
+};
+use crate::divergence::DivergenceRun;
+use crate::frame_times::FrameTimes;
+use crate::gpu_context::Context;
+use crate::gravity_compass::gravity_compass;
+use crate::heatmap;
+use crate::param_bounds::{bounded_slider, ParamBounds};
+use crate::pin_animation::{Keyframe, PinTrack};
+use crate::projection;
+use wgpu_bootstrap::{cgmath, egui};
+
+impl InstanceApp {
+    // The "Simulation" window: scene buttons, parameters, statistics and tools
+    pub(super) fn panel(&mut self, ctx: &egui::Context, context: &Context) {
+        egui::Window::new("Simulation").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Single cloth").clicked() {
+                    self.single_cloth(context);
+                }
+                if ui.button("Stiffness strip").clicked() {
+                    self.material_strip(context, MaterialParam::StructuralStiffness, &STIFFNESS_STRIP_VALUES);
+                }
+                if ui.button("Gravity space").clicked() {
+                    self.gravity_space_demo(context);
+                }
+                if ui.button("Flags").clicked() {
+                    self.flags_demo(context);
+                }
+                if ui.button("Reset").on_hover_text("Back to the starting positions (R)").clicked() {
+                    self.reset(context);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.paused, "Paused").on_hover_text("Freeze the simulation (space)");
+                if ui.add_enabled(self.paused, egui::Button::new("Step")).on_hover_text("Run a single frame (.)").clicked() {
+                    self.request_step();
+                }
+            });
+            if self.cloths.len() > 1 {
+                ui.collapsing("Cloths", |ui| {
+                    for index in 0..self.cloths.len() {
+                        let cloth = &self.cloths[index];
+                        let name = if cloth.label.is_empty() { format!("Cloth {}", index) } else { cloth.label.clone() };
+                        let mut local = cloth.sim_params1.options[0] > 0.5;
+                        if ui.checkbox(&mut local, format!("{}: local gravity", name)).changed() {
+                            self.set_local_gravity(index, local);
+                        }
+                    }
+                });
+            }
+            ui.separator();
+
+            let mut inverted = self.primary().sim_params1.collision[0] > 0.5;
+            if ui.checkbox(&mut inverted, "Contain inside sphere").changed() {
+                self.set_sphere_inverted(inverted);
+            }
+            let mut soft_sphere = self.soft_sphere.is_some();
+            if ui.checkbox(&mut soft_sphere, "Soft sphere").on_hover_text("Experimental: the first sphere becomes a ball of springs").changed() {
+                self.set_soft_sphere(context, soft_sphere);
+            }
+            let mut sticky = self.primary().sim_params1.colliders[2] > 0.5;
+            if ui.checkbox(&mut sticky, "Sticky collision").on_hover_text("Vertices touching a collider become fixed until reset").changed() {
+                self.set_sticky_collision(sticky);
+            }
+            let mut bar = matches!(self.colliders.first(), Some(Collider::Capsule { .. }));
+            if ui.checkbox(&mut bar, "Bar instead of sphere").on_hover_text("Drape the cloth over a capsule").changed() {
+                let origin = cgmath::Vector3::new(0.0, 0.0, 0.0);
+                let collider = if bar {
+                    let half_length = cgmath::Vector3::new(DEFAULT_BAR[0], 0.0, 0.0);
+                    Collider::Capsule { a: origin - half_length, b: origin + half_length, radius: DEFAULT_BAR[1] }
+                } else {
+                    Collider::Sphere { center: origin, radius: DEFAULT_COLLIDER_RADIUS }
+                };
+                self.set_colliders(vec![collider]);
+            }
+            let mut ground = self.ground_plane.is_some();
+            if ui.checkbox(&mut ground, "Ground plane").changed() {
+                if ground {
+                    let [y, restitution] = DEFAULT_GROUND_PLANE;
+                    self.set_ground_plane(y, restitution);
+                } else {
+                    self.clear_ground_plane();
+                }
+            }
+            if let Some([mut y, mut restitution]) = self.ground_plane {
+                let height_changed = ui.add(egui::Slider::new(&mut y, -5.0..=2.0).text("Height")).changed();
+                let restitution_changed = ui.add(bounded_slider(&mut restitution, ParamBounds::RESTITUTION, "Restitution")).changed();
+                if height_changed || restitution_changed {
+                    self.set_ground_plane(y, restitution);
+                }
+            }
+
+            ui.separator();
+            ui.label("Gravity");
+            let gravity = self.primary().sim_params2.gravity;
+            let mut magnitude = (gravity[0] * gravity[0] + gravity[1] * gravity[1] + gravity[2] * gravity[2]).sqrt();
+            if magnitude > 0.0 {
+                self.gravity_direction = [gravity[0] / magnitude, gravity[1] / magnitude, gravity[2] / magnitude];
+            }
+            let direction_changed = ui.add(gravity_compass(&mut self.gravity_direction)).changed();
+            let magnitude_changed = ui.add(bounded_slider(&mut magnitude, ParamBounds::GRAVITY, "Magnitude")).changed();
+            if direction_changed || magnitude_changed {
+                let [x, y, z] = self.gravity_direction;
+                self.set_gravity(cgmath::Vector3::new(x, y, z) * magnitude);
+            }
+            ui.separator();
+
+            ui.label("Springs");
+            let sim_params2 = &self.primary().sim_params2;
+            let mut stiffness = [sim_params2.warp[0], sim_params2.stiffness[0], sim_params2.stiffness[1], sim_params2.stiffness[2]];
+            let mut stiffness_changed = false;
+            for (value, text) in stiffness.iter_mut().zip(["Warp", "Weft", "Shear", "Bending"]) {
+                stiffness_changed |= ui.add(bounded_slider(value, ParamBounds::STIFFNESS, text)).changed();
+            }
+            if stiffness_changed {
+                let [warp, weft, shear, bending] = stiffness;
+                self.set_stiffness([weft, shear, bending]);
+                self.set_structural_stiffness(warp, weft);
+            }
+            let mut damping = self.primary().sim_params1.grid_k_radius[2];
+            if ui.add(bounded_slider(&mut damping, ParamBounds::DAMPING, "Damping")).changed() {
+                self.set_damping(damping);
+            }
+            let mut sphere_friction = self.primary().sim_params1.collision[2];
+            if ui.add(bounded_slider(&mut sphere_friction, ParamBounds::FRICTION, "Sphere friction")).changed() {
+                self.set_sphere_friction(sphere_friction);
+            }
+            let mut sphere_restitution = self.primary().sim_params1.collision[3];
+            if ui.add(bounded_slider(&mut sphere_restitution, ParamBounds::RESTITUTION, "Sphere restitution")).changed() {
+                self.set_sphere_restitution(sphere_restitution);
+            }
+            let mut bobbing = self.sphere_motion.is_some();
+            if ui.checkbox(&mut bobbing, "Bob sphere").changed() {
+                let amplitude = if bobbing { SPHERE_BOB.0 } else { 0.0 };
+                self.set_sphere_motion(amplitude, SPHERE_BOB.1, cgmath::Vector3::unit_y());
+            }
+            let mut contact_damping = self.primary().sim_params1.collision[1];
+            if ui.add(bounded_slider(&mut contact_damping, ParamBounds::CONTACT_DAMPING, "Contact damping")).changed() {
+                self.set_contact_damping(contact_damping);
+            }
+            let mut settle_damping = self.primary().sim_params2.damping[0];
+            if ui.add(bounded_slider(&mut settle_damping, ParamBounds::SETTLE_DAMPING, "Settle damping")).changed() {
+                self.set_settle_damping(settle_damping);
+            }
+            let mut sleep_threshold = self.primary().sim_params2.damping[3];
+            if ui.add(bounded_slider(&mut sleep_threshold, ParamBounds::SLEEP_THRESHOLD, "Sleep threshold"))
+                .on_hover_text("Speed below which a vertex stops, 0 never stops")
+                .changed()
+            {
+                self.set_sleep_threshold(sleep_threshold);
+            }
+            let mut max_spring_force = self.primary().sim_params2.stiffness[3];
+            if ui.add(bounded_slider(&mut max_spring_force, ParamBounds::MAX_SPRING_FORCE, "Max spring force")).changed() {
+                self.set_max_spring_force(max_spring_force);
+            }
+            let mut tear_length = self.primary().sim_params2.rest_length[3];
+            if ui.add(bounded_slider(&mut tear_length, ParamBounds::TEAR_LENGTH, "Tear length"))
+                .on_hover_text("Stretch factor past which springs tear, 0 never tears")
+                .changed()
+            {
+                self.set_tear_length(tear_length);
+            }
+            if ui.button("Reset parameters").clicked() {
+                self.reset_params();
+            }
+
+            ui.collapsing("Wind", |ui| {
+                let [x, y, z, mut turbulence] = self.wind;
+                let mut strength = (x * x + y * y + z * z).sqrt();
+                if strength > 0.0 {
+                    self.wind_direction = [x / strength, y / strength, z / strength];
+                }
+                let direction_changed = ui.add(gravity_compass(&mut self.wind_direction)).changed();
+                let strength_changed = ui.add(bounded_slider(&mut strength, ParamBounds::WIND, "Strength")).changed();
+                let turbulence_changed = ui.add(bounded_slider(&mut turbulence, ParamBounds::TURBULENCE, "Turbulence")).changed();
+                if direction_changed || strength_changed || turbulence_changed {
+                    let [x, y, z] = self.wind_direction;
+                    self.set_wind(cgmath::Vector3::new(x, y, z) * strength, turbulence);
+                }
+                let mut air_drag = self.primary().sim_params2.damping[1];
+                if ui.add(bounded_slider(&mut air_drag, ParamBounds::AIR_DRAG, "Air drag")).changed() {
+                    self.set_air_drag(air_drag);
+                }
+            });
+
+            ui.collapsing("Wind flow", |ui| {
+                let mut strength = self.primary().sim_params2.flow[1];
+                if ui.add(bounded_slider(&mut strength, ParamBounds::FLOW_STRENGTH, "Strength")).changed() {
+                    self.set_flow_strength(strength);
+                }
+                let mut tile_size = self.primary().sim_params2.flow[0];
+                if ui.add(egui::Slider::new(&mut tile_size, 0.5..=20.0).text("Tile size")).changed() {
+                    self.set_flow_tile_size(tile_size);
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Scroll");
+                    ui.add(egui::DragValue::new(&mut self.flow_scroll[0]).speed(0.01));
+                    ui.add(egui::DragValue::new(&mut self.flow_scroll[1]).speed(0.01));
+                });
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.flow_path).on_hover_text("Path of a .pfm flow texture");
+                    if ui.button("Load").clicked() {
+                        let path = self.flow_path.clone();
+                        self.flow_status = match self.set_flow_texture(context, &path) {
+                            Ok(()) => format!("Loaded {}", path),
+                            Err(error) => format!("Failed to load {}: {}", path, error),
+                        };
+                    }
+                });
+                if !self.flow_status.is_empty() {
+                    ui.weak(&self.flow_status);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let stats = self.last_stats();
+                ui.label(format!("Max speed: {:.2}", stats.max_speed));
+                ui.label(format!("Max stretch: {:.3}", stats.max_stretch));
+                ui.label(format!("Kinetic energy: {:.2}", stats.kinetic_energy));
+                if self.is_settled() {
+                    ui.colored_label(egui::Color32::from_rgb(0, 150, 60), "Settled");
+                }
+                if self.substeps > 1 {
+                    ui.colored_label(egui::Color32::from_rgb(200, 120, 0), format!("{} substeps", self.substeps));
+                }
+            });
+            ui.checkbox(&mut self.log_substeps, "Log substep spikes");
+            ui.horizontal(|ui| {
+                let mut auto_stabilize = self.auto_stabilize;
+                if ui.checkbox(&mut auto_stabilize, "Auto-stabilize").on_hover_text("Boost the damping for a second when the kinetic energy spikes").changed() {
+                    self.set_auto_stabilize(auto_stabilize);
+                }
+                let mut growth_limit = self.energy_growth_limit;
+                if ui.add(egui::Slider::new(&mut growth_limit, 1.5..=20.0).logarithmic(true).text("Energy growth")).changed() {
+                    self.set_energy_growth_limit(growth_limit);
+                }
+                if self.stabilize_frames_left > 0 {
+                    ui.colored_label(egui::Color32::from_rgb(200, 120, 0), "damping");
+                }
+            });
+            let mut iterations = self.iterations;
+            if ui.add(egui::Slider::new(&mut iterations, SOLVER_ITERATIONS).text("Solver iterations")).on_hover_text("Substeps per frame, stiffer cloth for more compute").changed() {
+                self.set_iterations(iterations);
+            }
+            let mut real_time = self.max_substep_dt > 0.0;
+            if ui.checkbox(&mut real_time, "Real time").on_hover_text("Simulate the frame time in substeps instead of a fixed step per frame").changed() {
+                self.set_max_substep_dt(if real_time { DEFAULT_MAX_SUBSTEP_DT } else { 0.0 });
+            }
+            let mut time_scale = self.time_scale;
+            if ui.add(egui::Slider::new(&mut time_scale, TIME_SCALE_RANGE).logarithmic(true).text("Time scale")).on_hover_text("Slow motion or fast forward ([ and ])").changed() {
+                self.set_time_scale(time_scale);
+            }
+            let mut fixed_rate = self.fixed_step > 0.0;
+            if ui.checkbox(&mut fixed_rate, "Fixed rate (120 Hz)").on_hover_text("Simulate the real time in fixed steps, independent of the frame rate").changed() {
+                self.set_fixed_step(if fixed_rate { DEFAULT_FIXED_STEP } else { 0.0 });
+            }
+            if real_time {
+                let mut max_substep_dt = self.max_substep_dt;
+                if ui.add(bounded_slider(&mut max_substep_dt, ParamBounds::TIME_STEP, "Max substep dt")).changed() {
+                    self.set_max_substep_dt(max_substep_dt);
+                }
+            }
+
+            let mut color_mode = self.color_mode;
+            egui::ComboBox::from_label("Color mode")
+                .selected_text(color_mode.name())
+                .show_ui(ui, |ui| {
+                    for mode in ColorMode::ALL {
+                        ui.selectable_value(&mut color_mode, mode, mode.name());
+                    }
+                });
+            if color_mode != self.color_mode {
+                self.set_color_mode(color_mode);
+            }
+            if let Some((quantity, _)) = self.color_mode.heatmap() {
+                let [mut min, mut max] = self.heatmap_range;
+                ui.horizontal(|ui| {
+                    ui.label("Range");
+                    ui.add(egui::DragValue::new(&mut min).speed(0.01));
+                    ui.add(egui::DragValue::new(&mut max).speed(0.01));
+                });
+                self.set_heatmap_range(min, max);
+                ui.add(heatmap::legend(self.heatmap_range[0], self.heatmap_range[1], quantity));
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Stages");
+                ui.checkbox(&mut self.stages.gravity, "Gravity");
+                ui.checkbox(&mut self.stages.springs, "Springs");
+                ui.checkbox(&mut self.stages.constraints, "Constraints");
+                ui.checkbox(&mut self.stages.collision, "Collision");
+                ui.checkbox(&mut self.stages.pins, "Pins");
+            });
+            ui.horizontal(|ui| {
+                let mut enabled = self.self_collision;
+                let mut radius = self.self_collision_radius;
+                let toggled = ui
+                    .checkbox(&mut enabled, "Self collision")
+                    .on_hover_text("Push apart fabric vertices closer than the radius")
+                    .changed();
+                let resized = enabled && ui.add(bounded_slider(&mut radius, ParamBounds::SELF_COLLISION_RADIUS, "Radius")).changed();
+                if toggled || resized {
+                    self.set_self_collision(radius, enabled);
+                }
+            });
+            if self.relax_frames_left > 0 {
+                ui.weak(format!("Relaxing without gravity, {} frames left", self.relax_frames_left));
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Integration");
+                for method in IntegrationMethod::ALL {
+                    ui.selectable_value(&mut self.integration_method, method, method.name());
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Constraints");
+                ui.selectable_value(&mut self.constraint_solver, ConstraintSolver::Jacobi, "Jacobi");
+                ui.selectable_value(&mut self.constraint_solver, ConstraintSolver::RedBlack, "Red-black");
+            });
+            ui.add(egui::Slider::new(&mut self.constraint_iterations, 0..=MAX_CONSTRAINT_ITERATIONS).text("Iterations"));
+
+            ui.collapsing("Divergence test", |ui| {
+                if ui.button("Nudge center vertex by 1e-4").clicked() {
+                    let center = self.primary().grid_rows / 2 * self.primary().grid_cols + self.primary().grid_cols / 2;
+                    self.start_divergence_run(context, center, 1e-4, 600);
+                }
+                if let Some(run) = &self.divergence {
+                    ui.label(format!("Frame {} / {}", run.last_frame(), run.frames));
+                    if let Some((_, last)) = run.samples().last() {
+                        ui.label(format!("Max divergence: {:.3e}", last));
+                    }
+                    if let Some(rate) = run.growth_rate(0.1) {
+                        ui.label(format!("Growth rate: {:.4} per frame", rate));
+                    }
+                    divergence_plot(ui, run);
+                    if run.is_done() && ui.button("Save CSV").clicked() {
+                        match output_path("divergence", "csv").and_then(|path| self.export_divergence_csv(&path).map(|_| path)) {
+                            Ok(path) => eprintln!("saved {}", path.display()),
+                            Err(error) => eprintln!("warning: could not save the divergence curve: {}", error),
+                        }
+                    }
+                }
+            });
+
+            ui.collapsing("CPU validation", |ui| {
+                if ui.button("Validate one step").on_hover_text("Compare one GPU step of the first cloth with the CPU reference").clicked() {
+                    self.validate_step(context);
+                }
+                if let Some(comparison) = self.validation {
+                    let (position_tolerance, velocity_tolerance) = self.precision.validation_tolerance();
+                    let verdict = if comparison.within(position_tolerance, velocity_tolerance) { "match" } else { "MISMATCH" };
+                    ui.label(format!(
+                        "{}: position error {:.2e}, velocity error {:.2e} (worst vertex {})",
+                        verdict, comparison.max_position_error, comparison.max_velocity_error, comparison.worst_vertex
+                    ));
+                }
+            });
+
+            if ui.button("Pin top corners").on_hover_text("Pin both ends of the first row of the first cloth").clicked() {
+                self.pin_top_corners();
+            }
+            ui.horizontal(|ui| {
+                ui.label("Pin animation");
+                if ui.button("Sway back edge").on_hover_text("Move the first row of the first cloth back and forth").clicked() {
+                    let cols = self.primary().grid_cols;
+                    let keyframes = [(0.0, 0.0), (0.5, 0.6), (1.5, -0.6), (2.0, 0.0)]
+                        .map(|(time, x)| Keyframe {
+                            time,
+                            offset: cgmath::Vector3::new(x, 0.0, 0.0),
+                        })
+                        .to_vec();
+                    self.animate_pins(context, PinTrack::new((0..cols).collect(), keyframes).looping(true));
+                }
+                if ui.button("Stop").clicked() {
+                    self.clear_pin_animations();
+                }
+            });
+
+            ui.checkbox(&mut self.backface_culling, "Backface culling")
+                .on_hover_text("Skip the fabric's back faces, the underside of folds is not drawn");
+            ui.horizontal(|ui| {
+                ui.label("Fabric view (M)");
+                for view in FabricView::ALL {
+                    ui.radio_value(&mut self.fabric_view, view, view.name());
+                }
+            });
+            ui.checkbox(&mut self.depth_prepass, "Depth pre-pass")
+                .on_hover_text("Write the depth first so folded fabric is only shaded once per pixel");
+            ui.horizontal(|ui| {
+                let mut tinted = self.backface_tint.is_some();
+                let mut color = self.backface_tint.unwrap_or(DEFAULT_BACKFACE_TINT);
+                let toggled = ui.checkbox(&mut tinted, "Back face tint").changed();
+                let recolored = tinted && ui.color_edit_button_rgb(&mut color).changed();
+                if toggled || recolored {
+                    self.set_backface_tint(tinted.then_some(color));
+                }
+            });
+            ui.horizontal(|ui| {
+                let mut painted = self.background.is_some();
+                let mut color = self.background.unwrap_or(BACKGROUND_PRESETS[0]);
+                let toggled = ui.checkbox(&mut painted, "Background").on_hover_text("Paint over the clear color (B cycles presets)").changed();
+                let recolored = painted && ui.color_edit_button_rgb(&mut color).changed();
+                if toggled || recolored {
+                    self.set_background(painted.then_some(color));
+                }
+            });
+
+            let mut render_preset = self.render_preset;
+            egui::ComboBox::from_label("Render preset")
+                .selected_text(render_preset.name())
+                .show_ui(ui, |ui| {
+                    for preset in RenderPreset::ALL {
+                        ui.selectable_value(&mut render_preset, preset, preset.name());
+                    }
+                });
+            if render_preset != self.render_preset {
+                self.apply_render_preset(render_preset);
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.orthographic, "Orthographic");
+                ui.checkbox(&mut self.edge_overlay, "Edges");
+            });
+            let mut fov = self.lens.fovy;
+            if ui.add(egui::Slider::new(&mut fov, FOV_RANGE).suffix("°").text("Field of view")).changed() {
+                self.set_fov(fov);
+            }
+            let mut far = self.lens.far;
+            if ui.add(egui::Slider::new(&mut far, 10.0..=2000.0).logarithmic(true).text("Far plane")).on_hover_text("Raise it when zooming out clips the cloth").changed() {
+                self.set_clip_planes(self.lens.near, far);
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.render_springs, "Springs").on_hover_text("Draw the springs colored by strain instead of the faces (S)");
+                ui.add_enabled(self.render_springs, egui::Checkbox::new(&mut self.render_shear_springs, "Shear"));
+                ui.add_enabled(self.force_pipeline.is_some(), egui::Checkbox::new(&mut self.force_glyphs, "Forces"))
+                    .on_hover_text("Draw the net force on a sample of the vertices (V), full precision only");
+            });
+
+            ui.collapsing("Motion blur", |ui| {
+                ui.checkbox(&mut self.motion_blur, "Enabled");
+                let mut changed = ui.add(egui::Slider::new(&mut self.motion_blur_time, 0.0..=0.2).text("Trail (s)")).changed();
+                changed |= ui.add(egui::Slider::new(&mut self.motion_blur_opacity, 0.0..=1.0).text("Opacity")).changed();
+                if changed {
+                    self.materials_dirty = true;
+                }
+            });
+
+            let mut lighting = self.lighting;
+            if ui.checkbox(&mut lighting, "Lighting").on_hover_text("Lambert shading from the vertex normals").changed() {
+                self.set_lighting(lighting);
+            }
+            let mut textured = self.textured;
+            if ui.checkbox(&mut textured, "Texture").on_hover_text("Fabric texture instead of the vertex colors").changed() {
+                self.set_textured(textured);
+            }
+            ui.collapsing("Detail normal map", |ui| {
+                let mut changed = ui.checkbox(&mut self.detail_enabled, "Enabled").changed();
+                changed |= ui.add(egui::Slider::new(&mut self.detail_tiling, 1.0..=128.0).text("Tiling")).changed();
+                changed |= ui.add(egui::Slider::new(&mut self.detail_strength, 0.0..=2.0).text("Strength")).changed();
+                if changed {
+                    self.materials_dirty = true;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let mut ruler_enabled = self.ruler_enabled;
+                if ui.checkbox(&mut ruler_enabled, "Ruler").on_hover_text("Click two points to measure their distance").changed() {
+                    self.set_ruler_enabled(ruler_enabled);
+                }
+                if ui.button("Clear").clicked() {
+                    self.ruler.clear();
+                }
+                if let Some(distance) = self.ruler_distance() {
+                    ui.label(format!("{:.3}", distance));
+                }
+            });
+            let mut grab_enabled = self.grab_enabled;
+            if ui.checkbox(&mut grab_enabled, "Grab with the mouse").on_hover_text("Drag fabric vertices around, off while the ruler is on").changed() {
+                self.set_grab_enabled(grab_enabled);
+            }
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_vertex_ids, "Show vertex ids");
+                if self.cloths.iter().any(|cloth| cloth.vertex_count() > MAX_LABELED_VERTICES) {
+                    ui.weak(format!("(grids up to {} vertices)", MAX_LABELED_VERTICES));
+                }
+            });
+            ui.checkbox(&mut self.show_frame_overlay, "Frame time overlay").on_hover_text("FPS, frame time and grid size (F)");
+
+            ui.collapsing("GPU profiler", |ui| match self.profiler.as_mut() {
+                None => {
+                    ui.label("GPU timestamps are not supported on this device");
+                }
+                Some(profiler) => {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.profiling, "Profile compute passes");
+                        if ui.button("Reset").clicked() {
+                            profiler.reset();
+                        }
+                    });
+                    let total: f32 = profiler.timings().iter().map(|t| t.average_ms()).sum();
+                    egui::Grid::new("stage_timings").striped(true).show(ui, |ui| {
+                        for timing in profiler.timings() {
+                            let average = timing.average_ms();
+                            ui.label(timing.name);
+                            ui.label(format!("{:.3} ms", average));
+                            let share = if total > 0.0 { average / total } else { 0.0 };
+                            ui.add(egui::ProgressBar::new(share).desired_width(100.0).text(format!("{:.0}%", share * 100.0)));
+                            ui.end_row();
+                        }
+                    });
+                    ui.label(format!("Total: {:.3} ms", total));
+                }
+            });
+        });
+    }
+
+    pub(super) fn draw_ruler(&self, ctx: &egui::Context) {
+        let view_projection = self.view_projection();
+        let viewport = ctx.screen_rect();
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Middle, egui::Id::new("ruler")));
+        let color = egui::Color32::from_rgb(200, 30, 30);
+        let screen: Vec<egui::Pos2> = self
+            .ruler
+            .points()
+            .iter()
+            .filter_map(|point| projection::project_to_screen(view_projection, self.ruler_position(*point)?, viewport))
+            .collect();
+        for point in &screen {
+            painter.circle_filled(*point, 4.0, color);
+        }
+        if let (&[a, b], Some(distance)) = (&screen[..], self.ruler_distance()) {
+            painter.line_segment([a, b], egui::Stroke::new(2.0, color));
+            painter.text(a.lerp(b, 0.5), egui::Align2::CENTER_BOTTOM, format!("{:.3}", distance), egui::FontId::monospace(14.0), color);
+        }
+    }
+
+    pub(super) fn draw_vertex_ids(&self, ctx: &egui::Context) {
+        let view_projection = self.view_projection();
+        let viewport = ctx.screen_rect();
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Middle, egui::Id::new("vertex_ids")));
+        for cloth in self.cloths.iter().filter(|cloth| self.mirrors_positions(cloth)) {
+            for (index, position) in cloth.positions.iter().enumerate() {
+                if let Some(screen) = projection::project_to_screen(view_projection, *position, viewport) {
+                    painter.text(screen, egui::Align2::CENTER_CENTER, index.to_string(), egui::FontId::monospace(10.0), egui::Color32::BLACK);
+                }
+            }
+        }
+    }
+
+    pub(super) fn draw_frame_overlay(&self, ctx: &egui::Context) {
+        let vertex_count: usize = self.cloths.iter().map(|cloth| cloth.vertex_count()).sum();
+        let last = self.frame_times.last();
+        egui::Area::new(egui::Id::new("frame_overlay"))
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(format!("{:.0} FPS", if last > 0.0 { 1.0 / last } else { 0.0 }));
+                    ui.label(format!("Frame time: {:.2} ms (average of {})", self.frame_times.average() * 1000.0, self.frame_times.samples().len()));
+                    ui.label(format!("Vertices: {}", vertex_count));
+                    ui.label(format!("Substeps: {}", self.substeps));
+                    ui.label(format!("Time scale: {:.2}x", self.time_scale));
+                    if let Some(compute_ms) = self.last_compute_ms() {
+                        ui.label(format!("GPU compute: {:.3} ms", compute_ms));
+                    }
+                    frame_time_plot(ui, &self.frame_times);
+                });
+            });
+    }
+
+    pub(super) fn draw_cloth_labels(&self, ctx: &egui::Context) {
+        let view_projection = self.view_projection();
+        let viewport = ctx.screen_rect();
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Middle, egui::Id::new("cloth_labels")));
+        for cloth in self.cloths.iter().filter(|cloth| !cloth.label.is_empty()) {
+            if let Some(screen) = projection::project_to_screen(view_projection, cloth.anchor, viewport) {
+                painter.text(screen, egui::Align2::CENTER_BOTTOM, &cloth.label, egui::FontId::proportional(14.0), egui::Color32::BLACK);
+            }
+        }
+    }
+}
+
+// Frame times oldest to newest, scaled to the longest one, with the 60 FPS
+// budget as a reference line when it fits
+fn frame_time_plot(ui: &mut egui::Ui, frame_times: &FrameTimes) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 50.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.bg_stroke);
+
+    let scale = frame_times.max().max(1.0 / 60.0);
+    let budget_y = rect.bottom() - rect.height() * (1.0 / 60.0) / scale;
+    painter.hline(rect.x_range(), budget_y, egui::Stroke::new(1.0, egui::Color32::from_rgb(200, 120, 0)));
+    let points: Vec<egui::Pos2> = frame_times
+        .samples()
+        .enumerate()
+        .map(|(frame, time)| {
+            egui::pos2(
+                rect.left() + rect.width() * frame as f32 / (frame_times.capacity() - 1).max(1) as f32,
+                rect.bottom() - rect.height() * time / scale,
+            )
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, ui.visuals().text_color())));
+}
+
+// log10 of the divergence over the frames of the run
+fn divergence_plot(ui: &mut egui::Ui, run: &DivergenceRun) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 80.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.bg_stroke);
+
+    let (min_log, max_log) = (-8.0, 1.0);
+    let points: Vec<egui::Pos2> = run
+        .samples()
+        .iter()
+        .map(|&(frame, divergence)| {
+            let log = divergence.max(1e-8).log10().clamp(min_log, max_log);
+            egui::pos2(
+                rect.left() + rect.width() * frame as f32 / run.frames.max(1) as f32,
+                rect.bottom() - rect.height() * (log - min_log) / (max_log - min_log),
+            )
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, ui.visuals().text_color())));
+}
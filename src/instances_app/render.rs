@@ -0,0 +1,472 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use super::{FabricView, InstanceApp, FORCE_GLYPH_STEP, MOTION_BLUR_SAMPLES};
+use crate::gpu_context::Context;
+use crate::png::{self, RgbaImage};
+use wgpu_bootstrap::wgpu;
+
+// Entry points, blending and culling of a render pipeline variant
+#[derive(Copy, Clone)]
+pub(super) struct RenderStyle {
+    pub(super) vertex_entry: &'static str,
+    pub(super) fragment_entry: &'static str,
+    pub(super) blend: wgpu::BlendState,
+    pub(super) depth_write: bool,
+    pub(super) cull_mode: Option<wgpu::Face>,
+    pub(super) topology: wgpu::PrimitiveTopology,
+    pub(super) depth_compare: wgpu::CompareFunction,
+    pub(super) write_mask: wgpu::ColorWrites,
+    pub(super) polygon_mode: wgpu::PolygonMode,
+}
+
+impl RenderStyle {
+    pub(super) const OPAQUE: RenderStyle = RenderStyle {
+        vertex_entry: "vs_main",
+        fragment_entry: "fs_main",
+        blend: wgpu::BlendState::REPLACE,
+        depth_write: true,
+        cull_mode: None,
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        depth_compare: wgpu::CompareFunction::Less,
+        write_mask: wgpu::ColorWrites::ALL,
+        polygon_mode: wgpu::PolygonMode::Fill,
+    };
+    // Translucent motion blur copies, drawn after the fabric without hiding each other
+    pub(super) const SMEAR: RenderStyle = RenderStyle {
+        vertex_entry: "vs_smear",
+        fragment_entry: "fs_smear",
+        blend: wgpu::BlendState::ALPHA_BLENDING,
+        depth_write: false,
+        cull_mode: None,
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        depth_compare: wgpu::CompareFunction::Less,
+        write_mask: wgpu::ColorWrites::ALL,
+        polygon_mode: wgpu::PolygonMode::Fill,
+    };
+    // Structural edges over the fabric, also drawn where they lie exactly on its faces
+    pub(super) const EDGES: RenderStyle = RenderStyle {
+        vertex_entry: "vs_edge",
+        fragment_entry: "fs_edge",
+        blend: wgpu::BlendState::REPLACE,
+        depth_write: false,
+        cull_mode: None,
+        topology: wgpu::PrimitiveTopology::LineList,
+        depth_compare: wgpu::CompareFunction::LessEqual,
+        write_mask: wgpu::ColorWrites::ALL,
+        polygon_mode: wgpu::PolygonMode::Fill,
+    };
+    // Spring diagram drawn instead of the fabric faces, colored by strain
+    pub(super) const SPRINGS: RenderStyle = RenderStyle {
+        vertex_entry: "vs_spring",
+        fragment_entry: "fs_spring",
+        blend: wgpu::BlendState::REPLACE,
+        depth_write: true,
+        cull_mode: None,
+        topology: wgpu::PrimitiveTopology::LineList,
+        depth_compare: wgpu::CompareFunction::Less,
+        write_mask: wgpu::ColorWrites::ALL,
+        polygon_mode: wgpu::PolygonMode::Fill,
+    };
+    // Fullscreen flat color drawn first, without vertex buffers
+    pub(super) const BACKGROUND: RenderStyle = RenderStyle {
+        vertex_entry: "vs_background",
+        fragment_entry: "fs_background",
+        blend: wgpu::BlendState::REPLACE,
+        depth_write: false,
+        cull_mode: None,
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        depth_compare: wgpu::CompareFunction::Always,
+        write_mask: wgpu::ColorWrites::ALL,
+        polygon_mode: wgpu::PolygonMode::Fill,
+    };
+    // Fabric triangles drawn as their outlines, needs `Features::POLYGON_MODE_LINE`
+    pub(super) const WIREFRAME: RenderStyle = RenderStyle {
+        polygon_mode: wgpu::PolygonMode::Line,
+        ..RenderStyle::OPAQUE
+    };
+    // Structural edges in the vertex colors, the wireframe without `POLYGON_MODE_LINE`
+    pub(super) const LINES: RenderStyle = RenderStyle {
+        fragment_entry: "fs_flat",
+        topology: wgpu::PrimitiveTopology::LineList,
+        ..RenderStyle::OPAQUE
+    };
+    // Lines along the net force of sampled fabric vertices
+    pub(super) const FORCES: RenderStyle = RenderStyle {
+        vertex_entry: "vs_force",
+        fragment_entry: "fs_flat",
+        topology: wgpu::PrimitiveTopology::LineList,
+        ..RenderStyle::OPAQUE
+    };
+    // One pixel per fabric vertex in its color
+    pub(super) const POINTS: RenderStyle = RenderStyle {
+        fragment_entry: "fs_flat",
+        topology: wgpu::PrimitiveTopology::PointList,
+        ..RenderStyle::OPAQUE
+    };
+
+    // Depth pre-pass version: same vertices, no color and a trivial fragment shader
+    pub(super) fn depth_only(self) -> RenderStyle {
+        RenderStyle {
+            fragment_entry: "fs_depth",
+            write_mask: wgpu::ColorWrites::empty(),
+            ..self
+        }
+    }
+
+    // Color pass after the pre-pass: only the front-most fragment is shaded
+    pub(super) fn depth_equal(self) -> RenderStyle {
+        RenderStyle {
+            depth_write: false,
+            depth_compare: wgpu::CompareFunction::Equal,
+            ..self
+        }
+    }
+}
+
+// Sphere and fabric pipelines of the depth pre-pass, see `set_depth_prepass`.
+// The `*_depth` ones fill the depth buffer, the others then shade with an
+// `Equal` depth test.
+pub(super) struct DepthPrepassPipelines {
+    pub(super) sphere_depth: wgpu::RenderPipeline,
+    pub(super) fabric_depth: wgpu::RenderPipeline,
+    pub(super) fabric_culled_depth: wgpu::RenderPipeline,
+    pub(super) sphere: wgpu::RenderPipeline,
+    pub(super) fabric: wgpu::RenderPipeline,
+    pub(super) fabric_culled: wgpu::RenderPipeline,
+}
+
+pub(super) fn create_render_pipeline(
+    context: &Context,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
+    style: RenderStyle,
+    label: &str,
+) -> wgpu::RenderPipeline {
+    context
+        .device()
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: style.vertex_entry,
+                buffers: vertex_layouts,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: style.fragment_entry,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: context.format(),
+                    blend: Some(style.blend),
+                    write_mask: style.write_mask,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: style.topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: style.cull_mode,
+                polygon_mode: style.polygon_mode,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: context.depth_stencil_format(),
+                depth_write_enabled: style.depth_write,
+                depth_compare: style.depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+}
+
+impl InstanceApp {
+    /// Renders the scene once into an offscreen texture the size of the window
+    /// and reads the pixels back. It goes through `render`, so it uses the same
+    /// pipelines and camera as the window, but the panel is left out and the
+    /// background is cleared to black. Expects an 8-bit RGBA or BGRA surface
+    /// format. Waits for the GPU, so keep it out of the per-frame path.
+    pub fn capture_frame(&self, context: &Context) -> RgbaImage {
+        let format = context.format();
+        assert_eq!(format.block_copy_size(None), Some(4), "capture_frame needs an 8-bit RGBA surface, not {:?}", format);
+        let size = context.size();
+        let (width, height) = ((size.x as u32).max(1), (size.y as u32).max(1));
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let create_target = |format, usage, label| {
+            context.device().create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let color = create_target(format, wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC, "Capture Color Texture");
+        let depth = create_target(context.depth_stencil_format(), wgpu::TextureUsages::RENDER_ATTACHMENT, "Capture Depth Texture");
+        let color_view = color.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Texture to buffer copies need rows padded to COPY_BYTES_PER_ROW_ALIGNMENT (256 bytes)
+        let padded_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Readback Buffer"),
+            size: padded_row as wgpu::BufferAddress * height as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Capture Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Capture Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &color_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.draw_scene(&mut render_pass);
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &color,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &staging_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            extent,
+        );
+        context.queue().submit(Some(encoder.finish()));
+
+        let slice = staging_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        context.device().poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("readback callback dropped")
+            .expect("failed to map the capture readback buffer");
+
+        let bgra = matches!(format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb);
+        let pixels = slice
+            .get_mapped_range()
+            .chunks_exact(padded_row as usize)
+            .flat_map(|row| row[..width as usize * 4].chunks_exact(4))
+            .map(|p| if bgra { [p[2], p[1], p[0], p[3]] } else { [p[0], p[1], p[2], p[3]] })
+            .collect();
+        staging_buffer.unmap();
+        RgbaImage { width, height, pixels }
+    }
+
+    /// Writes `capture_frame` as a PNG file. The P key saves one next to the
+    /// executable.
+    pub fn save_capture(&self, context: &Context, path: &Path) -> io::Result<()> {
+        png::write_png(BufWriter::new(File::create(path)?), &self.capture_frame(context))
+    }
+
+    // Every collision sphere, one instance each
+    fn draw_spheres(&self, render_pass: &mut wgpu::RenderPass<'_>, pipeline: &wgpu::RenderPipeline, camera_bind_group: &wgpu::BindGroup) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.sphere_material_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.sphere_vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.cloth_bindings.collider_buffer.slice(..));
+        render_pass.set_index_buffer(self.sphere_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        // The soft sphere is drawn in place of the first collider
+        let first = self.soft_sphere.is_some() as u32;
+        render_pass.draw_indexed(0..self.num_sphere_indices, 0, first..self.colliders.len() as u32);
+    }
+
+    // The soft sphere from its own vertices, in the sphere colors
+    fn draw_soft_sphere(&self, render_pass: &mut wgpu::RenderPass<'_>, camera_bind_group: &wgpu::BindGroup) {
+        let Some(soft_sphere) = &self.soft_sphere else {
+            return;
+        };
+        render_pass.set_pipeline(&self.ground_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.sphere_material_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, soft_sphere.vertex_buffer().slice(..));
+        render_pass.set_index_buffer(soft_sphere.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..soft_sphere.index_count, 0, 0..1);
+    }
+
+    // The faces of every cloth
+    fn draw_fabrics(&self, render_pass: &mut wgpu::RenderPass<'_>, pipeline: &wgpu::RenderPipeline, camera_bind_group: &wgpu::BindGroup) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        for cloth in &self.cloths {
+            render_pass.set_bind_group(1, &cloth.material_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, cloth.vertex_buffer(self.frame_parity).slice(..));
+            render_pass.set_index_buffer(cloth.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..cloth.indices.len() as u32, 0, 0..1);
+        }
+    }
+
+    // Everything but the egui overlays, for `App::render` and `capture_frame`
+    pub(super) fn draw_scene(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        let camera_bind_group = if self.orthographic { &self.ortho_camera_bind_group } else { self.camera.bind_group() };
+        let prepass = self.depth_prepass.then_some(&self.prepass_pipelines);
+
+        // Depth of the sphere and the fabric faces first, the color draws below
+        // then only shade what is in front
+        if let Some(pipelines) = prepass {
+            self.draw_spheres(render_pass, &pipelines.sphere_depth, camera_bind_group);
+            if !self.render_springs && self.fabric_view == FabricView::Solid {
+                let pipeline = if self.backface_culling { &pipelines.fabric_culled_depth } else { &pipelines.fabric_depth };
+                self.draw_fabrics(render_pass, pipeline, camera_bind_group);
+            }
+        }
+
+        // Flat background, before anything else
+        if self.background.is_some() {
+            render_pass.set_pipeline(&self.background_pipeline);
+            render_pass.set_bind_group(0, camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.background_material_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        // Floor at the ground plane, in the sphere colors
+        if self.ground_plane.is_some() {
+            render_pass.set_pipeline(&self.ground_pipeline);
+            render_pass.set_bind_group(0, camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.ground_material_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.ground_vertex_buffer.slice(..));
+            render_pass.draw(0..6, 0..1);
+        }
+
+        // Draw the sphere
+        self.draw_spheres(render_pass, prepass.map_or(&self.render_pipeline, |pipelines| &pipelines.sphere), camera_bind_group);
+        self.draw_soft_sphere(render_pass, camera_bind_group);
+    
+        // Draw the fabrics, or their springs
+        if self.render_springs {
+            render_pass.set_pipeline(&self.spring_pipeline);
+            render_pass.set_bind_group(0, camera_bind_group, &[]);
+            let vertex_size = self.precision.vertex_size();
+            for cloth in &self.cloths {
+                let vertex_buffer = cloth.vertex_buffer(self.frame_parity);
+                let count = cloth.vertex_count;
+                render_pass.set_bind_group(1, &cloth.material_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                for (direction, offset) in cloth.spring_offsets().into_iter().enumerate().take(if self.render_shear_springs { 4 } else { 2 }) {
+                    let first_vertex = 2 * direction as u32;
+                    render_pass.set_vertex_buffer(1, vertex_buffer.slice(offset as wgpu::BufferAddress * vertex_size..));
+                    render_pass.draw(first_vertex..first_vertex + 2, 0..count - offset);
+                }
+            }
+        } else {
+            match (self.fabric_view, &self.wireframe_pipeline) {
+                (FabricView::Solid, _) => {
+                    let pipeline = match (prepass, self.backface_culling) {
+                        (Some(pipelines), true) => &pipelines.fabric_culled,
+                        (Some(pipelines), false) => &pipelines.fabric,
+                        (None, true) => &self.fabric_culled_pipeline,
+                        (None, false) => &self.fabric_pipeline,
+                    };
+                    self.draw_fabrics(render_pass, pipeline, camera_bind_group);
+                }
+                (FabricView::Wireframe, Some(pipeline)) => self.draw_fabrics(render_pass, pipeline, camera_bind_group),
+                (FabricView::Wireframe, None) => {
+                    render_pass.set_pipeline(&self.lines_pipeline);
+                    render_pass.set_bind_group(0, camera_bind_group, &[]);
+                    for cloth in &self.cloths {
+                        render_pass.set_bind_group(1, &cloth.material_bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, cloth.vertex_buffer(self.frame_parity).slice(..));
+                        render_pass.set_index_buffer(cloth.edge_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                        render_pass.draw_indexed(0..cloth.edge_index_count, 0, 0..1);
+                    }
+                }
+                (FabricView::Points, _) => {
+                    render_pass.set_pipeline(&self.points_pipeline);
+                    render_pass.set_bind_group(0, camera_bind_group, &[]);
+                    for cloth in &self.cloths {
+                        render_pass.set_bind_group(1, &cloth.material_bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, cloth.vertex_buffer(self.frame_parity).slice(..));
+                        render_pass.draw(0..cloth.vertex_count() as u32, 0..1);
+                    }
+                }
+            }
+        }
+
+        // Structural edges over the fabrics
+        if self.edge_overlay {
+            render_pass.set_pipeline(&self.edge_pipeline);
+            for cloth in &self.cloths {
+                render_pass.set_bind_group(1, &cloth.material_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, cloth.vertex_buffer(self.frame_parity).slice(..));
+                render_pass.set_index_buffer(cloth.edge_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..cloth.edge_index_count, 0, 0..1);
+            }
+        }
+
+        // Force glyphs, one draw per sampled row
+        if let Some(pipeline) = self.force_pipeline.as_ref().filter(|_| self.force_glyphs) {
+            render_pass.set_pipeline(pipeline);
+            let vertex_size = self.precision.vertex_size();
+            for cloth in &self.cloths {
+                render_pass.set_bind_group(1, &cloth.material_bind_group, &[]);
+                let vertex_buffer = cloth.vertex_buffer(self.frame_parity);
+                for row in (0..cloth.grid_rows).step_by(FORCE_GLYPH_STEP as usize) {
+                    let row_start = (row * cloth.grid_cols) as wgpu::BufferAddress * vertex_size;
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(row_start..));
+                    render_pass.draw(0..2, 0..cloth.grid_cols.div_ceil(FORCE_GLYPH_STEP));
+                }
+            }
+        }
+
+        // Motion blur: one instance per trailing copy
+        if self.motion_blur {
+            render_pass.set_pipeline(&self.smear_pipeline);
+            for cloth in &self.cloths {
+                render_pass.set_bind_group(1, &cloth.material_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, cloth.vertex_buffer(self.frame_parity).slice(..));
+                render_pass.set_index_buffer(cloth.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..cloth.indices.len() as u32, 0, 0..MOTION_BLUR_SAMPLES);
+            }
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use super::cloth::{default_sim_params, fabric_grid, Cloth, ClothDesc, SimParams1, SimParams2};
+use super::{ClothConfig, ClothTransform, GridLimitError, InstanceApp, DEFAULT_CAMERA_RADIUS, DEFAULT_SIDE_LENGTH, FLAG_DEPTH, FLAG_WIND};
+use crate::gpu_context::Context;
+use crate::param_bounds::ParamBounds;
+use wgpu_bootstrap::cgmath;
+use cgmath::Rotation3;
+use serde::{Deserialize, Serialize};
+
+// Cloths of the material strip, the spacing matches the default rest lengths
+const STRIP_GRID_SIZE: u32 = 27;
+const STRIP_SIDE_LENGTH: f32 = 1.56;
+const STRIP_GAP: f32 = 0.6;
+// Cloths of the "Flags" scene, upright like `ClothPreset::Flag`
+const FLAGS_COUNT: usize = 3;
+const FLAGS_SIDE_LENGTH: f32 = 2.5;
+const FLAGS_GRID_SIZE: u32 = 40;
+
+/// Layout of the single cloth, see `ClothBuilder::preset`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClothPreset {
+    /// Flat and centered above the ball, nothing pinned
+    #[default]
+    #[serde(rename = "drape")]
+    DrapeOverSphere,
+    /// Hanging upright behind the ball from its pinned left edge, in a steady
+    /// turbulent wind
+    #[serde(rename = "flag")]
+    Flag,
+    /// Flat above the ball, pinned at its four corners
+    #[serde(rename = "hammock")]
+    Hammock,
+}
+
+impl ClothPreset {
+    /// Names on the command line, see `ClothBuilder::from_args`
+    pub const NAMES: [(&'static str, ClothPreset); 3] = [
+        ("drape", ClothPreset::DrapeOverSphere),
+        ("flag", ClothPreset::Flag),
+        ("hammock", ClothPreset::Hammock),
+    ];
+
+    // Placement of the grid, which lies in the local xz plane with row 0 at -z
+    pub(super) fn transform(self, initial_height: f32) -> ClothTransform {
+        match self {
+            ClothPreset::DrapeOverSphere | ClothPreset::Hammock => ClothTransform::from_translation(cgmath::Vector3::new(0.0, initial_height, 0.0)),
+            // Upright in the xy plane, row 0 on top, clear of the ball
+            ClothPreset::Flag => ClothTransform {
+                translation: cgmath::Vector3::new(0.0, initial_height, -FLAG_DEPTH),
+                rotation: cgmath::Quaternion::from_angle_x(cgmath::Deg(90.0)),
+            },
+        }
+    }
+
+    // Indices of the pinned vertices
+    pub(super) fn pinned(self, rows: u32, cols: u32) -> Vec<u32> {
+        match self {
+            ClothPreset::DrapeOverSphere => Vec::new(),
+            ClothPreset::Flag => (0..rows).map(|row| row * cols).collect(),
+            ClothPreset::Hammock => vec![0, cols - 1, (rows - 1) * cols, rows * cols - 1],
+        }
+    }
+}
+
+/// Scene the app starts with, see `ClothBuilder::scene`. The buttons at the
+/// top of the panel switch between them afterwards.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Scene {
+    /// One cloth dropped on the sphere, `InstanceApp::single_cloth`
+    SingleCloth,
+    /// Cloths of increasing structural stiffness side by side, `InstanceApp::material_strip`
+    StiffnessStrip,
+    /// World and local gravity side by side, `InstanceApp::gravity_space_demo`
+    GravitySpace,
+    /// Three flags in the wind side by side, `InstanceApp::flags_demo`
+    Flags,
+}
+
+impl Scene {
+    /// Names on the command line, see `ClothBuilder::from_args`
+    pub const NAMES: [(&'static str, Scene); 4] = [
+        ("single", Scene::SingleCloth),
+        ("strip", Scene::StiffnessStrip),
+        ("gravity-space", Scene::GravitySpace),
+        ("flags", Scene::Flags),
+    ];
+}
+
+/// Physics parameter varied across the cloths of `InstanceApp::material_strip`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MaterialParam {
+    StructuralStiffness,
+    ShearStiffness,
+    BendingStiffness,
+    Damping,
+}
+
+impl MaterialParam {
+    pub fn name(self) -> &'static str {
+        match self {
+            MaterialParam::StructuralStiffness => "Structural stiffness",
+            MaterialParam::ShearStiffness => "Shear stiffness",
+            MaterialParam::BendingStiffness => "Bending stiffness",
+            MaterialParam::Damping => "Damping",
+        }
+    }
+
+    pub fn bounds(self) -> ParamBounds {
+        match self {
+            MaterialParam::Damping => ParamBounds::DAMPING,
+            _ => ParamBounds::STIFFNESS,
+        }
+    }
+
+    fn apply(self, sim_params1: &mut SimParams1, sim_params2: &mut SimParams2, value: f32) {
+        let value = self.bounds().clamp(value);
+        match self {
+            MaterialParam::StructuralStiffness => sim_params2.set_structural_stiffness(value),
+            MaterialParam::ShearStiffness => sim_params2.stiffness[1] = value,
+            MaterialParam::BendingStiffness => sim_params2.stiffness[2] = value,
+            MaterialParam::Damping => sim_params1.grid_k_radius[2] = value,
+        }
+    }
+}
+
+impl InstanceApp {
+    /// Replaces the scene with one cloth per value in `values`, laid out in a row
+    /// along x at the same height, with `param` set to that value and everything
+    /// else at its default. Each cloth hangs from the two corners of its back edge
+    /// and is labeled with its value. All cloths start moving on the next update.
+    pub fn material_strip(&mut self, context: &Context, param: MaterialParam, values: &[f32]) {
+        if values.is_empty() {
+            return;
+        }
+        let pitch = STRIP_SIDE_LENGTH + STRIP_GAP;
+        let width = pitch * values.len() as f32 - STRIP_GAP;
+        let corners = [0, STRIP_GRID_SIZE - 1];
+
+        self.cloths = values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| {
+                let center = cgmath::Vector3::new(-width / 2.0 + STRIP_SIDE_LENGTH / 2.0 + pitch * i as f32, 2.0, 0.0);
+                let transform = ClothTransform::from_translation(center);
+                let mut vertices = fabric_grid(STRIP_GRID_SIZE, STRIP_GRID_SIZE, STRIP_SIDE_LENGTH, transform);
+                for col in corners {
+                    vertices[col as usize].fixed = 1.0;
+                }
+
+                let (mut sim_params1, mut sim_params2) = default_sim_params(STRIP_GRID_SIZE, STRIP_GRID_SIZE, STRIP_SIDE_LENGTH / (STRIP_GRID_SIZE - 1) as f32);
+                param.apply(&mut sim_params1, &mut sim_params2, value);
+                // Keep the scene-wide settings of the current scene
+                let current = &self.cloths[0];
+                sim_params1.collision = current.sim_params1.collision;
+                sim_params2.gravity = current.sim_params2.gravity;
+
+                let desc = ClothDesc {
+                    label: format!("{} = {}", param.name(), param.bounds().clamp(value)),
+                    vertices,
+                    grid_rows: STRIP_GRID_SIZE,
+                    grid_cols: STRIP_GRID_SIZE,
+                    sim_params1,
+                    sim_params2,
+                    transform,
+                    anchor: center - cgmath::Vector3::new(0.0, 0.0, STRIP_SIDE_LENGTH / 2.0 + 0.2),
+                };
+                Cloth::new(context, self.precision, &self.cloth_bindings, desc)
+            })
+            .collect();
+        self.clear_transient_state();
+
+        self.camera.set_radius((width * 1.2).max(7.0)).update(context);
+    }
+
+    /// Adds a cloth to the scene built from `config`, next to the ones already
+    /// there, and returns its index (see `set_local_gravity`). It has its own
+    /// buffers and uniforms, with its own grid size in them so its springs
+    /// stay within it, and is stepped in the same passes as the others. Its
+    /// parameters start from the first cloth's, with rest lengths of its own
+    /// grid spacing. Fails when the grid has fewer than 2 or more than 512
+    /// rows or columns or exceeds the device limits, see
+    /// `ClothBuilder::try_build`.
+    pub fn add_cloth(&mut self, context: &Context, config: &ClothConfig) -> Result<usize, GridLimitError> {
+        let config = ClothConfig {
+            side_length: if config.side_length > 0.0 { config.side_length } else { DEFAULT_SIDE_LENGTH },
+            ..config.clone()
+        };
+        let mut builder = self.builder.clone();
+        builder.fabric.rows = config.rows;
+        builder.fabric.cols = config.cols;
+        builder.check_limits(&context.device().limits())?;
+
+        let cloth = self.new_cloth(context, config);
+        self.cloths.push(cloth);
+        self.materials_dirty = true;
+        Ok(self.cloths.len() - 1)
+    }
+
+    // A cloth with the parameters of the current first cloth, but the grid
+    // and rest lengths of `config`
+    fn new_cloth(&self, context: &Context, config: ClothConfig) -> Cloth {
+        let ClothConfig { rows, cols, side_length, transform, pinned, label } = config;
+        let mut vertices = fabric_grid(rows, cols, side_length, transform);
+        for index in pinned {
+            if let Some(vertex) = vertices.get_mut(index as usize) {
+                vertex.fixed = 1.0;
+            }
+        }
+
+        let (grid_params1, grid_params2) = default_sim_params(rows, cols, side_length / (cols - 1) as f32);
+        let (mut sim_params1, mut sim_params2) = (self.cloths[0].sim_params1, self.cloths[0].sim_params2);
+        sim_params1.grid_k_radius[..2].copy_from_slice(&grid_params1.grid_k_radius[..2]);
+        sim_params2.rest_length[..3].copy_from_slice(&grid_params2.rest_length[..3]);
+
+        let desc = ClothDesc {
+            label,
+            vertices,
+            grid_rows: rows,
+            grid_cols: cols,
+            sim_params1,
+            sim_params2,
+            transform,
+            anchor: transform.transform_point(cgmath::Vector3::new(0.0, 0.2, -side_length / 2.0)),
+        };
+        Cloth::new(context, self.precision, &self.cloth_bindings, desc)
+    }
+
+    /// Replaces the scene with three flags side by side behind the ball, each
+    /// hanging from its pinned left edge, in the steady turbulent wind of
+    /// `ClothPreset::Flag`. Built with `add_cloth`, so they start from the
+    /// parameters of the current first cloth.
+    pub fn flags_demo(&mut self, context: &Context) {
+        let pitch = FLAGS_SIDE_LENGTH + STRIP_GAP;
+        let width = pitch * FLAGS_COUNT as f32 - STRIP_GAP;
+        let rotation = cgmath::Quaternion::from_angle_x(cgmath::Deg(90.0));
+        let cloths = (0..FLAGS_COUNT)
+            .map(|i| {
+                let config = ClothConfig {
+                    rows: FLAGS_GRID_SIZE,
+                    cols: FLAGS_GRID_SIZE,
+                    side_length: FLAGS_SIDE_LENGTH,
+                    transform: ClothTransform {
+                        translation: cgmath::Vector3::new(-width / 2.0 + FLAGS_SIDE_LENGTH / 2.0 + pitch * i as f32, 2.5, -FLAG_DEPTH),
+                        rotation,
+                    },
+                    pinned: (0..FLAGS_GRID_SIZE).map(|row| row * FLAGS_GRID_SIZE).collect(),
+                    label: String::new(),
+                };
+                self.new_cloth(context, config)
+            })
+            .collect();
+        self.cloths = cloths;
+        self.clear_transient_state();
+
+        let ([x, y, z], turbulence) = FLAG_WIND;
+        self.set_wind(cgmath::Vector3::new(x, y, z), turbulence);
+        self.camera.set_radius((width * 1.2).max(7.0)).update(context);
+    }
+
+    /// Replaces the scene with two cloths tilted by 60 degrees around z, both
+    /// pinned at two corners. The left one feels gravity in world space, the
+    /// right one in its own rotated frame, so it swings "sideways" along the tilt.
+    pub fn gravity_space_demo(&mut self, context: &Context) {
+        let rotation = cgmath::Quaternion::from_angle_z(cgmath::Deg(60.0));
+        let current = &self.cloths[0];
+        let (collision, gravity) = (current.sim_params1.collision, current.sim_params2.gravity);
+
+        self.cloths = [("World gravity", -1.5, false), ("Local gravity", 1.5, true)]
+            .into_iter()
+            .map(|(label, x, local_gravity)| {
+                let transform = ClothTransform {
+                    translation: cgmath::Vector3::new(x, 2.5, 0.0),
+                    rotation,
+                };
+                let mut vertices = fabric_grid(STRIP_GRID_SIZE, STRIP_GRID_SIZE, STRIP_SIDE_LENGTH, transform);
+                for col in [0, STRIP_GRID_SIZE - 1] {
+                    vertices[col as usize].fixed = 1.0;
+                }
+
+                let (mut sim_params1, mut sim_params2) = default_sim_params(STRIP_GRID_SIZE, STRIP_GRID_SIZE, STRIP_SIDE_LENGTH / (STRIP_GRID_SIZE - 1) as f32);
+                sim_params1.collision = collision;
+                sim_params1.options[0] = if local_gravity { 1.0 } else { 0.0 };
+                sim_params2.gravity = gravity;
+
+                let desc = ClothDesc {
+                    label: label.to_string(),
+                    vertices,
+                    grid_rows: STRIP_GRID_SIZE,
+                    grid_cols: STRIP_GRID_SIZE,
+                    sim_params1,
+                    sim_params2,
+                    transform,
+                    anchor: transform.translation + cgmath::Vector3::new(0.0, 1.2, 0.0),
+                };
+                Cloth::new(context, self.precision, &self.cloth_bindings, desc)
+            })
+            .collect();
+        self.clear_transient_state();
+        self.camera.set_radius(8.0).update(context);
+    }
+
+    /// Replaces the scene with the default single cloth above the sphere.
+    pub fn single_cloth(&mut self, context: &Context) {
+        self.cloths = vec![Cloth::new(context, self.precision, &self.cloth_bindings, self.builder.cloth_desc())];
+        self.relax_frames_left = self.builder.relax_frames;
+        self.clear_transient_state();
+        self.camera.set_radius(DEFAULT_CAMERA_RADIUS).update(context);
+    }
+}
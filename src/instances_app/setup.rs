@@ -0,0 +1,586 @@
+use super::{
+    create_detail_texture, create_fabric_texture, create_flow_texture, create_render_pipeline, Cloth, ClothBindings, ClothBuilder, ColliderMesh, ColorMode,
+    ConstraintSolver, DepthPrepassPipelines, FabricMaterial, FabricView, GpuCollider, InstanceApp, IntegrationMethod, RenderPreset, RenderStyle, SelectionOp,
+    StageToggles, BLUEPRINT_EDGE, DEFAULT_BACKFACE_TINT, DEFAULT_CAMERA_RADIUS, DEFAULT_ENERGY_GROWTH_LIMIT, DEFAULT_SELF_COLLISION_RADIUS,
+    DEFAULT_SETTLE_FRAMES, DEFAULT_SETTLE_SPEED, DETAIL_MAP_SIZE, FABRIC_TEXTURE_SIZE, FRAME_TIME_SAMPLES, MAX_COLLIDERS,
+};
+use crate::detail_map;
+use crate::frame_times::FrameTimes;
+use crate::gpu_context::Context;
+use crate::heatmap;
+use crate::orbit_camera::{CameraUniform, OrbitCamera};
+use crate::profiler::Profiler;
+use crate::projection::Lens;
+use crate::ruler::Ruler;
+use crate::vertex::Vertex;
+use wgpu_bootstrap::wgpu::{self, util::DeviceExt};
+
+pub(super) fn compute_buffer_entry(binding: u32, ty: wgpu::BufferBindingType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub(super) fn create_compute_pipeline(
+    context: &Context,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    entry_point: &str,
+    label: &str,
+) -> wgpu::ComputePipeline {
+    context
+        .device()
+        .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            module,
+            entry_point,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        })
+}
+
+impl InstanceApp {
+    pub(super) fn from_builder(context: &Context, builder: ClothBuilder) -> Self {
+        let precision = builder.precision;
+        let workgroup_layout = builder.workgroup_layout;
+
+        let collider_mesh = builder.collider_mesh.clone().unwrap_or_else(ColliderMesh::unit_sphere);
+
+        let sphere_vertex_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sphere Vertex Buffer"),
+            contents: bytemuck::cast_slice(&collider_mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE| wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
+        });
+
+        let sphere_index_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sphere Index Buffer"),
+            contents: bytemuck::cast_slice(&collider_mesh.indices),
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE| wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
+        });
+
+        // Shaders and pipeline
+        let shader = context.device().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shader.wgsl").into()),
+        });
+
+        // Create the compute shader, prefixed with the storage layout for the chosen precision,
+        // the workgroup size and the heatmap gradient shared with the legend
+        let compute_source = format!(
+            "{}\n{}\n{}\n{}",
+            precision.storage_shader(),
+            workgroup_layout.wgsl(),
+            heatmap::wgsl(),
+            include_str!("../computeShader.wgsl")
+        );
+        let compute_shader = context.device().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Compute Shader"),
+            source: wgpu::ShaderSource::Wgsl(compute_source.into()),
+        });
+
+        let camera_bind_group_layout = context.device().create_bind_group_layout(&CameraUniform::desc());
+
+        let material_bind_group_layout = context.device().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Material Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = context.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&camera_bind_group_layout, &material_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let compute_bind_group_layout = context
+        .device()
+        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Compute Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                compute_buffer_entry(3, wgpu::BufferBindingType::Storage { read_only: false }),
+                compute_buffer_entry(4, wgpu::BufferBindingType::Storage { read_only: false }),
+                compute_buffer_entry(5, wgpu::BufferBindingType::Storage { read_only: false }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // State before the step, read by the integration pass
+                compute_buffer_entry(8, wgpu::BufferBindingType::Storage { read_only: true }),
+                // Uniform rather than storage, the selection pipeline already
+                // uses all 8 storage buffers a stage gets by default
+                compute_buffer_entry(9, wgpu::BufferBindingType::Uniform),
+                // Fabric index buffer, rewritten by cs_tear_triangles
+                compute_buffer_entry(10, wgpu::BufferBindingType::Storage { read_only: false }),
+                compute_buffer_entry(11, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+
+        // Create the compute pipelines, one per solver stage
+        let compute_pipeline_layout = context.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Compute Pipeline Layout"),
+            bind_group_layouts: &[&compute_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_main", "Compute Pipeline");
+        let collide_pipeline = create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_collide", "Collide Pipeline");
+        let stats_pipeline = create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_reduce_stats", "Stats Pipeline");
+        let heatmap_pipeline = create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_heatmap", "Heatmap Pipeline");
+        let normals_pipeline = create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_normals", "Normals Pipeline");
+        let tear_pipeline = create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_tear_triangles", "Tear Pipeline");
+        let self_collision_pipelines = [
+            create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_clear_self_collision", "Self Collision Clear Pipeline"),
+            create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_bin_self_collision", "Self Collision Bin Pipeline"),
+            create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_self_collide", "Self Collision Pipeline"),
+        ];
+        let verlet_velocity_pipeline =
+            create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_verlet_velocity", "Verlet Velocity Pipeline");
+        let hold_pins_pipeline = create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_hold_pins", "Hold Pins Pipeline");
+        let jacobi_pipelines = [
+            create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_project_jacobi", "Jacobi Project Pipeline"),
+            create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_apply_jacobi", "Jacobi Apply Pipeline"),
+        ];
+        let red_black_pipelines = [
+            create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_project_red", "Red Project Pipeline"),
+            create_compute_pipeline(context, &compute_pipeline_layout, &compute_shader, "cs_project_black", "Black Project Pipeline"),
+        ];
+
+        // Soft sphere: the vertices of one cloth in the first group, the
+        // sphere's own buffers in the second
+        let soft_layout = context.device().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Soft Sphere Cloth Bind Group Layout"),
+            entries: &[compute_buffer_entry(0, wgpu::BufferBindingType::Storage { read_only: false })],
+        });
+        let soft_sphere_layout = context.device().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Soft Sphere Bind Group Layout"),
+            entries: &[
+                compute_buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+                compute_buffer_entry(3, wgpu::BufferBindingType::Storage { read_only: true }),
+                compute_buffer_entry(4, wgpu::BufferBindingType::Storage { read_only: true }),
+                compute_buffer_entry(5, wgpu::BufferBindingType::Storage { read_only: true }),
+                compute_buffer_entry(6, wgpu::BufferBindingType::Uniform),
+            ],
+        });
+        let soft_pipeline_layout = context.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Soft Sphere Pipeline Layout"),
+            bind_group_layouts: &[&soft_layout, &soft_sphere_layout],
+            push_constant_ranges: &[],
+        });
+        let soft_pipelines = [
+            create_compute_pipeline(context, &soft_pipeline_layout, &compute_shader, "cs_soft_step", "Soft Sphere Step Pipeline"),
+            create_compute_pipeline(context, &soft_pipeline_layout, &compute_shader, "cs_soft_push_cloth", "Soft Sphere Push Pipeline"),
+        ];
+
+        // Selection operations: per-vertex weights (one buffer per cloth) plus the operation to apply
+        let selection_op_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Selection Op Buffer"),
+            size: std::mem::size_of::<SelectionOp>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let selection_bind_group_layout = context.device().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Selection Bind Group Layout"),
+            entries: &[
+                compute_buffer_entry(0, wgpu::BufferBindingType::Storage { read_only: true }),
+                compute_buffer_entry(1, wgpu::BufferBindingType::Uniform),
+            ],
+        });
+
+        let selection_pipeline_layout = context.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Selection Pipeline Layout"),
+            bind_group_layouts: &[&compute_bind_group_layout, &selection_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let selection_pipeline = create_compute_pipeline(context, &selection_pipeline_layout, &compute_shader, "cs_apply_selection", "Selection Pipeline");
+
+        // Create render pipelines: the sphere always uses the f32 layout, the fabric follows `precision`
+        // The unit sphere is scaled and moved per instance to every collider
+        let collider_style = RenderStyle {
+            vertex_entry: "vs_collider",
+            ..RenderStyle::OPAQUE
+        };
+        let render_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[Vertex::desc(), GpuCollider::desc()], collider_style, "Render Pipeline");
+        let fabric_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], RenderStyle::OPAQUE, "Fabric Pipeline");
+        // The fabric's front faces are the ones seen from +y in its local frame
+        let culled_style = RenderStyle {
+            cull_mode: Some(wgpu::Face::Back),
+            ..RenderStyle::OPAQUE
+        };
+        let fabric_culled_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], culled_style, "Fabric Culled Pipeline");
+        let prepass_pipelines = DepthPrepassPipelines {
+            sphere_depth: create_render_pipeline(context, &pipeline_layout, &shader, &[Vertex::desc(), GpuCollider::desc()], collider_style.depth_only(), "Sphere Depth Pipeline"),
+            fabric_depth: create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], RenderStyle::OPAQUE.depth_only(), "Fabric Depth Pipeline"),
+            fabric_culled_depth: create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], culled_style.depth_only(), "Fabric Culled Depth Pipeline"),
+            sphere: create_render_pipeline(context, &pipeline_layout, &shader, &[Vertex::desc(), GpuCollider::desc()], collider_style.depth_equal(), "Sphere Depth Equal Pipeline"),
+            fabric: create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], RenderStyle::OPAQUE.depth_equal(), "Fabric Depth Equal Pipeline"),
+            fabric_culled: create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], culled_style.depth_equal(), "Fabric Culled Depth Equal Pipeline"),
+        };
+        let wireframe_pipeline = context
+            .device()
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+            .then(|| create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], RenderStyle::WIREFRAME, "Wireframe Pipeline"));
+        let lines_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], RenderStyle::LINES, "Fabric Lines Pipeline");
+        let points_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], RenderStyle::POINTS, "Fabric Points Pipeline");
+        let smear_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], RenderStyle::SMEAR, "Motion Blur Pipeline");
+        let edge_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[precision.vertex_desc()], RenderStyle::EDGES, "Edge Pipeline");
+        let spring_pipeline = create_render_pipeline(
+            context,
+            &pipeline_layout,
+            &shader,
+            &[precision.spring_end_desc(0), precision.spring_end_desc(1)],
+            RenderStyle::SPRINGS,
+            "Spring Pipeline",
+        );
+        let force_pipeline = precision
+            .force_glyph_desc()
+            .map(|desc| create_render_pipeline(context, &pipeline_layout, &shader, &[desc], RenderStyle::FORCES, "Force Glyph Pipeline"));
+        let background_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[], RenderStyle::BACKGROUND, "Background Pipeline");
+        let ground_pipeline = create_render_pipeline(context, &pipeline_layout, &shader, &[Vertex::desc()], RenderStyle::OPAQUE, "Ground Pipeline");
+        let ground_vertex_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Ground Vertex Buffer"),
+            size: (6 * std::mem::size_of::<Vertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Camera setup
+        let lens = Lens {
+            fovy: 45.0,
+            aspect: context.size().x / context.size().y,
+            near: 0.5,
+            far: 100.0,
+        };
+        let mut camera = OrbitCamera::new(context, lens.fovy, lens.aspect, lens.near, lens.far);
+        camera.set_radius(DEFAULT_CAMERA_RADIUS).update(context);
+        let camera_home = (camera.radius(), camera.longitude(), camera.latitude());
+        let ortho_camera_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Orthographic Camera Buffer"),
+            size: std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let ortho_camera_bind_group = context.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Orthographic Camera Bind Group"),
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: ortho_camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // Detail normal map, tiled over the fabric when enabled
+        let detail_view = create_detail_texture(context, DETAIL_MAP_SIZE, DETAIL_MAP_SIZE, &detail_map::weave_normal_map(DETAIL_MAP_SIZE, 4));
+        let detail_sampler = context.device().create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Detail Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        // Fabric texture, a checkerboard until replaced with `set_texture`
+        let fabric_texture_view = create_fabric_texture(
+            context,
+            FABRIC_TEXTURE_SIZE,
+            FABRIC_TEXTURE_SIZE,
+            &detail_map::checkerboard(FABRIC_TEXTURE_SIZE, 16),
+        );
+
+        // Wind flow field, calm until a texture is loaded with `set_flow_texture`
+        let flow_view = create_flow_texture(context, 1, 1, &[[0.0; 3]]);
+        let flow_sampler = context.device().create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Flow Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let collider_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Collider Buffer"),
+            size: (MAX_COLLIDERS * std::mem::size_of::<GpuCollider>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let cloth_bindings = ClothBindings {
+            compute_layout: compute_bind_group_layout,
+            selection_layout: selection_bind_group_layout,
+            selection_op_buffer,
+            material_layout: material_bind_group_layout,
+            detail_view,
+            detail_sampler,
+            fabric_texture_view,
+            flow_view,
+            flow_sampler,
+            collider_buffer,
+            soft_layout,
+        };
+        let cloth = Cloth::new(context, precision, &cloth_bindings, builder.cloth_desc());
+
+        // The sphere never uses the detail map, its tint follows the render preset
+        let sphere_material_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sphere Material Buffer"),
+            size: std::mem::size_of::<FabricMaterial>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let background_material_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Background Material Buffer"),
+            size: std::mem::size_of::<FabricMaterial>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let ground_material_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Ground Material Buffer"),
+            size: std::mem::size_of::<FabricMaterial>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sphere_debug_color_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sphere Debug Color Buffer"),
+            size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let sphere_material_bind_group = cloth_bindings.material_bind_group(context, &sphere_material_buffer, &sphere_debug_color_buffer);
+        let background_material_bind_group = cloth_bindings.material_bind_group(context, &background_material_buffer, &sphere_debug_color_buffer);
+        let ground_material_bind_group = cloth_bindings.material_bind_group(context, &ground_material_buffer, &sphere_debug_color_buffer);
+
+        let num_sphere_indices = collider_mesh.indices.len() as u32;
+
+        InstanceApp {
+            sphere_vertex_buffer,
+            sphere_index_buffer,
+            render_pipeline,
+            fabric_pipeline,
+            fabric_culled_pipeline,
+            prepass_pipelines,
+            wireframe_pipeline,
+            lines_pipeline,
+            points_pipeline,
+            fabric_view: FabricView::Solid,
+            smear_pipeline,
+            edge_pipeline,
+            spring_pipeline,
+            force_pipeline,
+            force_glyphs: false,
+            background_pipeline,
+            compute_pipeline,
+            collide_pipeline,
+            stats_pipeline,
+            selection_pipeline,
+            heatmap_pipeline,
+            normals_pipeline,
+            tear_pipeline,
+            self_collision_pipelines,
+            verlet_velocity_pipeline,
+            hold_pins_pipeline,
+            jacobi_pipelines,
+            red_black_pipelines,
+            soft_pipelines,
+            soft_sphere_layout,
+            soft_sphere: None,
+            num_sphere_indices,
+            colliders: builder.colliders.clone(),
+            colliders_dirty: true,
+            sphere_motion: None,
+            ground_plane: None,
+            ground_pipeline,
+            ground_vertex_buffer,
+            ground_dirty: false,
+            wind: [0.0; 4],
+            wind_direction: [1.0, 0.0, 0.0],
+            elapsed_time: 0.0,
+            simulated_frames: 0,
+            sphere_material_buffer,
+            sphere_debug_color_buffer,
+            sphere_material_bind_group,
+            background_material_buffer,
+            background_material_bind_group,
+            ground_material_buffer,
+            ground_material_bind_group,
+            camera,
+            camera_home,
+            lens,
+            last_size: context.size(),
+            lens_dirty: false,
+            orthographic: false,
+            ortho_camera_buffer,
+            ortho_camera_bind_group,
+            cloth_bindings,
+            cloths: vec![cloth],
+            frame_parity: false,
+            gravity_direction: [0.0, -1.0, 0.0],
+            precision,
+            workgroup_layout,
+            pending_selection_ops: Vec::new(),
+            profiler: Profiler::new(context),
+            profiling: false,
+            show_vertex_ids: false,
+            frame_times: FrameTimes::new(FRAME_TIME_SAMPLES),
+            show_frame_overlay: true,
+            time_step: builder.time_step,
+            max_substep_dt: 0.0,
+            fixed_step: 0.0,
+            step_accumulator: 0.0,
+            time_scale: 1.0,
+            auto_stabilize: false,
+            energy_growth_limit: DEFAULT_ENERGY_GROWTH_LIMIT,
+            last_kinetic_energy: 0.0,
+            stabilize_frames_left: 0,
+            settle_speed: DEFAULT_SETTLE_SPEED,
+            settle_frames: DEFAULT_SETTLE_FRAMES,
+            settled_frames: 0,
+            iterations: 1,
+            pending_gust: None,
+            frame_time: builder.time_step,
+            substeps: 1,
+            log_substeps: true,
+            integration_method: IntegrationMethod::Euler,
+            constraint_solver: ConstraintSolver::RedBlack,
+            constraint_iterations: builder.constraint_iterations,
+            lighting: true,
+            detail_enabled: false,
+            textured: false,
+            detail_tiling: 32.0,
+            detail_strength: 1.0,
+            backface_culling: false,
+            depth_prepass: false,
+            nudged_corner: 0,
+            motion_blur: false,
+            motion_blur_time: 0.05,
+            motion_blur_opacity: 0.4,
+            render_preset: RenderPreset::Standard,
+            edge_overlay: false,
+            edge_color: BLUEPRINT_EDGE,
+            render_springs: false,
+            render_shear_springs: true,
+            fill_tint: None,
+            backface_tint: Some(DEFAULT_BACKFACE_TINT),
+            background: None,
+            materials_dirty: true,
+            divergence: None,
+            divergence_start_frame: 0,
+            pin_animations: Vec::new(),
+            validation: None,
+            stages: StageToggles::default(),
+            self_collision_radius: DEFAULT_SELF_COLLISION_RADIUS,
+            self_collision: false,
+            color_mode: ColorMode::Material,
+            heatmap_range: [0.0, 1.0],
+            flow_scroll: [0.1, 0.0],
+            flow_path: String::new(),
+            flow_status: String::new(),
+            relax_frames_left: builder.relax_frames,
+            builder,
+            ruler_enabled: false,
+            grab_enabled: false,
+            grab: None,
+            ruler: Ruler::default(),
+            pointer_over_ui: false,
+            keyboard_over_ui: false,
+            paused: false,
+            step_once: false,
+        }
+    }
+}
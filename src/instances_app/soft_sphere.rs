@@ -0,0 +1,205 @@
+use super::{collider_vertex, Cloth};
+use crate::gpu_context::Context;
+use crate::soft_body::{self, EdgeAdjacency};
+use crate::vertex::Vertex;
+use wgpu_bootstrap::{
+    cgmath,
+    util::geometry::icosphere,
+    wgpu::{self, util::DeviceExt},
+};
+use cgmath::InnerSpace;
+
+// Icosphere of the soft sphere (642 vertices), its vertex mass, the stiffness
+// of the springs along its edges and to the rest shape, and its velocity
+// damping per second
+const SOFT_SPHERE_SUBDIVISIONS: u32 = 3;
+const SOFT_VERTEX_MASS: f32 = 0.05;
+const SOFT_EDGE_STIFFNESS: f32 = 40.0;
+const SOFT_CORE_STIFFNESS: f32 = 30.0;
+const SOFT_DAMPING: f32 = 2.0;
+// The soft sphere is pushed in by cloth vertices closer than this many contact
+// radii, with this stiffness, so a cloth resting on it dents it
+const SOFT_CONTACT_REACH: f32 = 1.5;
+const SOFT_CONTACT_STIFFNESS: f32 = 3.0;
+
+// Layout of SoftParams in computeShader.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct SoftParams {
+    center: [f32; 4],     // anchor of the core springs xyz, unused  16 bytes
+    gravity_dt: [f32; 4], // gravity xyz, dt of a substep  16 bytes
+    springs: [f32; 4],    // edge stiffness, core stiffness, damping, unused  16 bytes
+    contact: [f32; 4],    // push radius, reach, stiffness, unused  16 bytes
+}
+
+// Layout of SoftNode in computeShader.wgsl, padded to its 16 byte alignment
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SoftNode {
+    rest: [f32; 4],
+    springs: [u32; 2],
+    padding: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SoftSpring {
+    neighbor: u32,
+    rest_length: f32,
+}
+
+// Ball of springs standing in for the first collider, see `InstanceApp::set_soft_sphere`
+pub(super) struct SoftSphere {
+    // Full precision vertices, stepped from one buffer to the other
+    vertex_buffers: [wgpu::Buffer; 2],
+    pub(super) index_buffer: wgpu::Buffer,
+    pub(super) index_count: u32,
+    params_buffer: wgpu::Buffer,
+    // Indexed by the parity of the state they read, like the cloth's
+    bind_groups: [wgpu::BindGroup; 2],
+    parity: bool,
+    initial_vertices: Vec<Vertex>,
+    // Distance the cloth is kept from the sphere's vertices, the longest edge
+    // so the cloth can't slip between them
+    contact_radius: f32,
+}
+
+impl SoftSphere {
+    pub(super) fn new(context: &Context, layout: &wgpu::BindGroupLayout, center: cgmath::Vector3<f32>, radius: f32) -> Self {
+        let (positions, indices) = icosphere(SOFT_SPHERE_SUBDIVISIONS);
+        let (directions, indices) = soft_body::weld(&positions, &indices, 1e-5);
+        let adjacency = EdgeAdjacency::from_triangles(directions.len(), &indices);
+        let rest: Vec<cgmath::Vector3<f32>> = directions.iter().map(|direction| direction * radius).collect();
+
+        let nodes: Vec<SoftNode> = rest
+            .iter()
+            .enumerate()
+            .map(|(vertex, offset)| SoftNode {
+                rest: [offset.x, offset.y, offset.z, 0.0],
+                springs: [adjacency.offsets[vertex], adjacency.neighbors(vertex).len() as u32],
+                padding: [0; 2],
+            })
+            .collect();
+        let springs: Vec<SoftSpring> = (0..rest.len())
+            .flat_map(|vertex| {
+                let rest = &rest;
+                adjacency.neighbors(vertex).iter().map(move |&neighbor| SoftSpring {
+                    neighbor,
+                    rest_length: (rest[vertex] - rest[neighbor as usize]).magnitude(),
+                })
+            })
+            .collect();
+        let initial_vertices: Vec<Vertex> = rest
+            .iter()
+            .zip(&directions)
+            .map(|(offset, direction)| Vertex {
+                mass: SOFT_VERTEX_MASS,
+                fixed: 0.0,
+                ..collider_vertex(center + offset, *direction)
+            })
+            .collect();
+
+        let vertex_buffers = ["Soft Sphere Vertex Buffer A", "Soft Sphere Vertex Buffer B"].map(|label| {
+            context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(&initial_vertices),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            })
+        });
+        let index_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Soft Sphere Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let node_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Soft Sphere Node Buffer"),
+            contents: bytemuck::cast_slice(&nodes),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let spring_buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Soft Sphere Spring Buffer"),
+            contents: bytemuck::cast_slice(&springs),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let params_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Soft Sphere Params Buffer"),
+            size: std::mem::size_of::<SoftParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_groups = [0, 1].map(|previous| {
+            let buffers = [&vertex_buffers[1 - previous], &vertex_buffers[previous], &node_buffer, &spring_buffer, &params_buffer];
+            let entries: Vec<wgpu::BindGroupEntry> = buffers
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                    binding: binding as u32 + 2,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect();
+            context.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Soft Sphere Bind Group"),
+                layout,
+                entries: &entries,
+            })
+        });
+
+        SoftSphere {
+            vertex_buffers,
+            index_buffer,
+            index_count: indices.len() as u32,
+            params_buffer,
+            bind_groups,
+            parity: false,
+            initial_vertices,
+            contact_radius: soft_body::longest_edge(&rest, &adjacency),
+        }
+    }
+
+    fn vertex_count(&self) -> u32 {
+        self.initial_vertices.len() as u32
+    }
+
+    // The state the next step reads, drawn in its place
+    pub(super) fn vertex_buffer(&self) -> &wgpu::Buffer {
+        &self.vertex_buffers[self.parity as usize]
+    }
+
+    pub(super) fn upload_params(&self, context: &Context, center: cgmath::Vector3<f32>, gravity: [f32; 3], dt: f32) {
+        let params = SoftParams {
+            center: [center.x, center.y, center.z, 0.0],
+            gravity_dt: [gravity[0], gravity[1], gravity[2], dt],
+            springs: [SOFT_EDGE_STIFFNESS, SOFT_CORE_STIFFNESS, SOFT_DAMPING, 0.0],
+            contact: [self.contact_radius, self.contact_radius * SOFT_CONTACT_REACH, SOFT_CONTACT_STIFFNESS, 0.0],
+        };
+        context.queue().write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+
+    pub(super) fn restore_initial_vertices(&self, context: &Context) {
+        for buffer in &self.vertex_buffers {
+            context.queue().write_buffer(buffer, 0, bytemuck::cast_slice(&self.initial_vertices));
+        }
+    }
+
+    // One substep: the sphere feels the first cloth, then every cloth is
+    // pushed out of it. `cloth_parity` is the cloths' current state.
+    pub(super) fn step(&mut self, encoder: &mut wgpu::CommandEncoder, pipelines: &[wgpu::ComputePipeline; 2], cloths: &[Cloth], cloth_parity: bool) {
+        let Some(primary) = cloths.first() else {
+            return;
+        };
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Soft Sphere Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_bind_group(1, &self.bind_groups[self.parity as usize], &[]);
+        compute_pass.set_pipeline(&pipelines[0]);
+        compute_pass.set_bind_group(0, primary.soft_bind_group(cloth_parity), &[]);
+        compute_pass.dispatch_workgroups(self.vertex_count().div_ceil(256), 1, 1);
+        compute_pass.set_pipeline(&pipelines[1]);
+        for cloth in cloths {
+            compute_pass.set_bind_group(0, cloth.soft_bind_group(cloth_parity), &[]);
+            compute_pass.dispatch_workgroups(cloth.thread_group_count(), 1, 1);
+        }
+        self.parity = !self.parity;
+    }
+}
@@ -0,0 +1,363 @@
+use super::{
+    ConstraintSolver, InstanceApp, IntegrationMethod, SelectionOp, SimStats, MAX_SUBSTEPS, STABILIZE_DAMPING, STABILIZE_FRAMES, STABILIZE_MIN_ENERGY,
+};
+use crate::gpu_context::Context;
+use crate::param_bounds::ParamBounds;
+use crate::selection::Selection;
+use wgpu_bootstrap::{cgmath, wgpu};
+
+impl InstanceApp {
+    // Operations are applied in order at the start of the next update, always
+    // to the first cloth: the select_* methods build selections over its grid.
+    // The weights overwrite the selection buffer from the start, so a shorter
+    // selection would leave the previous operation's weights in the tail and a
+    // longer one overflow it.
+    pub(super) fn queue_selection_op(&mut self, selection: &Selection, op: SelectionOp) {
+        let vertex_count = self.primary().vertex_count();
+        assert_eq!(
+            selection.weights().len(),
+            vertex_count,
+            "selection has {} weights, the first cloth has {} vertices",
+            selection.weights().len(),
+            vertex_count
+        );
+        if !selection.is_empty() {
+            self.pending_selection_ops.push((selection.clone(), op));
+        }
+    }
+
+    fn apply_selection_ops(&mut self, context: &Context) {
+        let cloth = &self.cloths[0];
+        // One submission per operation so each one sees its own weights and op uniform
+        for (selection, op) in self.pending_selection_ops.drain(..) {
+            context.queue().write_buffer(&cloth.selection_buffer, 0, bytemuck::cast_slice(selection.weights()));
+            context.queue().write_buffer(&self.cloth_bindings.selection_op_buffer, 0, bytemuck::cast_slice(&[op]));
+
+            let mut encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Selection Encoder"),
+            });
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Selection Pass"),
+                    timestamp_writes: None,
+                });
+                compute_pass.set_pipeline(&self.selection_pipeline);
+                compute_pass.set_bind_group(0, cloth.compute_bind_group(self.frame_parity), &[]);
+                compute_pass.set_bind_group(1, &cloth.selection_bind_group, &[]);
+                cloth.dispatch_vertices(&mut compute_pass, self.workgroup_layout);
+            }
+            context.queue().submit(Some(encoder.finish()));
+        }
+    }
+
+    // Compares the total kinetic energy of freshly arrived stats with the
+    // previous ones and starts the stabilize boost on a spike
+    fn check_energy(&mut self) {
+        let energy = self.last_stats().kinetic_energy;
+        let spiked = !energy.is_finite() || (energy > STABILIZE_MIN_ENERGY && energy > self.last_kinetic_energy * self.energy_growth_limit);
+        if self.auto_stabilize && spiked {
+            self.stabilize_frames_left = STABILIZE_FRAMES;
+        }
+        self.last_kinetic_energy = energy;
+    }
+
+    // Counts the stats readbacks in a row whose max speed stays below the
+    // settle speed, a faster one starts over
+    fn check_settled(&mut self) {
+        let max_speed = self.max_speed();
+        self.settled_frames = if max_speed < self.settle_speed { self.settled_frames.saturating_add(1) } else { 0 };
+    }
+
+    // The stabilize boost goes into the unused damping slot of every cloth
+    // while frames of it are left, and counts down with the simulated frames
+    fn sync_stabilize(&mut self, stepping: bool) {
+        let boost = if self.stabilize_frames_left > 0 { STABILIZE_DAMPING } else { 0.0 };
+        for cloth in self.cloths.iter_mut().filter(|cloth| cloth.sim_params2.damping[2] != boost) {
+            cloth.sim_params2.damping[2] = boost;
+            cloth.params_dirty = true;
+        }
+        if stepping {
+            self.stabilize_frames_left = self.stabilize_frames_left.saturating_sub(1);
+        }
+    }
+
+    // The gust becomes an acceleration over this frame's simulated time, and
+    // is cleared again on the next one
+    fn sync_gust(&mut self, stepping: bool) {
+        let gust = if stepping { self.pending_gust.take() } else { None };
+        let acceleration = gust.map_or([0.0; 4], |velocity| {
+            let velocity = velocity / self.frame_time;
+            [velocity.x, velocity.y, velocity.z, 0.0]
+        });
+        for cloth in self.cloths.iter_mut().filter(|cloth| cloth.sim_params2.gust != acceleration) {
+            cloth.sim_params2.gust = acceleration;
+            cloth.params_dirty = true;
+        }
+    }
+
+    // Whole fixed steps the accumulated real time covers, the rest carries over
+    // to the next frame. A longer backlog than MAX_SUBSTEPS steps is dropped. A
+    // single step while paused runs one step and accumulates nothing.
+    fn take_fixed_steps(&mut self, delta_time: f32) -> u32 {
+        if self.paused {
+            self.step_accumulator = 0.0;
+            return 1;
+        }
+        self.step_accumulator += delta_time;
+        let steps = (self.step_accumulator / self.fixed_step).floor() as u32;
+        if steps > MAX_SUBSTEPS {
+            self.step_accumulator = 0.0;
+            return MAX_SUBSTEPS;
+        }
+        self.step_accumulator -= steps as f32 * self.fixed_step;
+        steps
+    }
+
+    // All cloths share the substep count so they stay in sync. The frame time
+    // is split further when a velocity spike needs smaller steps. With a fixed
+    // step every step is split the same way, whatever the number of steps.
+    fn update_substeps(&mut self, delta_time: f32, fixed_steps: u32) {
+        let (steps, rate_substeps) = if self.fixed_step > 0.0 {
+            let steps = fixed_steps.max(1);
+            self.frame_time = self.fixed_step * steps as f32;
+            (steps, 1)
+        } else if self.max_substep_dt > 0.0 {
+            let frame_limit = self.max_substep_dt * MAX_SUBSTEPS as f32;
+            self.frame_time = delta_time.clamp(ParamBounds::TIME_STEP.min, frame_limit);
+            (1, ((self.frame_time / self.max_substep_dt).ceil() as u32).clamp(1, MAX_SUBSTEPS))
+        } else {
+            // Sped up, the substeps stay within the time step
+            self.frame_time = self.time_step * self.time_scale;
+            (1, (self.time_scale.ceil() as u32).clamp(1, MAX_SUBSTEPS))
+        };
+
+        let step_time = self.frame_time / steps as f32;
+        let rate_substeps = rate_substeps.max(self.iterations);
+        let spike_substeps = self.cloths.iter().map(|cloth| cloth.substeps_for_spike(step_time)).max().unwrap_or(1);
+        let substeps = spike_substeps.max(rate_substeps) * steps;
+        if spike_substeps > rate_substeps && substeps != self.substeps && self.log_substeps {
+            let max_speed = self.max_speed();
+            eprintln!(
+                "warning: max speed {:.2} would move a vertex {:.3} in one step, using {} substeps",
+                max_speed,
+                max_speed * step_time / rate_substeps as f32,
+                substeps
+            );
+        }
+        self.substeps = substeps;
+
+        // Every substep runs with the same dt, uploaded once before the frame's dispatches
+        let step = self.frame_time / substeps as f32;
+        for cloth in self.cloths.iter_mut().filter(|cloth| cloth.sim_params2.time[0] != step) {
+            cloth.sim_params2.time[0] = step;
+            cloth.params_dirty = true;
+        }
+    }
+
+    /// Runs one frame of `delta_time` real seconds: picks up the statistics
+    /// the GPU finished, steps the simulation (unless paused) and submits the
+    /// compute passes. `App::update` calls it every frame, call it directly to
+    /// drive the simulation without a window, e.g. on a `HeadlessContext`.
+    pub fn advance(&mut self, delta_time: f32, context: &Context) {
+        if context.size() != self.last_size {
+            self.resize(context);
+        }
+        if self.lens_dirty {
+            self.rebuild_camera(context);
+        }
+        self.frame_times.push(delta_time);
+        let mut stats_arrived = false;
+        for cloth in &mut self.cloths {
+            if let Some(bytes) = cloth.stats_readback.try_read(context) {
+                cloth.stats = SimStats::from_bytes(&bytes);
+                stats_arrived = true;
+            }
+        }
+        if stats_arrived {
+            self.check_energy();
+            self.check_settled();
+        }
+        // While paused only a requested single step advances the simulation,
+        // and with a fixed step only once a whole step of real time went by
+        let mut stepping = !self.paused || self.step_once;
+        self.step_once = false;
+        let sim_delta_time = delta_time * self.time_scale;
+        let fixed_steps = if stepping && self.fixed_step > 0.0 { self.take_fixed_steps(sim_delta_time) } else { 0 };
+        stepping &= self.fixed_step == 0.0 || fixed_steps > 0;
+        self.update_substeps(sim_delta_time, fixed_steps);
+        if stepping {
+            self.elapsed_time += self.frame_time;
+            self.simulated_frames += 1;
+            self.advance_flow();
+            self.advance_sphere_motion(self.frame_time);
+        }
+        self.sync_debug_options();
+        self.sync_gust(stepping);
+        self.sync_stabilize(stepping);
+        for cloth in &mut self.cloths {
+            cloth.upload_params(context);
+        }
+        self.upload_soft_sphere(context);
+        self.upload_materials(context);
+        self.upload_colliders(context);
+        self.upload_ground(context);
+        if self.orthographic {
+            self.upload_ortho_camera(context);
+        }
+        self.apply_selection_ops(context);
+        if stepping {
+            self.advance_pin_animations(context);
+        }
+        self.advance_grab(context);
+
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.begin_frame(context, self.profiling);
+        }
+        for cloth in &mut self.cloths {
+            if let Some(bytes) = cloth.position_readback.try_read(context) {
+                let vertices = self.precision.vertices_from_bytes(&bytes);
+                cloth.positions = vertices.iter().map(|v| cgmath::Vector3::new(v.position[0], v.position[1], v.position[2])).collect();
+                cloth.fixed = vertices.iter().map(|v| v.fixed > 0.5).collect();
+                cloth.positions_frame = cloth.position_readback_frame;
+            }
+        }
+        self.record_divergence();
+
+        let mut encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Compute Encoder"),
+        });
+
+        // One compute pass per stage so each can be timed on its own, repeated
+        // for each substep when a velocity spike was detected. Every cloth is
+        // stepped in the same pass.
+        // The integration stage reads the current state and writes the other
+        // buffer, the later stages update that one in place.
+        let mut stages = vec![("Forces & integration", vec![&self.compute_pipeline], true)];
+        if self.stages.constraints && self.constraint_iterations > 0 {
+            // Two dispatches per iteration: project and apply, or red then black
+            let iteration = match self.constraint_solver {
+                ConstraintSolver::Jacobi => &self.jacobi_pipelines,
+                ConstraintSolver::RedBlack => &self.red_black_pipelines,
+            };
+            let iterations = self.constraint_iterations as usize;
+            stages.push(("Constraints", iteration.iter().cycle().take(iteration.len() * iterations).collect(), false));
+        }
+        if self.self_collision {
+            // The pushes land in the Jacobi scratch buffer and are applied the same way
+            let mut pipelines: Vec<_> = self.self_collision_pipelines.iter().collect();
+            pipelines.push(&self.jacobi_pipelines[1]);
+            stages.push(("Self collision", pipelines, false));
+        }
+        if self.stages.collision {
+            stages.push(("Collision", vec![&self.collide_pipeline], false));
+        }
+        if self.integration_method == IntegrationMethod::Verlet {
+            stages.push(("Verlet velocity", vec![&self.verlet_velocity_pipeline], false));
+        }
+        if self.stages.pins {
+            stages.push(("Pins", vec![&self.hold_pins_pipeline], false));
+        }
+        let substeps = if stepping { self.substeps as usize } else { 0 };
+        let mut parity = self.frame_parity;
+        for (index, (name, pipelines, swaps)) in stages.iter().cycle().take(stages.len() * substeps).enumerate() {
+            {
+                let timestamp_writes = self.profiler.as_mut().and_then(|profiler| profiler.stage(name));
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some(name),
+                    timestamp_writes,
+                });
+
+                for pipeline in pipelines {
+                    compute_pass.set_pipeline(pipeline);
+                    for cloth in &self.cloths {
+                        let bind_group = if *swaps { cloth.step_bind_group(parity) } else { cloth.compute_bind_group(parity) };
+                        compute_pass.set_bind_group(0, bind_group, &[]);
+                        cloth.dispatch_vertices(&mut compute_pass, self.workgroup_layout);
+                    }
+                }
+            }
+            if *swaps {
+                parity = !parity;
+            }
+            // The soft sphere steps once per substep, after the cloth stages
+            if index % stages.len() == stages.len() - 1 {
+                if let Some(soft_sphere) = self.soft_sphere.as_mut() {
+                    soft_sphere.step(&mut encoder, &self.soft_pipelines, &self.cloths, parity);
+                }
+            }
+        }
+        self.frame_parity = parity;
+
+        // Normals for the lighting, from the state at the end of the frame
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Normals Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.normals_pipeline);
+            for cloth in &self.cloths {
+                compute_pass.set_bind_group(0, cloth.compute_bind_group(self.frame_parity), &[]);
+                cloth.dispatch_vertices(&mut compute_pass, self.workgroup_layout);
+            }
+            // Triangles of the torn springs drop out of the index buffer
+            compute_pass.set_pipeline(&self.tear_pipeline);
+            for cloth in self.cloths.iter().filter(|cloth| cloth.tearing()) {
+                compute_pass.set_bind_group(0, cloth.compute_bind_group(self.frame_parity), &[]);
+                compute_pass.dispatch_workgroups(cloth.cell_group_count(), 1, 1);
+            }
+        }
+
+        // Heat colors from the state at the end of the frame
+        if self.color_mode.heatmap().is_some() {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Heatmap Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.heatmap_pipeline);
+            for cloth in &self.cloths {
+                compute_pass.set_bind_group(0, cloth.compute_bind_group(self.frame_parity), &[]);
+                cloth.dispatch_vertices(&mut compute_pass, self.workgroup_layout);
+            }
+        }
+
+        // Stats for `last_stats` and the next frame's substep decision, one reduction in flight per cloth
+        for cloth in self.cloths.iter_mut().filter(|cloth| cloth.stats_readback.is_idle()) {
+            encoder.clear_buffer(&cloth.stats_buffer, 0, None);
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Stats Pass"),
+                    timestamp_writes: None,
+                });
+                compute_pass.set_pipeline(&self.stats_pipeline);
+                compute_pass.set_bind_group(0, cloth.compute_bind_group(self.frame_parity), &[]);
+                cloth.dispatch_vertices(&mut compute_pass, self.workgroup_layout);
+            }
+            cloth.stats_readback.request(&mut encoder, &cloth.stats_buffer);
+        }
+        // A divergence run compares both cloths at the same frame, so their
+        // copies only go out together
+        let mirrored: Vec<bool> = self.cloths.iter().map(|cloth| self.mirrors_positions(cloth)).collect();
+        let paired = self.divergence.as_ref().is_some_and(|run| !run.is_done());
+        let all_idle = self.cloths.iter().all(|cloth| cloth.position_readback.is_idle());
+        for (cloth, _) in self.cloths.iter_mut().zip(mirrored).filter(|(_, mirrored)| *mirrored) {
+            if cloth.position_readback.is_idle() && (all_idle || !paired) {
+                cloth.position_readback_frame = self.simulated_frames;
+                cloth.position_readback.request(&mut encoder, &cloth.vertex_buffers[self.frame_parity as usize]);
+            }
+        }
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.end_frame(&mut encoder);
+        }
+        context.queue().submit(Some(encoder.finish()));
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.after_submit();
+        }
+        for cloth in &mut self.cloths {
+            cloth.position_readback.map();
+            cloth.stats_readback.map();
+        }
+        if stepping {
+            self.relax_frames_left = self.relax_frames_left.saturating_sub(1);
+        }
+    }
+}
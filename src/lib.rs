@@ -0,0 +1,24 @@
+pub mod cli_args;
+pub mod cpu_reference;
+pub mod detail_map;
+pub mod divergence;
+pub mod env_config;
+pub mod export;
+pub mod flow_map;
+pub mod frame_times;
+pub mod gpu_context;
+pub mod gravity_compass;
+pub mod heatmap;
+pub mod instances_app;
+pub mod orbit_camera;
+pub mod param_bounds;
+pub mod pin_animation;
+pub mod png;
+pub mod profiler;
+pub mod projection;
+pub mod readback;
+pub mod ruler;
+pub mod selection;
+pub mod sim_config;
+pub mod soft_body;
+pub mod vertex;
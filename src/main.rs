@@ -1,19 +1,44 @@
-mod instances_app;
-
 use std::sync::Arc;
 
-use crate::instances_app::InstanceApp;
-use wgpu_bootstrap::{egui, Runner};
+use cloth_sim::cli_args::{ArgsError, USAGE};
+use cloth_sim::instances_app::{ClothBuilder, CLEAR_COLOR};
+use wgpu_bootstrap::Runner;
 
 fn main() {
+    let builder = match ClothBuilder::new().from_env() {
+        Ok(builder) => builder,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+    // Command line options win over the environment
+    let builder = match builder.from_args(std::env::args().skip(1)) {
+        Ok(builder) => builder,
+        Err(ArgsError::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+
     let mut runner = Runner::new(
         "Fabric Simulation",
         800,
         600,
-        egui::Color32::from_rgb(255, 206, 27),
+        CLEAR_COLOR,
         32,
         0,
-        Box::new(|context| Arc::new(InstanceApp::new(context))),
+        Box::new(move |context| match builder.try_build(context) {
+            Ok(app) => Arc::new(app),
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(2);
+            }
+        }),
     );
     runner.run();
 }
@@ -0,0 +1,144 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::gpu_context::Context;
+use crate::projection::{self, Lens};
+use wgpu_bootstrap::{
+    egui,
+    wgpu::{self, util::DeviceExt},
+};
+
+// Radians of orbit per point of mouse drag
+const DRAG_SPEED: f32 = 0.01;
+// Latitude stays this far from the poles, where the up vector degenerates
+const POLE_MARGIN: f32 = 0.01;
+// Where a new camera starts, slightly above the horizon
+const DEFAULT_RADIUS: f32 = 7.0;
+const DEFAULT_LATITUDE: f32 = 0.4;
+
+/// View and projection matrices, `CameraUniform` in shader.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view: [[f32; 4]; 4],
+    pub proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    /// Layout of the bind group holding the uniform at binding 0.
+    pub fn desc() -> wgpu::BindGroupLayoutDescriptor<'static> {
+        wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        }
+    }
+}
+
+/// Perspective camera orbiting the origin, at `radius` from it and at the
+/// spherical angles `longitude` and `latitude` (see `projection::orbit_view`).
+/// Dragging with the primary button turns it. The setters only change the
+/// angles, `update` uploads them.
+pub struct OrbitCamera {
+    radius: f32,
+    longitude: f32,
+    latitude: f32,
+    lens: Lens,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl OrbitCamera {
+    pub fn new(context: &Context, fovy: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let lens = Lens { fovy, aspect, near, far };
+        let uniform = Self::uniform(DEFAULT_RADIUS, 0.0, DEFAULT_LATITUDE, lens);
+        let buffer = context.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let layout = context.device().create_bind_group_layout(&CameraUniform::desc());
+        let bind_group = context.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        OrbitCamera {
+            radius: DEFAULT_RADIUS,
+            longitude: 0.0,
+            latitude: DEFAULT_LATITUDE,
+            lens,
+            buffer,
+            bind_group,
+        }
+    }
+
+    fn uniform(radius: f32, longitude: f32, latitude: f32, lens: Lens) -> CameraUniform {
+        CameraUniform {
+            view: projection::orbit_view(radius, longitude, latitude).into(),
+            proj: projection::orbit_perspective(lens).into(),
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn longitude(&self) -> f32 {
+        self.longitude
+    }
+
+    pub fn latitude(&self) -> f32 {
+        self.latitude
+    }
+
+    pub fn set_radius(&mut self, radius: f32) -> &mut Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn set_longitude(&mut self, longitude: f32) -> &mut Self {
+        self.longitude = longitude;
+        self
+    }
+
+    /// Clamped just short of the poles.
+    pub fn set_latitude(&mut self, latitude: f32) -> &mut Self {
+        self.latitude = latitude.clamp(-FRAC_PI_2 + POLE_MARGIN, FRAC_PI_2 - POLE_MARGIN);
+        self
+    }
+
+    /// Uploads the view and projection for the current radius and angles.
+    pub fn update(&mut self, context: &Context) {
+        let uniform = Self::uniform(self.radius, self.longitude, self.latitude, self.lens);
+        context.queue().write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Orbits with a primary button drag: horizontal motion turns the
+    /// longitude, vertical motion the latitude.
+    pub fn input(&mut self, input: egui::InputState, context: &Context) {
+        if !input.pointer.primary_down() {
+            return;
+        }
+        let delta = input.pointer.delta();
+        if delta == egui::Vec2::ZERO {
+            return;
+        }
+        let (longitude, latitude) = (self.longitude + delta.x * DRAG_SPEED, self.latitude + delta.y * DRAG_SPEED);
+        self.set_longitude(longitude).set_latitude(latitude).update(context);
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
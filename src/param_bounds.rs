@@ -0,0 +1,90 @@
+use std::ops::RangeInclusive;
+
+use wgpu_bootstrap::egui;
+
+/// Range a tunable simulation parameter is kept in.
+///
+/// Values are clamped to `min..=max` before they reach the uniforms. Between
+/// `stable_max` and `max` the simulation usually holds but can blow up, e.g.
+/// when several stiff parameters are combined, so the sliders show a warning.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ParamBounds {
+    pub min: f32,
+    pub max: f32,
+    pub stable_max: f32,
+}
+
+impl ParamBounds {
+    /// Gravity magnitude
+    pub const GRAVITY: ParamBounds = ParamBounds { min: 0.0, max: 20.0, stable_max: 15.0 };
+    /// Velocity damping per second for vertices touching the sphere, above
+    /// 1 / dt the contact velocity is zeroed in a single step
+    pub const CONTACT_DAMPING: ParamBounds = ParamBounds { min: 0.0, max: 500.0, stable_max: 400.0 };
+    /// Spring stiffness, shared by the structural, shear and bending springs
+    pub const STIFFNESS: ParamBounds = ParamBounds { min: 0.0, max: 200.0, stable_max: 120.0 };
+    /// Force limit of a single spring, low values make the cloth stretchy
+    pub const MAX_SPRING_FORCE: ParamBounds = ParamBounds { min: 1.0, max: 500.0, stable_max: 250.0 };
+    /// Global velocity damping (`k_spring`)
+    pub const DAMPING: ParamBounds = ParamBounds { min: 0.0, max: 2.0, stable_max: 2.0 };
+    /// Velocity damping per second applied after the collision response, above
+    /// 1 / dt the cloth stops dead every substep
+    pub const SETTLE_DAMPING: ParamBounds = ParamBounds { min: 0.0, max: 100.0, stable_max: 100.0 };
+    /// Speed below which a vertex is stopped at the end of a substep, high
+    /// values freeze a cloth that is still falling slowly
+    pub const SLEEP_THRESHOLD: ParamBounds = ParamBounds { min: 0.0, max: 1.0, stable_max: 0.2 };
+    /// Spring rest length, zero collapses the springs
+    pub const REST_LENGTH: ParamBounds = ParamBounds { min: 0.01, max: 0.5, stable_max: 0.3 };
+    /// Vertex mass, zero or negative masses divide by zero in the integration
+    pub const MASS: ParamBounds = ParamBounds { min: 0.01, max: 10.0, stable_max: 10.0 };
+    /// Scale of the wind acceleration read from the flow texture
+    pub const FLOW_STRENGTH: ParamBounds = ParamBounds { min: 0.0, max: 30.0, stable_max: 20.0 };
+    /// Share of the sliding velocity the sphere takes away from a vertex in
+    /// contact, 1 makes the cloth stick where it lands
+    pub const FRICTION: ParamBounds = ParamBounds { min: 0.0, max: 1.0, stable_max: 1.0 };
+    /// Share of the speed into the surface a vertex keeps when it bounces off
+    /// the ground or a collision sphere
+    pub const RESTITUTION: ParamBounds = ParamBounds { min: 0.0, max: 1.0, stable_max: 1.0 };
+    /// Wind strength, the force on a vertex whose face is square to the wind
+    pub const WIND: ParamBounds = ParamBounds { min: 0.0, max: 30.0, stable_max: 20.0 };
+    /// Drag against the velocity relative to the wind, along the normal
+    pub const AIR_DRAG: ParamBounds = ParamBounds { min: 0.0, max: 10.0, stable_max: 5.0 };
+    /// Amplitude of the noise added to the wind
+    pub const TURBULENCE: ParamBounds = ParamBounds { min: 0.0, max: 20.0, stable_max: 15.0 };
+    /// Stretch factor past which a spring tears, 0 turns tearing off and values
+    /// close to 1 tear the cloth under its own weight
+    pub const TEAR_LENGTH: ParamBounds = ParamBounds { min: 0.0, max: 4.0, stable_max: 4.0 };
+    /// Distance self collision keeps fabric vertices apart, also the cell size
+    /// of its spatial hash
+    pub const SELF_COLLISION_RADIUS: ParamBounds = ParamBounds { min: 0.01, max: 0.3, stable_max: 0.3 };
+    /// Simulated seconds per frame, split into substeps when the cloth moves fast
+    pub const TIME_STEP: ParamBounds = ParamBounds { min: 0.0001, max: 0.01, stable_max: 0.004 };
+
+    pub fn clamp(&self, value: f32) -> f32 {
+        value.clamp(self.min, self.max)
+    }
+
+    pub fn range(&self) -> RangeInclusive<f32> {
+        self.min..=self.max
+    }
+
+    pub fn near_unstable(&self, value: f32) -> bool {
+        value > self.stable_max
+    }
+}
+
+/// Slider limited to `bounds`, with a warning sign next to it when the value
+/// gets close to the unstable edge.
+pub fn bounded_slider<'a>(value: &'a mut f32, bounds: ParamBounds, text: &'a str) -> impl egui::Widget + 'a {
+    move |ui: &mut egui::Ui| {
+        ui.horizontal(|ui| {
+            *value = bounds.clamp(*value);
+            let response = ui.add(egui::Slider::new(value, bounds.range()).text(text));
+            if bounds.near_unstable(*value) {
+                ui.colored_label(egui::Color32::from_rgb(200, 120, 0), "⚠")
+                    .on_hover_text(format!("Above {} the simulation may become unstable", bounds.stable_max));
+            }
+            response
+        })
+        .inner
+    }
+}
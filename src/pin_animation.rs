@@ -0,0 +1,65 @@
+use wgpu_bootstrap::cgmath::Vector3;
+
+/// Offset of the animated pins at `time` seconds into the track.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub offset: Vector3<f32>,
+}
+
+/// Keyframed path moving a group of pinned fabric vertices together.
+///
+/// The path is an offset from where the vertices were when the track started,
+/// interpolated linearly between keyframes. Before the first keyframe the
+/// offset is the first one's, after the last it holds the last one's unless
+/// the track loops.
+#[derive(Clone, Debug)]
+pub struct PinTrack {
+    pub vertices: Vec<u32>,
+    keyframes: Vec<Keyframe>,
+    looping: bool,
+}
+
+impl PinTrack {
+    pub fn new(vertices: Vec<u32>, mut keyframes: Vec<Keyframe>) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        PinTrack {
+            vertices,
+            keyframes,
+            looping: false,
+        }
+    }
+
+    /// Restarts the path after the last keyframe, `time` wrapping over the
+    /// duration of the track.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    pub fn offset_at(&self, time: f32) -> Vector3<f32> {
+        let (Some(first), Some(last)) = (self.keyframes.first(), self.keyframes.last()) else {
+            return Vector3::new(0.0, 0.0, 0.0);
+        };
+        let time = if self.looping && self.duration() > 0.0 { time.rem_euclid(self.duration()) } else { time };
+        if time <= first.time {
+            return first.offset;
+        }
+        if time >= last.time {
+            return last.offset;
+        }
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        let (a, b) = (self.keyframes[next - 1], self.keyframes[next]);
+        let t = (time - a.time) / (b.time - a.time);
+        a.offset + (b.offset - a.offset) * t
+    }
+}
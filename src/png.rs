@@ -0,0 +1,79 @@
+use std::io::{self, Write};
+
+/// 8-bit RGBA pixels, `width` x `height` stored row by row from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+// Largest payload of a stored (uncompressed) deflate block
+const MAX_STORED_BLOCK: usize = 65535;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let mut crc_input = kind.to_vec();
+    crc_input.extend_from_slice(data);
+    writer.write_all(&crc32(&crc_input).to_be_bytes())
+}
+
+/// Writes `image` as a PNG (truecolor with alpha, 8 bits per channel).
+///
+/// The pixels go into stored deflate blocks without compression, so the file is
+/// about the size of the raw pixels, but any PNG reader opens it.
+pub fn write_png<W: Write>(mut writer: W, image: &RgbaImage) -> io::Result<()> {
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width.to_be_bytes());
+    header.extend_from_slice(&image.height.to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), default compression and filters, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(&mut writer, b"IHDR", &header)?;
+
+    // Every scanline starts with filter type 0 (none)
+    let mut scanlines = Vec::with_capacity(image.pixels.len() * 4 + image.height as usize);
+    for row in image.pixels.chunks_exact(image.width.max(1) as usize) {
+        scanlines.push(0);
+        scanlines.extend(row.iter().flatten());
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = scanlines.chunks(MAX_STORED_BLOCK).peekable();
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        zlib.push(last as u8);
+        zlib.extend_from_slice(&length.to_le_bytes());
+        zlib.extend_from_slice(&(!length).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&scanlines).to_be_bytes());
+    write_chunk(&mut writer, b"IDAT", &zlib)?;
+
+    write_chunk(&mut writer, b"IEND", &[])?;
+    writer.flush()
+}
@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::gpu_context::Context;
+use wgpu_bootstrap::wgpu;
+
+// Enough for every compute pass of a frame, two queries per pass
+const MAX_STAGES: u32 = 16;
+
+/// Accumulated GPU time of one solver stage.
+#[derive(Clone, Debug)]
+pub struct StageTiming {
+    pub name: &'static str,
+    pub last_ms: f32,
+    pub total_ms: f64,
+    pub samples: u32,
+}
+
+impl StageTiming {
+    pub fn average_ms(&self) -> f32 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            (self.total_ms / self.samples as f64) as f32
+        }
+    }
+}
+
+/// Per-stage GPU timings of the compute passes.
+///
+/// Every stage is its own compute pass, bracketed by a pair of timestamp
+/// queries. The queries are resolved at the end of the frame and mapped
+/// asynchronously, so results show up one or more frames late; frames recorded
+/// while a readback is still in flight are simply not profiled.
+pub struct Profiler {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    timestamp_period: f32,
+    recording: bool,
+    frame_stages: Vec<&'static str>,
+    pending_stages: Vec<&'static str>,
+    readback_ready: Arc<AtomicBool>,
+    timings: Vec<StageTiming>,
+    last_frame_ms: Option<f32>,
+}
+
+impl Profiler {
+    /// Returns `None` when the device can't write timestamps (`TIMESTAMP_QUERY`).
+    pub fn new(context: &Context) -> Option<Self> {
+        if !context.device().features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_count = MAX_STAGES * 2;
+        let size = (query_count as usize * std::mem::size_of::<u64>()) as wgpu::BufferAddress;
+        let query_set = context.device().create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Profiler Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: query_count,
+        });
+        let resolve_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Profiler Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Profiler Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Some(Profiler {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            timestamp_period: context.queue().get_timestamp_period(),
+            recording: false,
+            frame_stages: Vec::new(),
+            pending_stages: Vec::new(),
+            readback_ready: Arc::new(AtomicBool::new(false)),
+            timings: Vec::new(),
+            last_frame_ms: None,
+        })
+    }
+
+    /// Collects finished results and decides whether this frame gets profiled.
+    pub fn begin_frame(&mut self, context: &Context, enabled: bool) {
+        if !self.pending_stages.is_empty() {
+            context.device().poll(wgpu::Maintain::Poll);
+            if self.readback_ready.swap(false, Ordering::AcqRel) {
+                self.collect();
+            }
+        }
+        self.frame_stages.clear();
+        self.recording = enabled && self.pending_stages.is_empty();
+    }
+
+    /// Timestamp writes for the compute pass of the stage `name`, if this frame is recorded.
+    pub fn stage(&mut self, name: &'static str) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        if !self.recording || self.frame_stages.len() as u32 >= MAX_STAGES {
+            return None;
+        }
+        let index = self.frame_stages.len() as u32;
+        self.frame_stages.push(name);
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index * 2),
+            end_of_pass_write_index: Some(index * 2 + 1),
+        })
+    }
+
+    /// Resolves the frame's queries, call before finishing the encoder.
+    pub fn end_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.recording || self.frame_stages.is_empty() {
+            return;
+        }
+        let query_count = self.frame_stages.len() as u32 * 2;
+        encoder.resolve_query_set(&self.query_set, 0..query_count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            query_count as wgpu::BufferAddress * std::mem::size_of::<u64>() as wgpu::BufferAddress,
+        );
+    }
+
+    /// Starts mapping the results, call after the encoder was submitted.
+    pub fn after_submit(&mut self) {
+        if !self.recording || self.frame_stages.is_empty() {
+            return;
+        }
+        self.recording = false;
+        self.pending_stages = std::mem::take(&mut self.frame_stages);
+        let ready = self.readback_ready.clone();
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            if result.is_ok() {
+                ready.store(true, Ordering::Release);
+            }
+        });
+    }
+
+    fn collect(&mut self) {
+        {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            // A stage dispatched several times in a frame counts as one sample
+            let mut frame: Vec<(&'static str, f32)> = Vec::new();
+            for (index, name) in self.pending_stages.iter().enumerate() {
+                let ticks = timestamps[index * 2 + 1].saturating_sub(timestamps[index * 2]);
+                let ms = ticks as f32 * self.timestamp_period / 1_000_000.0;
+                match frame.iter_mut().find(|(n, _)| n == name) {
+                    Some((_, total)) => *total += ms,
+                    None => frame.push((name, ms)),
+                }
+            }
+            self.last_frame_ms = Some(frame.iter().map(|(_, ms)| ms).sum());
+            for (name, ms) in frame {
+                match self.timings.iter_mut().find(|t| t.name == name) {
+                    Some(timing) => {
+                        timing.last_ms = ms;
+                        timing.total_ms += ms as f64;
+                        timing.samples += 1;
+                    }
+                    None => self.timings.push(StageTiming { name, last_ms: ms, total_ms: ms as f64, samples: 1 }),
+                }
+            }
+        }
+        self.readback_buffer.unmap();
+        self.pending_stages.clear();
+    }
+
+    /// Timings in the order the stages were first seen.
+    pub fn timings(&self) -> &[StageTiming] {
+        &self.timings
+    }
+
+    /// GPU time of all the stages of the last profiled frame.
+    pub fn last_frame_ms(&self) -> Option<f32> {
+        self.last_frame_ms
+    }
+
+    pub fn reset(&mut self) {
+        self.timings.clear();
+        self.last_frame_ms = None;
+    }
+}
@@ -0,0 +1,128 @@
+use wgpu_bootstrap::{
+    cgmath::{self, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4},
+    egui,
+};
+
+/// Perspective parameters handed to `OrbitCamera::new`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Lens {
+    pub fovy: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+/// View matrix of an orbit camera looking at the origin from the spherical
+/// coordinates (`radius`, `longitude`, `latitude`), the convention used by
+/// `OrbitCamera`.
+pub fn orbit_view(radius: f32, longitude: f32, latitude: f32) -> Matrix4<f32> {
+    let eye = Point3::new(
+        radius * latitude.cos() * longitude.cos(),
+        radius * latitude.sin(),
+        radius * latitude.cos() * longitude.sin(),
+    );
+    Matrix4::look_at_rh(eye, Point3::new(0.0, 0.0, 0.0), Vector3::unit_y())
+}
+
+/// Unit direction the orbit camera looks in, from its eye to the origin.
+pub fn orbit_forward(longitude: f32, latitude: f32) -> Vector3<f32> {
+    -Vector3::new(latitude.cos() * longitude.cos(), latitude.sin(), latitude.cos() * longitude.sin())
+}
+
+/// Projection the orbit camera uploads for `lens`.
+pub fn orbit_perspective(lens: Lens) -> Matrix4<f32> {
+    cgmath::perspective(cgmath::Deg(lens.fovy), lens.aspect, lens.near, lens.far)
+}
+
+/// View-projection of the orbit camera, see `orbit_view`. Mirrors what the
+/// camera uploads, for CPU side projection.
+pub fn orbit_view_projection(radius: f32, longitude: f32, latitude: f32, lens: Lens) -> Matrix4<f32> {
+    orbit_perspective(lens) * orbit_view(radius, longitude, latitude)
+}
+
+// cgmath projections map depth to -1..1, wgpu expects 0..1
+#[rustfmt::skip]
+const OPENGL_TO_WGPU: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+/// Orthographic projection framing the same height at the orbit center as the
+/// perspective `lens` does from `radius`, with wgpu's 0..1 depth range.
+pub fn orbit_orthographic(radius: f32, lens: Lens) -> Matrix4<f32> {
+    let half_height = radius * (lens.fovy.to_radians() / 2.0).tan();
+    let half_width = half_height * lens.aspect;
+    OPENGL_TO_WGPU * cgmath::ortho(-half_width, half_width, -half_height, half_height, lens.near, lens.far)
+}
+
+/// Screen position of a world space point inside `viewport`, `None` when it is
+/// behind the camera or outside the view.
+pub fn project_to_screen(view_projection: Matrix4<f32>, point: Vector3<f32>, viewport: egui::Rect) -> Option<egui::Pos2> {
+    let clip = view_projection * Vector4::new(point.x, point.y, point.z, 1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+        return None;
+    }
+    Some(egui::pos2(
+        viewport.min.x + (ndc.x + 1.0) * 0.5 * viewport.width(),
+        viewport.min.y + (1.0 - ndc.y) * 0.5 * viewport.height(),
+    ))
+}
+
+/// Half line from `origin` along the unit vector `direction`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Point where the ray crosses the horizontal plane at `height`, `None` when
+    /// it runs parallel to the plane or away from it.
+    pub fn plane_hit(&self, height: f32) -> Option<Vector3<f32>> {
+        if self.direction.y.abs() < 1e-6 {
+            return None;
+        }
+        let t = (height - self.origin.y) / self.direction.y;
+        (t > 0.0).then(|| self.origin + self.direction * t)
+    }
+
+    /// Index of the point of `points` closest to the ray, among those in front
+    /// of the origin and within `max_distance` of it.
+    pub fn nearest_point(&self, points: &[Vector3<f32>], max_distance: f32) -> Option<usize> {
+        points
+            .iter()
+            .enumerate()
+            .filter_map(|(index, point)| {
+                let offset = point - self.origin;
+                let along = offset.dot(self.direction);
+                let distance = (offset - self.direction * along).magnitude();
+                (along > 0.0 && distance <= max_distance).then_some((index, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Ray through the screen position `screen` inside `viewport`, the inverse of
+/// `project_to_screen`. `None` when `view_projection` is not invertible.
+pub fn screen_ray(view_projection: Matrix4<f32>, screen: egui::Pos2, viewport: egui::Rect) -> Option<Ray> {
+    let inverse = view_projection.invert()?;
+    let ndc_x = (screen.x - viewport.min.x) / viewport.width() * 2.0 - 1.0;
+    let ndc_y = 1.0 - (screen.y - viewport.min.y) / viewport.height() * 2.0;
+    let unproject = |depth: f32| {
+        let point = inverse * Vector4::new(ndc_x, ndc_y, depth, 1.0);
+        point.truncate() / point.w
+    };
+    let near = unproject(-1.0);
+    let far = unproject(1.0);
+    Some(Ray {
+        origin: near,
+        direction: (far - near).normalize(),
+    })
+}
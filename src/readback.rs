@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::gpu_context::Context;
+use wgpu_bootstrap::wgpu;
+
+/// Non-blocking copy of a GPU buffer to the CPU.
+///
+/// `request` records a copy into a staging buffer, `map` starts mapping it once
+/// the encoder was submitted, and `try_read` returns the bytes when the GPU is
+/// done, typically a frame or two later. Only one copy is in flight at a time.
+pub struct AsyncReadback {
+    buffer: wgpu::Buffer,
+    ready: Arc<AtomicBool>,
+    copied: bool,
+    mapping: bool,
+}
+
+impl AsyncReadback {
+    pub fn new(context: &Context, size: wgpu::BufferAddress, label: &str) -> Self {
+        let buffer = context.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        AsyncReadback {
+            buffer,
+            ready: Arc::new(AtomicBool::new(false)),
+            copied: false,
+            mapping: false,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        !self.copied && !self.mapping
+    }
+
+    /// Records a copy of `source` unless one is already in flight.
+    pub fn request(&mut self, encoder: &mut wgpu::CommandEncoder, source: &wgpu::Buffer) {
+        if !self.is_idle() {
+            return;
+        }
+        let size = self.buffer.size().min(source.size());
+        encoder.copy_buffer_to_buffer(source, 0, &self.buffer, 0, size);
+        self.copied = true;
+    }
+
+    /// Starts mapping a copy recorded by `request`, call after submitting the encoder.
+    pub fn map(&mut self) {
+        if !self.copied || self.mapping {
+            return;
+        }
+        self.mapping = true;
+        let ready = self.ready.clone();
+        self.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            if result.is_ok() {
+                ready.store(true, Ordering::Release);
+            }
+        });
+    }
+
+    /// The copied bytes, once the mapping finished.
+    pub fn try_read(&mut self, context: &Context) -> Option<Vec<u8>> {
+        if !self.mapping {
+            return None;
+        }
+        context.device().poll(wgpu::Maintain::Poll);
+        if !self.ready.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+        let bytes = self.buffer.slice(..).get_mapped_range().to_vec();
+        self.buffer.unmap();
+        self.copied = false;
+        self.mapping = false;
+        Some(bytes)
+    }
+}
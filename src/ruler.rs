@@ -0,0 +1,42 @@
+use wgpu_bootstrap::cgmath::{InnerSpace, Vector3};
+
+/// End of a measurement, either a fabric vertex, followed as the cloth moves,
+/// or a fixed point in the scene.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RulerPoint {
+    Vertex { cloth: usize, vertex: usize },
+    Fixed(Vector3<f32>),
+}
+
+/// Distance measurement between two clicked points. A third click starts a
+/// new measurement.
+#[derive(Clone, Debug, Default)]
+pub struct Ruler {
+    points: Vec<RulerPoint>,
+}
+
+impl Ruler {
+    pub fn click(&mut self, point: RulerPoint) {
+        if self.points.len() == 2 {
+            self.points.clear();
+        }
+        self.points.push(point);
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn points(&self) -> &[RulerPoint] {
+        &self.points
+    }
+
+    /// World space distance once both points are placed, with `resolve`
+    /// returning the current position of a point.
+    pub fn distance(&self, resolve: impl Fn(RulerPoint) -> Option<Vector3<f32>>) -> Option<f32> {
+        match self.points[..] {
+            [a, b] => Some((resolve(b)? - resolve(a)?).magnitude()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use wgpu_bootstrap::cgmath::Vector3;
+
+/// A weighted set of fabric vertices.
+///
+/// Holds one weight per fabric vertex (`row * grid_cols + col`), `0.0` meaning
+/// "not selected". Selections are built with the `InstanceApp::select_*` methods,
+/// can be combined, and are handed back to the `InstanceApp::*_selection`
+/// operations, which upload the weights to the GPU and apply the operation in a
+/// compute pass. Operations that blend (impulse, recolor) scale by the weight.
+/// The operations apply to the first cloth and need a selection over its grid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection {
+    weights: Vec<f32>,
+}
+
+impl Selection {
+    pub fn empty(vertex_count: usize) -> Self {
+        Selection {
+            weights: vec![0.0; vertex_count],
+        }
+    }
+
+    /// Vertices inside the `rows` x `cols` rectangle of a `grid_rows` x `grid_cols` grid.
+    pub fn rect(grid_rows: u32, grid_cols: u32, rows: Range<u32>, cols: Range<u32>) -> Self {
+        let mut selection = Self::empty((grid_rows * grid_cols) as usize);
+        for row in rows.start..rows.end.min(grid_rows) {
+            for col in cols.start..cols.end.min(grid_cols) {
+                selection.weights[(row * grid_cols + col) as usize] = 1.0;
+            }
+        }
+        selection
+    }
+
+    /// The given vertex indices, out of range indices are ignored.
+    pub fn indices(vertex_count: usize, indices: &[u32]) -> Self {
+        let mut selection = Self::empty(vertex_count);
+        for &index in indices {
+            if let Some(weight) = selection.weights.get_mut(index as usize) {
+                *weight = 1.0;
+            }
+        }
+        selection
+    }
+
+    /// Vertices whose position lies in the axis aligned box `min..=max`.
+    pub fn region(positions: &[Vector3<f32>], min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        let weights = positions
+            .iter()
+            .map(|p| {
+                let inside = p.x >= min.x && p.x <= max.x
+                    && p.y >= min.y && p.y <= max.y
+                    && p.z >= min.z && p.z <= max.z;
+                if inside { 1.0 } else { 0.0 }
+            })
+            .collect();
+        Selection { weights }
+    }
+
+    /// Same selection with every weight multiplied by `factor`.
+    pub fn weighted(mut self, factor: f32) -> Self {
+        for weight in &mut self.weights {
+            *weight *= factor;
+        }
+        self
+    }
+
+    /// Per-vertex maximum of both selections. Panics if they don't cover the
+    /// same number of vertices.
+    pub fn union(&self, other: &Selection) -> Self {
+        self.assert_same_len(other);
+        let weights = self.weights.iter().zip(&other.weights).map(|(a, b)| a.max(*b)).collect();
+        Selection { weights }
+    }
+
+    /// Per-vertex minimum of both selections. Panics if they don't cover the
+    /// same number of vertices.
+    pub fn intersection(&self, other: &Selection) -> Self {
+        self.assert_same_len(other);
+        let weights = self.weights.iter().zip(&other.weights).map(|(a, b)| a.min(*b)).collect();
+        Selection { weights }
+    }
+
+    fn assert_same_len(&self, other: &Selection) {
+        assert_eq!(
+            self.weights.len(),
+            other.weights.len(),
+            "selections over {} and {} vertices can't be combined",
+            self.weights.len(),
+            other.weights.len()
+        );
+    }
+
+    pub fn contains(&self, index: u32) -> bool {
+        self.weights.get(index as usize).is_some_and(|w| *w > 0.0)
+    }
+
+    /// Indices of the selected vertices.
+    pub fn indices_iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.weights.iter().enumerate().filter(|(_, w)| **w > 0.0).map(|(i, _)| i as u32)
+    }
+
+    pub fn count(&self) -> usize {
+        self.weights.iter().filter(|w| **w > 0.0).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    // One weight per vertex, as they are, for the operations that read the
+    // weight as a value rather than a blend factor
+    pub(crate) fn from_weights(weights: Vec<f32>) -> Self {
+        Selection { weights }
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 rows of 4 columns:
+    //   0  1  2  3
+    //   4  5  6  7
+    //   8  9 10 11
+    #[test]
+    fn rect_selects_the_rows_and_columns() {
+        let selection = Selection::rect(3, 4, 1..3, 1..3);
+        assert_eq!(selection.weights().len(), 12);
+        assert_eq!(selection.indices_iter().collect::<Vec<_>>(), vec![5, 6, 9, 10]);
+    }
+
+    #[test]
+    fn rect_is_clipped_to_the_grid() {
+        let selection = Selection::rect(3, 4, 2..10, 3..10);
+        assert_eq!(selection.indices_iter().collect::<Vec<_>>(), vec![11]);
+        assert!(Selection::rect(3, 4, 3..5, 0..4).is_empty());
+    }
+
+    #[test]
+    fn indices_ignores_out_of_range() {
+        let selection = Selection::indices(12, &[0, 7, 7, 12, 100]);
+        assert_eq!(selection.weights().len(), 12);
+        assert_eq!(selection.indices_iter().collect::<Vec<_>>(), vec![0, 7]);
+        assert_eq!(selection.count(), 2);
+    }
+
+    #[test]
+    fn region_includes_the_box_faces() {
+        let positions = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            Vector3::new(1.5, 0.5, 0.5),
+            Vector3::new(0.5, -0.1, 0.5),
+        ];
+        let selection = Selection::region(&positions, Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(selection.weights(), &[1.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn union_and_intersection_combine_the_weights() {
+        let a = Selection::indices(4, &[0, 1]).weighted(0.5);
+        let b = Selection::indices(4, &[1, 2]);
+        assert_eq!(a.union(&b).weights(), &[0.5, 1.0, 1.0, 0.0]);
+        assert_eq!(a.intersection(&b).weights(), &[0.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "selections over 4 and 12 vertices")]
+    fn union_of_different_grids_panics() {
+        Selection::empty(4).union(&Selection::empty(12));
+    }
+
+    #[test]
+    #[should_panic(expected = "selections over 12 and 4 vertices")]
+    fn intersection_of_different_grids_panics() {
+        Selection::empty(12).intersection(&Selection::empty(4));
+    }
+
+    #[test]
+    fn contains_needs_a_positive_weight() {
+        let selection = Selection::indices(4, &[1, 2]).union(&Selection::from_weights(vec![0.0, 0.0, 0.0, -1.0]));
+        assert!(!selection.contains(0));
+        assert!(selection.contains(1));
+        assert!(selection.contains(2));
+        assert!(!selection.contains(3));
+        assert!(!selection.contains(4));
+        assert!(!Selection::indices(4, &[1]).weighted(0.0).contains(1));
+    }
+}
@@ -3,23 +3,73 @@ struct CameraUniform {
     proj: mat4x4<f32>,
 };
 
+// Per-mesh shading parameters, the sphere uses one with the detail map disabled
+struct FabricMaterial {
+    grid: vec4<f32>,    // grid_rows, grid_cols, color_mode, textured
+    detail: vec4<f32>,  // detail_tiling, detail_strength, enabled, lighting
+    motion: vec4<f32>,  // blur_time, blur_opacity, blur_samples, unused
+    tint: vec4<f32>,    // flat fill color replacing the vertex color when a > 0
+    edge: vec4<f32>,    // color of the edge overlay
+    springs: vec4<f32>, // structural and shear rest length, unused x2
+    backface: vec4<f32>, // color multiplied into back faces when a > 0
+    rim: vec4<f32>,     // rim light color and strength, only the sphere has one
+};
+
 @group(0) @binding(0) var<uniform> camera: CameraUniform;
 
+@group(1) @binding(0) var<uniform> material: FabricMaterial;
+@group(1) @binding(1) var detail_normal_map: texture_2d<f32>;
+@group(1) @binding(2) var detail_sampler: sampler;
+// Colors of the debug color modes, written by the compute shader
+@group(1) @binding(3) var<storage, read> debug_colors: array<u32>;
+// Color texture stretched over the grid uv, sampled with the detail sampler
+@group(1) @binding(4) var fabric_texture: texture_2d<f32>;
+
+// ColorMode in instances_app.rs
+const COLOR_MODE_MATERIAL = 0u;
+
 struct VertexInput {
     @location(0) position: vec4<f32>,
     @location(1) color: vec4<f32>,
     @location(2) mass: f32,
     @location(3) velocity: vec4<f32>,
     @location(4) fixed: f32,
+    @location(5) normal: vec4<f32>,
 };
 
 struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
+    // Invariant so the depth pre-pass and the color pass compute the same depth
+    @builtin(position) @invariant clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) view_position: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) view_normal: vec3<f32>,
 };
 
+// Light from above and behind the viewer, in view space
+const LIGHT_DIRECTION = vec3<f32>(0.4, 0.8, 0.6);
+
 @vertex
-fn vs_main(model: VertexInput) -> VertexOutput {
+fn vs_main(model: VertexInput, @builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    return fabric_vertex(model, vertex_index, model.position);
+}
+
+// Unit sphere instance scaled and moved to a collider, center xyz and radius w.
+// For a capsule each half of the sphere moves out to its end of the axis and
+// the triangles across the cut stretch into the cylinder.
+@vertex
+fn vs_collider(
+    model: VertexInput,
+    @location(6) collider: vec4<f32>,
+    @location(7) half_axis: vec4<f32>,
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    let end = select(-1.0, 1.0, dot(model.position.xyz, half_axis.xyz) >= 0.0);
+    let position = vec4<f32>(model.position.xyz * collider.w + collider.xyz + half_axis.xyz * end, 1.0);
+    return fabric_vertex(model, vertex_index, position);
+}
+
+fn fabric_vertex(model: VertexInput, vertex_index: u32, position: vec4<f32>) -> VertexOutput {
     var out: VertexOutput;
 /*
     if (model.fixed == 1.0) {
@@ -29,11 +79,241 @@ fn vs_main(model: VertexInput) -> VertexOutput {
     }
 */
     out.color = model.color;
-    out.clip_position = camera.proj * camera.view * model.position;
+    if (material.tint.a > 0.0) {
+        out.color = vec4<f32>(material.tint.rgb, 1.0);
+    }
+    if (u32(material.grid.z) != COLOR_MODE_MATERIAL) {
+        out.color = unpack4x8unorm(debug_colors[vertex_index]);
+    }
+    out.clip_position = camera.proj * camera.view * position;
+    out.view_position = (camera.view * position).xyz;
+    out.view_normal = (camera.view * vec4<f32>(model.normal.xyz, 0.0)).xyz;
+
+    // Grid coordinates of the vertex, 0..1 across the cloth
+    let rows = max(u32(material.grid.x), 2u);
+    let cols = max(u32(material.grid.y), 2u);
+    out.uv = vec2<f32>(
+        f32(vertex_index % cols) / f32(cols - 1u),
+        f32(vertex_index / cols) / f32(rows - 1u),
+    );
+    return out;
+}
+
+// Depth, in clip space units, the edge overlay is pulled towards the camera so
+// it wins against the faces it lies on
+const EDGE_DEPTH_OFFSET = 2e-4;
+
+@vertex
+fn vs_edge(model: VertexInput, @builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out = fabric_vertex(model, vertex_index, model.position);
+    out.clip_position.z -= EDGE_DEPTH_OFFSET * out.clip_position.w;
+    return out;
+}
+
+@fragment
+fn fs_edge(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(material.edge.rgb, 1.0);
+}
+
+// The two ends of a spring, from the fabric vertex buffer bound twice, and the
+// intact springs of the start (SPRING_* in computeShader.wgsl)
+struct SpringEnds {
+    @location(0) start: vec4<f32>,
+    @location(1) end: vec4<f32>,
+    @location(2) springs: u32,
+};
+
+// Spring bit of each direction of vs_spring: right, down, down-right, down-left
+const SPRING_DIRECTION_BITS = array<u32, 4>(2u, 8u, 128u, 64u);
+
+// Springs are green at rest length and red once stretched by this factor
+const SPRING_STRETCH_LIMIT = 1.1;
+
+// Spring diagram, one instance per spring starting at vertex `start_index`.
+// The second vertex buffer binding is shifted to the neighbor, vertex_index / 2
+// is the direction (right, down, down-right, down-left) and vertex_index % 2
+// the end of the line.
+@vertex
+fn vs_spring(
+    ends: SpringEnds,
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) start_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let direction = vertex_index / 2u;
+    let rest_length = select(material.springs.y, material.springs.x, direction < 2u);
+    let stretch = length(ends.end.xyz - ends.start.xyz) / rest_length;
+    let strain = clamp((stretch - 1.0) / (SPRING_STRETCH_LIMIT - 1.0), 0.0, 1.0);
+    out.color = vec4<f32>(mix(vec3<f32>(0.1, 0.85, 0.2), vec3<f32>(0.95, 0.1, 0.1), strain), 1.0);
+
+    let position = vec4<f32>(select(ends.start.xyz, ends.end.xyz, vertex_index % 2u == 1u), 1.0);
+    out.clip_position = camera.proj * camera.view * position;
+    out.view_position = (camera.view * position).xyz;
+    out.uv = vec2<f32>(0.0);
+    out.view_normal = vec3<f32>(0.0);
+
+    // The right and down-right neighbors of the last column and the down-left
+    // one of the first column wrap to another row, move those lines out of view
+    // along with the torn springs
+    let cols = max(u32(material.grid.y), 2u);
+    let col = start_index % cols;
+    var direction_bits = SPRING_DIRECTION_BITS;
+    let torn = (ends.springs & direction_bits[direction]) == 0u;
+    if (torn || (col == cols - 1u && (direction == 0u || direction == 2u)) || (col == 0u && direction == 3u)) {
+        out.clip_position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+    }
+    return out;
+}
+
+@fragment
+fn fs_spring(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+
+struct ForceGlyph {
+    @location(0) position: vec4<f32>,
+    @location(1) force: vec3<f32>,
+};
+
+// Length of a force glyph per unit of force, and the longest one
+const FORCE_GLYPH_SCALE = 0.5;
+const FORCE_GLYPH_MAX = 0.4;
+
+// Force glyph, one instance per sampled fabric vertex: a line from the vertex
+// (vertex_index 0) along the net force of its last step (1)
+@vertex
+fn vs_force(glyph: ForceGlyph, @builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let tip = vertex_index % 2u == 1u;
+    var arrow = glyph.force * FORCE_GLYPH_SCALE;
+    let arrow_length = length(arrow);
+    if (arrow_length > FORCE_GLYPH_MAX) {
+        arrow *= FORCE_GLYPH_MAX / arrow_length;
+    }
+    let position = vec4<f32>(glyph.position.xyz + select(vec3<f32>(0.0), arrow, tip), 1.0);
+    out.color = select(vec4<f32>(1.0, 0.9, 0.2, 1.0), vec4<f32>(0.95, 0.2, 0.1, 1.0), tip);
+    out.clip_position = camera.proj * camera.view * position;
+    out.view_position = (camera.view * position).xyz;
+    out.uv = vec2<f32>(0.0);
+    out.view_normal = vec3<f32>(0.0);
+    return out;
+}
+
+// Fabric wireframe fallback, points and force glyphs, unlit vertex colors
+@fragment
+fn fs_flat(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+
+// Fullscreen triangle behind everything, filled with the material tint
+@vertex
+fn vs_background(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let corner = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 1.0, 1.0);
+    out.color = vec4<f32>(material.tint.rgb, 1.0);
+    out.view_position = vec3<f32>(0.0);
+    out.uv = corner;
+    out.view_normal = vec3<f32>(0.0);
     return out;
 }
 
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+fn fs_background(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color;
 }
+
+// Motion blur copy number `sample`, moved back along the velocity. Slow
+// vertices get a transparent copy so only the fast parts smear.
+@vertex
+fn vs_smear(
+    model: VertexInput,
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) sample: u32,
+) -> VertexOutput {
+    let t = f32(sample + 1u) / max(material.motion.z, 1.0);
+    let trail = model.velocity.xyz * material.motion.x;
+    var out = fabric_vertex(model, vertex_index, model.position - vec4<f32>(trail * t, 0.0));
+    let fade = (1.0 - t + 1.0 / max(material.motion.z, 1.0)) * smoothstep(0.0, 0.05, length(trail));
+    out.color = vec4<f32>(out.color.rgb, material.motion.y * fade);
+    return out;
+}
+
+@fragment
+fn fs_smear(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+
+// Tangent frame from the screen space gradients of the position and the uv,
+// the grid uv gives the natural warp/weft directions
+fn cotangent_frame(normal: vec3<f32>, position: vec3<f32>, uv: vec2<f32>) -> mat3x3<f32> {
+    let dp1 = dpdx(position);
+    let dp2 = dpdy(position);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
+
+    let dp2perp = cross(dp2, normal);
+    let dp1perp = cross(normal, dp1);
+    let tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    let bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+
+    let inv_max = inverseSqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+    return mat3x3<f32>(tangent * inv_max, bitangent * inv_max, normal);
+}
+
+// Depth pre-pass, the color target is masked out
+@fragment
+fn fs_depth() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    // The texture replaces the vertex color, but not the debug colors. Sampled
+    // before any branch, textureSample needs uniform control flow.
+    var color = in.color;
+    let texel = textureSample(fabric_texture, detail_sampler, in.uv);
+    if (material.grid.w > 0.5 && u32(material.grid.z) == COLOR_MODE_MATERIAL) {
+        color = vec4<f32>(texel.rgb, color.a);
+    }
+
+    // Back faces get their own tint so folds read even without lighting
+    if (!front_facing && material.backface.a > 0.0) {
+        color = vec4<f32>(color.rgb * material.backface.rgb, color.a);
+    }
+
+    let detail_enabled = material.detail.z > 0.5;
+    if (!detail_enabled && material.detail.w < 0.5) {
+        return color;
+    }
+
+    // Interpolated vertex normal in view space, flipped on back faces so both
+    // sides are lit
+    let position = in.view_position;
+    var normal = normalize(in.view_normal);
+    if (!front_facing) {
+        normal = -normal;
+    }
+
+    // Perturb it with the tiled detail normal map
+    var shading_normal = normal;
+    if (detail_enabled) {
+        let detail_uv = in.uv * material.detail.x;
+        let tbn = cotangent_frame(normal, position, detail_uv);
+        let sampled = textureSample(detail_normal_map, detail_sampler, detail_uv).xyz * 2.0 - 1.0;
+        let detail = normalize(vec3<f32>(sampled.xy * material.detail.y, sampled.z));
+        shading_normal = normalize(tbn * detail);
+    }
+
+    // Lambert term with some ambient so the unlit side stays readable
+    let diffuse = max(dot(shading_normal, normalize(LIGHT_DIRECTION)), 0.0);
+    var lit = color.rgb * (0.35 + 0.65 * diffuse);
+
+    // Rim light where the surface turns away from the viewer, outlines the
+    // silhouette so the sphere reads as round
+    if (material.rim.a > 0.0) {
+        let facing = max(dot(shading_normal, normalize(-position)), 0.0);
+        lit += material.rim.rgb * material.rim.a * pow(1.0 - facing, 3.0);
+    }
+    return vec4<f32>(lit, color.a);
+}
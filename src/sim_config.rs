@@ -0,0 +1,187 @@
+use std::fmt;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use wgpu_bootstrap::cgmath::Vector3;
+
+use crate::instances_app::{ClothPreset, Collider, FabricConfig, GridLimitError};
+
+/// Every tunable parameter of a single cloth scene, saved and loaded as JSON
+/// by `InstanceApp::save_config` and `InstanceApp::load_config`.
+///
+/// Unlike the `SimParams` uniforms it is laid out for people: one field per
+/// parameter, in the units of the `InstanceApp` setters, and every field
+/// optional in the file so older files still load: a missing field takes its
+/// default, where `None` keeps what the app was built with (see
+/// `ClothBuilder`). `InstanceApp::apply_config` packs it into the uniforms.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimConfig {
+    pub grid: FabricConfig,
+    pub preset: ClothPreset,
+    /// Jitter of the starting positions, see `ClothBuilder::seed`
+    pub seed: Option<u64>,
+    pub time_step: Option<f32>,
+    /// Structural (weft), shear and bending
+    pub stiffness: Option<[f32; 3]>,
+    /// Structural stiffness along the columns, the weft one when `None`
+    pub warp_stiffness: Option<f32>,
+    /// Structural, shear and bending, the grid spacing when `None`
+    pub rest_lengths: Option<[f32; 3]>,
+    pub gravity: Option<[f32; 3]>,
+    /// Wind xyz and turbulence, see `InstanceApp::set_wind`. The preset's
+    /// wind when `None`.
+    pub wind: Option<[f32; 4]>,
+    /// Global velocity damping (`k_spring`)
+    pub damping: Option<f32>,
+    pub settle_damping: f32,
+    pub air_drag: f32,
+    pub sleep_threshold: f32,
+    /// No collisions when empty
+    pub colliders: Option<Vec<ColliderConfig>>,
+    pub sphere_inverted: bool,
+    pub sticky_collision: bool,
+    pub sphere_friction: f32,
+    /// Bounciness of the colliders, see `InstanceApp::set_sphere_restitution`
+    pub sphere_restitution: Option<f32>,
+    pub contact_damping: f32,
+    /// Indices of the pinned vertices, the preset's pins when `None`
+    pub pinned: Option<Vec<u32>>,
+}
+
+/// A `Collider` with plain arrays, tagged by its `shape` in JSON.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum ColliderConfig {
+    Sphere { center: [f32; 3], radius: f32 },
+    Capsule { a: [f32; 3], b: [f32; 3], radius: f32 },
+}
+
+impl From<Collider> for ColliderConfig {
+    fn from(collider: Collider) -> Self {
+        match collider {
+            Collider::Sphere { center, radius } => ColliderConfig::Sphere { center: center.into(), radius },
+            Collider::Capsule { a, b, radius } => ColliderConfig::Capsule { a: a.into(), b: b.into(), radius },
+        }
+    }
+}
+
+impl From<ColliderConfig> for Collider {
+    fn from(config: ColliderConfig) -> Self {
+        match config {
+            ColliderConfig::Sphere { center, radius } => Collider::Sphere { center: Vector3::from(center), radius },
+            ColliderConfig::Capsule { a, b, radius } => Collider::Capsule { a: Vector3::from(a), b: Vector3::from(b), radius },
+        }
+    }
+}
+
+/// A configuration file that `InstanceApp::load_config` can't use.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Grid(GridLimitError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "can't read the configuration: {}", error),
+            ConfigError::Json(error) => write!(f, "invalid configuration: {}", error),
+            ConfigError::Grid(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> Self {
+        ConfigError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(error: serde_json::Error) -> Self {
+        ConfigError::Json(error)
+    }
+}
+
+impl From<GridLimitError> for ConfigError {
+    fn from(error: GridLimitError) -> Self {
+        ConfigError::Grid(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_json() {
+        let config = SimConfig {
+            grid: FabricConfig { rows: 20, cols: 30, side_length: 1.5, initial_height: 0.5 },
+            preset: ClothPreset::Flag,
+            seed: Some(7),
+            time_step: Some(0.002),
+            stiffness: Some([40.0, 10.0, 2.0]),
+            warp_stiffness: Some(60.0),
+            rest_lengths: Some([0.05, 0.07, 0.1]),
+            gravity: Some([0.0, -9.81, 0.0]),
+            wind: Some([1.0, 0.0, -0.5, 0.2]),
+            damping: Some(0.98),
+            settle_damping: 0.5,
+            air_drag: 0.1,
+            sleep_threshold: 0.01,
+            colliders: Some(vec![
+                ColliderConfig::Sphere { center: [0.0, 1.0, 0.0], radius: 0.5 },
+                ColliderConfig::Capsule { a: [-1.0, 0.0, 0.0], b: [1.0, 0.0, 0.0], radius: 0.25 },
+            ]),
+            sphere_inverted: true,
+            sticky_collision: true,
+            sphere_friction: 0.3,
+            sphere_restitution: Some(0.4),
+            contact_damping: 2.0,
+            pinned: Some(vec![0, 29]),
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<SimConfig>(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn empty_object_is_the_default() {
+        assert_eq!(serde_json::from_str::<SimConfig>("{}").unwrap(), SimConfig::default());
+    }
+
+    #[test]
+    fn missing_grid_fields_take_their_default() {
+        let config: SimConfig = serde_json::from_str(r#"{"grid": {"rows": 12}, "preset": "hammock"}"#).unwrap();
+        assert_eq!(config.grid, FabricConfig { rows: 12, ..FabricConfig::default() });
+        assert_eq!(config.preset, ClothPreset::Hammock);
+    }
+
+    #[test]
+    fn colliders_are_tagged_by_shape() {
+        let json = r#"[
+            {"shape": "sphere", "center": [0, 1, 0], "radius": 0.5},
+            {"shape": "capsule", "a": [-1, 0, 0], "b": [1, 0, 0], "radius": 0.25}
+        ]"#;
+        let colliders: Vec<ColliderConfig> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            colliders,
+            vec![
+                ColliderConfig::Sphere { center: [0.0, 1.0, 0.0], radius: 0.5 },
+                ColliderConfig::Capsule { a: [-1.0, 0.0, 0.0], b: [1.0, 0.0, 0.0], radius: 0.25 },
+            ]
+        );
+        let sphere = serde_json::to_value(colliders[0]).unwrap();
+        assert_eq!(sphere["shape"], "sphere");
+        assert!(serde_json::from_str::<ColliderConfig>(r#"{"shape": "cube", "radius": 1}"#).is_err());
+    }
+
+    #[test]
+    fn collider_conversion_round_trips() {
+        let config = ColliderConfig::Capsule { a: [0.0, 1.0, 2.0], b: [3.0, 4.0, 5.0], radius: 0.5 };
+        assert_eq!(ColliderConfig::from(Collider::from(config)), config);
+    }
+}
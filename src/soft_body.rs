@@ -0,0 +1,89 @@
+use std::collections::{BTreeSet, HashMap};
+
+use wgpu_bootstrap::cgmath::{InnerSpace, Vector3};
+
+/// Neighbors of every vertex along the edges of a triangle mesh.
+///
+/// Stored as compressed rows: the neighbors of vertex `i` are
+/// `neighbors[offsets[i]..offsets[i + 1]]`, sorted, so every edge shows up
+/// once from each end. This is the spring topology of the soft sphere, see
+/// `InstanceApp::set_soft_sphere`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EdgeAdjacency {
+    pub offsets: Vec<u32>,
+    pub neighbors: Vec<u32>,
+}
+
+impl EdgeAdjacency {
+    /// Edges of the triangle list `indices` over `vertex_count` vertices.
+    /// Triangles with an index out of range are skipped, and so are the
+    /// degenerate edges from a vertex to itself.
+    pub fn from_triangles(vertex_count: usize, indices: &[u32]) -> Self {
+        let mut edges = vec![BTreeSet::new(); vertex_count];
+        for triangle in indices.chunks_exact(3) {
+            if triangle.iter().any(|&index| index as usize >= vertex_count) {
+                continue;
+            }
+            for (a, b) in [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+                if a != b {
+                    edges[a as usize].insert(b);
+                    edges[b as usize].insert(a);
+                }
+            }
+        }
+        let mut adjacency = EdgeAdjacency {
+            offsets: Vec::with_capacity(vertex_count + 1),
+            neighbors: Vec::new(),
+        };
+        adjacency.offsets.push(0);
+        for neighbors in edges {
+            adjacency.neighbors.extend(neighbors);
+            adjacency.offsets.push(adjacency.neighbors.len() as u32);
+        }
+        adjacency
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn neighbors(&self, vertex: usize) -> &[u32] {
+        &self.neighbors[self.offsets[vertex] as usize..self.offsets[vertex + 1] as usize]
+    }
+
+    /// Each edge once.
+    pub fn edge_count(&self) -> usize {
+        self.neighbors.len() / 2
+    }
+}
+
+/// Merges the vertices of a triangle mesh that are within `tolerance` of each
+/// other, on a grid of that size, and remaps `indices` onto the survivors.
+///
+/// Meshes often repeat a vertex for every face around it, which would leave
+/// the faces unconnected in an `EdgeAdjacency`.
+pub fn weld(positions: &[Vector3<f32>], indices: &[u32], tolerance: f32) -> (Vec<Vector3<f32>>, Vec<u32>) {
+    let tolerance = tolerance.max(f32::EPSILON);
+    let mut cells: HashMap<[i32; 3], u32> = HashMap::new();
+    let mut welded = Vec::new();
+    let remap: Vec<u32> = positions
+        .iter()
+        .map(|position| {
+            let cell = [position.x, position.y, position.z].map(|value| (value / tolerance).round() as i32);
+            *cells.entry(cell).or_insert_with(|| {
+                welded.push(*position);
+                welded.len() as u32 - 1
+            })
+        })
+        .collect();
+    // Out of range indices stay out of range, `EdgeAdjacency` skips their triangles
+    let indices = indices.iter().map(|&index| remap.get(index as usize).copied().unwrap_or(u32::MAX)).collect();
+    (welded, indices)
+}
+
+/// Longest edge of `adjacency` between `positions`, 0 without edges.
+pub fn longest_edge(positions: &[Vector3<f32>], adjacency: &EdgeAdjacency) -> f32 {
+    (0..adjacency.vertex_count())
+        .flat_map(|vertex| adjacency.neighbors(vertex).iter().map(move |&neighbor| (positions[vertex] - positions[neighbor as usize]).magnitude()))
+        .fold(0.0, f32::max)
+}
@@ -0,0 +1,216 @@
+use std::mem::{offset_of, size_of};
+
+use wgpu_bootstrap::wgpu;
+
+/// Full precision fabric vertex, the layout of `Vertex` in computeShader.wgsl
+/// (112 bytes). The same struct fills the sphere, collider and ground buffers.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Vertex {
+    pub(crate) position: [f32; 4],    // 16 bytes (0-15)
+    pub(crate) color: [f32; 4],       // 16 bytes (16-31)
+    pub(crate) mass: f32,             // 4 bytes  (32-35)
+    pub(crate) force: [f32; 3],       // 12 bytes (36-47) net force of the last step, in the padding before velocity
+    pub(crate) velocity: [f32; 4],    // 16 bytes (48-63)
+    pub(crate) fixed: f32,            // 4 bytes  (64-67)
+    pub(crate) springs: u32,          // 4 bytes  (68-71)  intact springs, see INTACT_SPRINGS
+    pub(crate) grid: [u32; 2],        // 8 bytes  (72-79)  row and column in the cloth grid, see ColorMode::Grid
+    pub(crate) normal: [f32; 4],      // 16 bytes (80-95), written by cs_normals
+    pub(crate) pin_target: [f32; 4],  // 16 bytes (96-111) where a fixed vertex is held, w 1 once set, see cs_hold_pins
+}
+
+/// Half precision fabric vertex, see vertexStorageF16.wgsl (48 bytes).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct PackedVertex {
+    pub(crate) position: [u16; 4],    // 8 bytes  (0-7)   f16
+    pub(crate) velocity: [u16; 4],    // 8 bytes  (8-15)  f16
+    pub(crate) color: [u8; 4],        // 4 bytes  (16-19) unorm8
+    pub(crate) mass: f32,             // 4 bytes  (20-23)
+    pub(crate) fixed: f32,            // 4 bytes  (24-27)
+    pub(crate) normal: [i8; 4],       // 4 bytes  (28-31) snorm8
+    pub(crate) springs: u32,          // 4 bytes  (32-35)
+    pub(crate) grid: u32,             // 4 bytes  (36-39) row in the low 16 bits, column in the high ones
+    pub(crate) pin_target: [u16; 4],  // 8 bytes  (40-47) f16
+}
+
+// One bit per spring to the 8 neighbors (SPRING_* in computeShader.wgsl), a
+// bit is cleared for good when the spring tears
+pub(crate) const INTACT_SPRINGS: u32 = 0xff;
+
+impl Vertex {
+    pub(crate) fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                // Position
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // Color
+                wgpu::VertexAttribute {
+                    offset: 16,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // Mass
+                wgpu::VertexAttribute {
+                    offset: 32,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                // Velocity
+                wgpu::VertexAttribute {
+                    offset: 48,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // Fixed
+                wgpu::VertexAttribute {
+                    offset: 64,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                // Normal
+                wgpu::VertexAttribute {
+                    offset: 80,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+pub(crate) fn pack_half4(value: [f32; 4]) -> [u16; 4] {
+    value.map(|v| half::f16::from_f32(v).to_bits())
+}
+
+impl From<&Vertex> for PackedVertex {
+    fn from(vertex: &Vertex) -> Self {
+        PackedVertex {
+            position: pack_half4(vertex.position),
+            velocity: pack_half4(vertex.velocity),
+            color: vertex.color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+            mass: vertex.mass,
+            fixed: vertex.fixed,
+            normal: vertex.normal.map(|c| (c.clamp(-1.0, 1.0) * 127.0).round() as i8),
+            springs: vertex.springs,
+            grid: vertex.grid[0] | vertex.grid[1] << 16,
+            pin_target: pack_half4(vertex.pin_target),
+        }
+    }
+}
+
+fn unpack_half4(value: [u16; 4]) -> [f32; 4] {
+    value.map(|v| half::f16::from_bits(v).to_f32())
+}
+
+impl From<&PackedVertex> for Vertex {
+    fn from(packed: &PackedVertex) -> Self {
+        Vertex {
+            position: unpack_half4(packed.position),
+            color: packed.color.map(|c| c as f32 / 255.0),
+            mass: packed.mass,
+            force: [0.0; 3],
+            velocity: unpack_half4(packed.velocity),
+            fixed: packed.fixed,
+            springs: packed.springs,
+            grid: [packed.grid & 0xffff, packed.grid >> 16],
+            normal: packed.normal.map(|c| (c as f32 / 127.0).max(-1.0)),
+            pin_target: unpack_half4(packed.pin_target),
+        }
+    }
+}
+
+impl PackedVertex {
+    pub(crate) fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<PackedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                // Position
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float16x4,
+                },
+                // Color
+                wgpu::VertexAttribute {
+                    offset: 16,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Unorm8x4,
+                },
+                // Mass
+                wgpu::VertexAttribute {
+                    offset: 20,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                // Velocity
+                wgpu::VertexAttribute {
+                    offset: 8,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float16x4,
+                },
+                // Fixed
+                wgpu::VertexAttribute {
+                    offset: 24,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                // Normal
+                wgpu::VertexAttribute {
+                    offset: 28,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Snorm8x4,
+                },
+            ],
+        }
+    }
+}
+
+// Offsets of the intact springs, read by the spring diagram pipeline
+pub(crate) const SPRINGS_OFFSET: wgpu::BufferAddress = offset_of!(Vertex, springs) as wgpu::BufferAddress;
+pub(crate) const PACKED_SPRINGS_OFFSET: wgpu::BufferAddress = offset_of!(PackedVertex, springs) as wgpu::BufferAddress;
+// Offsets of the velocity and the pin flag, written one vertex at a time while
+// the mouse holds it
+pub(crate) const VELOCITY_OFFSET: wgpu::BufferAddress = offset_of!(Vertex, velocity) as wgpu::BufferAddress;
+pub(crate) const PACKED_VELOCITY_OFFSET: wgpu::BufferAddress = offset_of!(PackedVertex, velocity) as wgpu::BufferAddress;
+// Offset of the net force, drawn by the force glyphs. Full precision only, the
+// packed layout has no room for it
+pub(crate) const FORCE_OFFSET: wgpu::BufferAddress = offset_of!(Vertex, force) as wgpu::BufferAddress;
+pub(crate) const FIXED_OFFSET: wgpu::BufferAddress = offset_of!(Vertex, fixed) as wgpu::BufferAddress;
+pub(crate) const PACKED_FIXED_OFFSET: wgpu::BufferAddress = offset_of!(PackedVertex, fixed) as wgpu::BufferAddress;
+// Offsets of the pin target, moved along with the position of a pin
+pub(crate) const PIN_TARGET_OFFSET: wgpu::BufferAddress = offset_of!(Vertex, pin_target) as wgpu::BufferAddress;
+pub(crate) const PACKED_PIN_TARGET_OFFSET: wgpu::BufferAddress = offset_of!(PackedVertex, pin_target) as wgpu::BufferAddress;
+
+// The shaders and `desc` hard-code these offsets, catch a field moved by mistake
+const _: () = {
+    assert!(size_of::<Vertex>() == 112);
+    assert!(offset_of!(Vertex, position) == 0);
+    assert!(offset_of!(Vertex, color) == 16);
+    assert!(offset_of!(Vertex, mass) == 32);
+    assert!(offset_of!(Vertex, force) == 36);
+    assert!(offset_of!(Vertex, velocity) == 48);
+    assert!(offset_of!(Vertex, fixed) == 64);
+    assert!(offset_of!(Vertex, springs) == 68);
+    assert!(offset_of!(Vertex, grid) == 72);
+    assert!(offset_of!(Vertex, normal) == 80);
+    assert!(offset_of!(Vertex, pin_target) == 96);
+
+    assert!(size_of::<PackedVertex>() == 48);
+    assert!(offset_of!(PackedVertex, position) == 0);
+    assert!(offset_of!(PackedVertex, velocity) == 8);
+    assert!(offset_of!(PackedVertex, color) == 16);
+    assert!(offset_of!(PackedVertex, mass) == 20);
+    assert!(offset_of!(PackedVertex, fixed) == 24);
+    assert!(offset_of!(PackedVertex, normal) == 28);
+    assert!(offset_of!(PackedVertex, springs) == 32);
+    assert!(offset_of!(PackedVertex, grid) == 36);
+    assert!(offset_of!(PackedVertex, pin_target) == 40);
+};
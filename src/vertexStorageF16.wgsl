@@ -0,0 +1,79 @@
+// Half precision storage: position and velocity are packed as four f16 each,
+// the color as unorm8x4 and the normal as snorm8x4 (48 bytes per vertex
+// instead of 112). The solver still works on the unpacked f32 `Vertex`, only
+// loads and stores go through here.
+
+struct PackedVertex {
+    position: vec2<u32>,  // 4 x f16
+    velocity: vec2<u32>,  // 4 x f16
+    color: u32,           // unorm8x4
+    mass: f32,
+    fixed: f32,
+    normal: u32,          // snorm8x4
+    springs: u32,
+    grid: u32,            // row in the low 16 bits, column in the high ones
+    pin_target: vec2<u32>,  // 4 x f16
+}
+
+@group(0) @binding(0) var<storage, read_write> vertices: array<PackedVertex>;
+// State before the integration step, see `load_previous_vertex`
+@group(0) @binding(8) var<storage, read> previous_vertices: array<PackedVertex>;
+
+// Vertices in the bound buffer, for the soft sphere passes that don't see the
+// parameters. The cloth passes use `vertex_count` instead.
+fn vertex_buffer_length() -> u32 {
+    return arrayLength(&vertices);
+}
+
+fn unpack_half4(packed: vec2<u32>) -> vec4<f32> {
+    return vec4<f32>(unpack2x16float(packed.x), unpack2x16float(packed.y));
+}
+
+fn pack_half4(value: vec4<f32>) -> vec2<u32> {
+    return vec2<u32>(pack2x16float(value.xy), pack2x16float(value.zw));
+}
+
+fn unpack_vertex(packed: PackedVertex) -> Vertex {
+    return Vertex(
+        unpack_half4(packed.position),
+        unpack4x8unorm(packed.color),
+        packed.mass,
+        array<f32, 3>(),  // the force isn't stored
+        unpack_half4(packed.velocity),
+        packed.fixed,
+        packed.springs,
+        vec2<u32>(packed.grid & 0xffffu, packed.grid >> 16u),
+        unpack4x8snorm(packed.normal),
+        unpack_half4(packed.pin_target)
+    );
+}
+
+fn load_vertex(index: u32) -> Vertex {
+    return unpack_vertex(vertices[index]);
+}
+
+fn load_previous_vertex(index: u32) -> Vertex {
+    return unpack_vertex(previous_vertices[index]);
+}
+
+fn load_position(index: u32) -> vec3<f32> {
+    return unpack_half4(vertices[index].position).xyz;
+}
+
+fn store_vertex(index: u32, vertex: Vertex) {
+    vertices[index] = PackedVertex(
+        pack_half4(vertex.position),
+        pack_half4(vertex.velocity),
+        pack4x8unorm(vertex.color),
+        vertex.mass,
+        vertex.fixed,
+        pack4x8snorm(vertex.normal),
+        vertex.springs,
+        vertex.grid.x | (vertex.grid.y << 16u),
+        pack_half4(vertex.pin_target)
+    );
+}
+
+fn store_normal(index: u32, normal: vec4<f32>) {
+    vertices[index].normal = pack4x8snorm(normal);
+}
@@ -0,0 +1,31 @@
+// Full precision storage: the buffer holds `Vertex` as-is (112 bytes each).
+
+@group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
+// State before the integration step, see `load_previous_vertex`
+@group(0) @binding(8) var<storage, read> previous_vertices: array<Vertex>;
+
+// Vertices in the bound buffer, for the soft sphere passes that don't see the
+// parameters. The cloth passes use `vertex_count` instead.
+fn vertex_buffer_length() -> u32 {
+    return arrayLength(&vertices);
+}
+
+fn load_vertex(index: u32) -> Vertex {
+    return vertices[index];
+}
+
+fn load_previous_vertex(index: u32) -> Vertex {
+    return previous_vertices[index];
+}
+
+fn load_position(index: u32) -> vec3<f32> {
+    return vertices[index].position.xyz;
+}
+
+fn store_vertex(index: u32, vertex: Vertex) {
+    vertices[index] = vertex;
+}
+
+fn store_normal(index: u32, normal: vec4<f32>) {
+    vertices[index].normal = normal;
+}
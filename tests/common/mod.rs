@@ -0,0 +1,11 @@
+use cloth_sim::gpu_context::HeadlessContext;
+
+/// A headless device for the test, `None` (and a note on stderr) on a machine
+/// without a GPU adapter, in which case the test passes without running.
+pub fn headless_context() -> Option<HeadlessContext> {
+    let context = HeadlessContext::new(800, 600);
+    if context.is_none() {
+        eprintln!("skipped: no GPU adapter");
+    }
+    context
+}
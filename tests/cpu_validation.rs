@@ -0,0 +1,24 @@
+mod common;
+
+use cloth_sim::instances_app::{ClothBuilder, Precision};
+
+// One step of a 10x10 cloth on the GPU against `cpu_step`, from rest and again
+// once it is falling and the springs are stretched
+#[test]
+fn gpu_step_matches_the_cpu_reference() {
+    let Some(context) = common::headless_context() else {
+        return;
+    };
+    for precision in [Precision::F32, Precision::F16] {
+        let mut app = ClothBuilder::new().grid_size(10).precision(precision).try_build(&context).unwrap();
+        let (position_tolerance, velocity_tolerance) = precision.validation_tolerance();
+        let comparison = app.validate_step(&context);
+        assert!(comparison.within(position_tolerance, velocity_tolerance), "{:?} at rest: {:?}", precision, comparison);
+
+        for _ in 0..30 {
+            app.advance(1.0 / 60.0, &context);
+        }
+        let comparison = app.validate_step(&context);
+        assert!(comparison.within(position_tolerance, velocity_tolerance), "{:?} falling: {:?}", precision, comparison);
+    }
+}
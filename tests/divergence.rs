@@ -0,0 +1,49 @@
+mod common;
+
+use cloth_sim::instances_app::ClothBuilder;
+
+// Frames the headless run gets to collect every sample, the readbacks lag a
+// frame or two behind the simulation
+const FRAME_BUDGET: u32 = 200;
+
+#[test]
+fn divergence_run_samples_both_cloths_asynchronously() {
+    let Some(context) = common::headless_context() else {
+        return;
+    };
+    let mut app = ClothBuilder::new().grid_size(16).try_build(&context).unwrap();
+    // Let the cloth start moving so the nudge has something to grow with
+    for _ in 0..20 {
+        app.advance(1.0 / 60.0, &context);
+    }
+    app.start_divergence_run(&context, 8 * 16 + 8, 1e-3, 60);
+    for _ in 0..FRAME_BUDGET {
+        app.advance(1.0 / 60.0, &context);
+        if app.divergence_run().is_some_and(|run| run.is_done()) {
+            break;
+        }
+    }
+
+    let run = app.divergence_run().expect("the run was started");
+    assert!(run.is_done(), "only {} of {} frames sampled", run.last_frame(), run.frames);
+    let samples = run.samples();
+    assert!(samples.windows(2).all(|pair| pair[0].0 < pair[1].0), "frames out of order: {:?}", samples);
+    assert!(samples.iter().all(|(_, divergence)| divergence.is_finite() && *divergence > 0.0), "{:?}", samples);
+    // Most frames get a sample even though none of them waits for the GPU
+    assert!(samples.len() * 3 >= run.frames as usize, "{} samples over {} frames", samples.len(), run.frames);
+
+    let mut csv = Vec::new();
+    run.write_csv(&mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap().lines().count(), samples.len() + 1);
+}
+
+#[test]
+fn divergence_csv_needs_a_run() {
+    let Some(context) = common::headless_context() else {
+        return;
+    };
+    let app = ClothBuilder::new().grid_size(8).try_build(&context).unwrap();
+    let path = std::env::temp_dir().join("cloth_sim_divergence_test.csv");
+    let error = app.export_divergence_csv(&path).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
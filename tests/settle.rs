@@ -0,0 +1,29 @@
+mod common;
+
+use cloth_sim::instances_app::ClothBuilder;
+
+// Frames of 1/60 s the default cloth gets to fall onto the sphere and come to
+// rest, about 2800 with the damping below
+const FRAME_BUDGET: u32 = 3600;
+
+// Drops the default cloth onto the sphere, over the floor the panel's "Ground
+// plane" checkbox adds. Without the floor the corners slide down forever, and
+// the vertices resting against the sphere keep about 0.07 units per second
+// from its contact response, so the threshold sits above that
+#[test]
+fn default_cloth_settles_on_the_sphere() {
+    let Some(context) = common::headless_context() else {
+        return;
+    };
+    let mut app = ClothBuilder::new().try_build(&context).unwrap();
+    app.set_ground_plane(-1.5, 0.2);
+    app.set_settle_damping(5.0);
+    app.set_settle_threshold(0.1, 30);
+    assert!(!app.is_settled());
+
+    let settled = (0..FRAME_BUDGET).any(|_| {
+        app.advance(1.0 / 60.0, &context);
+        app.is_settled()
+    });
+    assert!(settled, "not settled after {} frames: {:?}", FRAME_BUDGET, app.last_stats());
+}